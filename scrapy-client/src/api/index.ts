@@ -1,4 +1,4 @@
-import { ScrapeSchema, ScrapingResult } from "@/types";
+import { ApiError, CrawlResponse, FieldError, ScrapeParams } from "@/types";
 import axios, { AxiosError, AxiosInstance, AxiosResponse } from "axios";
 
 // Define the base URL for your API
@@ -12,10 +12,15 @@ const apiClient: AxiosInstance = axios.create({
   },
 });
 
+// API key for servers with authentication enabled
+const API_KEY: string | undefined = import.meta.env.VITE_SCRAPY_API_KEY;
+
 // Request interceptor
 apiClient.interceptors.request.use(
   (config) => {
-    // You can modify the request config here (e.g., add authentication tokens)
+    if (API_KEY) {
+      config.headers.Authorization = `Bearer ${API_KEY}`;
+    }
     return config;
   },
   (error: AxiosError) => {
@@ -46,6 +51,20 @@ apiClient.interceptors.response.use(
   },
 );
 
+// Prefer the server's error message, which names the failing request
+const errorMessage = (error: AxiosError<ApiError>, fallback: string) => {
+  const body = error.response?.data;
+  if (!body?.message) {
+    return fallback;
+  }
+  const details = body.details as { fields?: FieldError[] } | null | undefined;
+  const fields = details?.fields;
+  const message = fields
+    ? fields.map(({ field, message }) => `${field}: ${message}`).join("\n")
+    : body.message;
+  return `${message} (request ${body.requestId})`;
+};
+
 const api = {
   getModels: async () => {
     try {
@@ -54,21 +73,38 @@ const api = {
     } catch (error) {
       if (error instanceof AxiosError) {
         throw new Error(
-          error.response?.data?.message ||
-            "An error occurred while fetching models",
+          errorMessage(error, "An error occurred while fetching models"),
         );
       }
       throw error;
     }
   },
-  crawl: async (params: ScrapeSchema) => {
+  crawl: async (params: ScrapeParams) => {
+    try {
+      const response = await apiClient.post<CrawlResponse>("/crawl", params);
+      return response.data;
+    } catch (error) {
+      if (error instanceof AxiosError) {
+        throw new Error(errorMessage(error, "An error occurred while crawling"));
+      }
+      throw error;
+    }
+  },
+  crawlUpload: async (params: ScrapeParams, file: File) => {
     try {
-      const response = await apiClient.post<ScrapingResult[]>("/crawl", params);
+      const form = new FormData();
+      form.append("params", JSON.stringify(params));
+      form.append("file", file);
+      const response = await apiClient.post<CrawlResponse>(
+        "/crawl/upload",
+        form,
+        { headers: { "Content-Type": "multipart/form-data" } },
+      );
       return response.data;
     } catch (error) {
       if (error instanceof AxiosError) {
         throw new Error(
-          error.response?.data?.message || "An error occurred while crawling",
+          errorMessage(error, "An error occurred while uploading the URL list"),
         );
       }
       throw error;
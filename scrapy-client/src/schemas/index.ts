@@ -1,5 +1,15 @@
+import type * as Api from "@/types/api";
 import { z } from "zod";
 
+/**
+ * A zod schema for every field of `T`, a type generated from the server's
+ * models. Checked with `satisfies`, so a field the API does not accept, or
+ * one of the wrong type, fails to compile.
+ */
+type SchemaFor<T> = {
+  [K in keyof T]-?: z.ZodType<T[K], z.ZodTypeDef, unknown>;
+};
+
 // Common schemas
 const MetadataSchema = z.record(z.unknown()).nullable();
 
@@ -22,7 +32,7 @@ export const MessageTypeSchema = z.enum([
   "progress",
   "scrapingResult",
   "raw",
-]);
+]) satisfies z.ZodType<Api.MessageType>;
 
 export const IncomingMessageSchema = z.object({
   type: MessageTypeSchema,
@@ -63,15 +73,31 @@ export const ScrapingResultMessageSchema = IncomingMessageSchema.extend({
 });
 
 // Main schemas
-export const scrapeSchema = z.object({
+const scrapeFields = {
   model: z.string().min(1, "Please select a model"),
-  apiKey: z.string().min(1, "Please enter your API key"),
+  // Optional when the server has a key or a stored credential is named
+  apiKey: z.string().default(""),
+  credential: z.string().optional(),
   url: z.string().min(1, "Please enter a URL"),
   enableScraping: z.boolean(),
   tags: z.array(z.string()).default([]),
   enablePagination: z.boolean(),
   paginationDetails: z.string().optional(),
-});
+} satisfies SchemaFor<
+  Pick<
+    Api.ScrapeParams,
+    | "model"
+    | "apiKey"
+    | "credential"
+    | "url"
+    | "enableScraping"
+    | "tags"
+    | "enablePagination"
+    | "paginationDetails"
+  >
+>;
+
+export const scrapeSchema = z.object(scrapeFields);
 
 export const ConnectionStatusSchema = z.enum([
   "Connecting",
@@ -0,0 +1,528 @@
+// Generated from the server's OpenAPI document by `make api-types`.
+// Do not edit: change the Rust models and regenerate instead.
+
+/** The JSON body of every failed API request. */
+export interface ApiError {
+  /** Stable, machine-readable error code such as `quota_exceeded`. */
+  code: string;
+  details?: unknown;
+  message: string;
+  requestId: string;
+}
+
+export interface BrowserOptions {
+  maxScrolls?: number;
+  scrollToBottom?: boolean;
+  timeoutMs?: number;
+  waitForNetworkIdle?: boolean;
+  waitForSelector?: string | null;
+}
+
+/**
+ * Limits on AI spending for a crawl. Pages left once a limit is reached are
+ * fetched but not sent for extraction. Extractions reserve their worst case
+ * usage up front, so concurrent ones cannot overspend together.
+ */
+export interface BudgetConfig {
+  maxCostUsd?: number | null;
+  maxTokens?: number | null;
+}
+
+export interface BudgetSpec {
+  maxCostUsd?: number | null;
+  maxPages?: number | null;
+  maxTokens?: number | null;
+}
+
+export interface BudgetStats {
+  costUsd: number;
+  /** Pages not sent for extraction because the budget was used up. */
+  skippedExtractions: number;
+  tokens: number;
+}
+
+export interface CacheStats {
+  aiHits: number;
+  aiMisses: number;
+  httpHits: number;
+  httpMisses: number;
+  httpRevalidated: number;
+}
+
+export interface CanonicalConfig {
+  honorCanonicalTag?: boolean;
+  ignoreScheme?: boolean;
+  ignoreTrailingSlash?: boolean;
+  sortQuery?: boolean;
+  /** Query parameters to drop; a trailing `*` matches by prefix. */
+  stripParams?: string[];
+}
+
+export interface CrawlResponse {
+  budget: BudgetStats;
+  cache: CacheStats;
+  dedup: DedupStats;
+  diff?: JobDiff | null;
+  frontier: FrontierStats;
+  pages: PageReport[];
+  results: ScrapingResult[];
+}
+
+/** A stored credential as shown to API clients, without its value. */
+export interface Credential {
+  createdAt: string;
+  name: string;
+  owner?: string | null;
+  provider: string;
+  updatedAt: string;
+}
+
+/** Body of `PUT /credentials/<name>`. */
+export interface CredentialRequest {
+  apiKey: string;
+  provider?: string;
+}
+
+export interface DedupConfig {
+  enabled?: boolean;
+  /** Maximum SimHash Hamming distance for two pages to count as near duplicates. */
+  maxDistance?: number;
+  nearDuplicates?: boolean;
+}
+
+export interface DedupDecision {
+  distance: number;
+  duplicateOf: string;
+  kind: DuplicateKind;
+  url: string;
+}
+
+export interface DedupStats {
+  decisions: DedupDecision[];
+  exactDuplicates: number;
+  nearDuplicates: number;
+  unique: number;
+}
+
+export type DuplicateKind = "exact" | "near";
+
+export type ExportFormat = "json" | "jsonl" | "csv";
+
+export interface ExtractionSpec {
+  /** Stored credential holding the AI provider key. */
+  credential?: string | null;
+  dedup?: DedupConfig;
+  enabled?: boolean;
+  /** Information to extract from each page. */
+  fields?: string[];
+  model?: string;
+  /** JSON schema the extracted records should follow. */
+  schema?: unknown;
+}
+
+export type FailureKind = "dns" | "connect" | "timeout" | "tls" | "clientError" | "serverError" | "blocked" | "other";
+
+/**
+ * HTTP settings of a crawl request. Unset fields keep the server's value;
+ * `headers` are added to the server's headers.
+ */
+export interface FetchOverrides {
+  connectTimeoutMs?: number | null;
+  headers?: Record<string, string>;
+  maxRedirects?: number | null;
+  maxResponseBytes?: number | null;
+  persistCookies?: boolean | null;
+  proxies?: string[];
+  readTimeoutMs?: number | null;
+  userAgent?: string | null;
+  /** User agents to rotate through, one per request. */
+  userAgentPool?: string[];
+}
+
+export interface FetchSpec {
+  bypassCache?: boolean;
+  fetcher?: FetcherConfig;
+  http?: FetchOverrides | null;
+  ignoreRobotsTxt?: boolean;
+  rateLimit?: RateLimitConfig | null;
+  retry?: RetryConfig | null;
+  session?: SessionConfig | null;
+}
+
+export type FetcherConfig = {
+  backend: "http";
+} | (BrowserOptions & {
+  backend: "browser";
+});
+
+export interface FieldChange {
+  after: unknown;
+  before: unknown;
+}
+
+export interface FrontierConfig {
+  followLinks?: boolean;
+  maxDepth?: number | null;
+  maxPages?: number | null;
+  maxPagesPerDomain?: number | null;
+  paginationBoost?: number;
+  priorityPatterns?: PriorityPattern[];
+}
+
+export interface FrontierStats {
+  depthDistribution: Record<string, number>;
+  duplicates: number;
+  peakSize: number;
+  pending: number;
+  queued: number;
+  skippedDomainCap: number;
+  skippedMaxDepth: number;
+  skippedMaxPages: number;
+}
+
+export type InputUrl = string | {
+  row: number;
+  url: string;
+};
+
+export interface JobDiff {
+  addedPages: string[];
+  changedPages: PageDiff[];
+  previousRunId?: string | null;
+  removedPages: string[];
+  runId: string;
+  unchangedPages: number;
+}
+
+/** One crawl, recorded against the user who started it. */
+export interface JobRecord {
+  costUsd: number;
+  error?: string | null;
+  finishedAt?: string | null;
+  id: string;
+  jobId?: string | null;
+  /** `None` for crawls started without authentication. */
+  owner?: string | null;
+  pages: number;
+  results: number;
+  startUrls: number;
+  startedAt: string;
+  status: JobStatus;
+  tokens: number;
+}
+
+/**
+ * A crawl described as a YAML, TOML or JSON file so it can be reviewed and
+ * versioned. The spec name doubles as the job id, so every run is diffed
+ * against the previous one.
+ */
+export interface JobSpec {
+  budget?: BudgetSpec;
+  description?: string | null;
+  extraction?: ExtractionSpec;
+  fetch?: FetchSpec;
+  /** Skip extraction for pages unchanged since the previous run. */
+  incremental?: boolean;
+  links?: LinkSpec;
+  name: string;
+  /**
+   * Files `scrapy-cli --job` writes the records to. Runs started through
+   * the API return the records in the response and write no files.
+   */
+  outputs?: OutputSpec[];
+  /** Record field used to match records between runs. */
+  recordKey?: string | null;
+  seeds: SeedSpec;
+  version: number;
+  webhooks?: WebhookConfig[];
+}
+
+export type JobStatus = "running" | "succeeded" | "failed";
+
+export interface LinkSpec {
+  canonical?: CanonicalConfig;
+  follow?: boolean;
+  maxDepth?: number | null;
+  maxPagesPerDomain?: number | null;
+  paginationBoost?: number;
+  priorityPatterns?: PriorityPattern[];
+}
+
+export type MessageType = "progress" | "raw" | "scrapingResult" | "error" | "success" | "warning";
+
+export interface OutputSpec {
+  format: ExportFormat;
+  path: string;
+}
+
+/** What to do when a schedule fires while its previous run is still going. */
+export type OverlapPolicy = "skip" | "queue";
+
+export interface PageDiff {
+  addedRecords: unknown[];
+  changedRecords: RecordDiff[];
+  removedRecords: unknown[];
+  url: string;
+}
+
+export interface PageReport {
+  attempts: number;
+  /**
+   * Page this one was skipped in favour of, through `rel=canonical` or
+   * duplicate detection.
+   */
+  duplicateOf?: string | null;
+  failure?: FailureKind | null;
+  httpStatus?: number | null;
+  reason?: string | null;
+  status: PageStatus;
+  url: string;
+}
+
+export type PageStatus = "scraped" | "failed" | "disallowedByRobots";
+
+export interface PaginationInfo {
+  pageUrls: string[];
+  tokenCounts: UsageMetadata;
+}
+
+export interface PriorityPattern {
+  boost: number;
+  pattern: string;
+}
+
+export interface RateLimitConfig {
+  burst?: number;
+  maxBackoffSecs?: number;
+  maxConcurrentPerHost?: number;
+  requestsPerSecond?: number;
+}
+
+export interface RecordDiff {
+  fields: Record<string, FieldChange>;
+  /** Value of the configured record key, or the record's position on the page. */
+  key: string;
+}
+
+export interface RetryConfig {
+  baseDelayMs?: number;
+  maxDelayMs?: number;
+  maxRetries?: number;
+}
+
+export type RunTrigger = "scheduled" | "manual";
+
+export interface Schedule {
+  createdAt: string;
+  enabled: boolean;
+  id: string;
+  name: string;
+  nextRunAt?: string | null;
+  overlap: OverlapPolicy;
+  /**
+   * User the scheduled crawls run for; `None` when created without
+   * authentication.
+   */
+  owner?: string | null;
+  params: unknown;
+  running: boolean;
+  trigger: ScheduleTrigger;
+  updatedAt: string;
+}
+
+/**
+ * Body of the create and update schedule routes. `params` is a crawl
+ * request as accepted by `POST /crawl`, except that it may not carry an
+ * `apiKey`: schedules name a stored `credential` instead.
+ */
+export interface ScheduleRequest {
+  enabled?: boolean;
+  name: string;
+  overlap?: OverlapPolicy;
+  params: unknown;
+  trigger: ScheduleTrigger;
+}
+
+export interface ScheduleRun {
+  error?: string | null;
+  finishedAt?: string | null;
+  id: string;
+  pages: number;
+  results: number;
+  scheduleId: string;
+  startedAt: string;
+  status: ScheduleRunStatus;
+  trigger: RunTrigger;
+}
+
+export type ScheduleRunStatus = "running" | "succeeded" | "failed" | "skipped";
+
+/**
+ * When a schedule fires. Cron expressions are evaluated in UTC and accept
+ * either the standard five fields or a leading seconds field.
+ */
+export type ScheduleTrigger = {
+  expression: string;
+  type: "cron";
+} | {
+  everySecs: number;
+  type: "interval";
+};
+
+export interface ScrapeParams {
+  /** Falls back to the server's `ai.api_key` when empty. */
+  apiKey?: string;
+  budget?: BudgetConfig;
+  bypassCache?: boolean;
+  canonical?: CanonicalConfig;
+  /** Name of a stored credential to use instead of `api_key`. */
+  credential?: string | null;
+  dedup?: DedupConfig;
+  enablePagination: boolean;
+  enableScraping: boolean;
+  fetch?: FetchOverrides | null;
+  fetcher?: FetcherConfig;
+  frontier?: FrontierConfig;
+  ignoreRobotsTxt?: boolean;
+  incremental?: boolean;
+  jobId?: string | null;
+  /** Falls back to the server's `ai.default_model` when empty. */
+  model?: string;
+  paginationDetails?: string | null;
+  /**
+   * Per-host limits for this job, applied on top of the server's limits,
+   * which all jobs share.
+   */
+  rateLimit?: RateLimitConfig | null;
+  recordKey?: string | null;
+  retry?: RetryConfig | null;
+  /** JSON schema the extracted records should follow. */
+  schema?: unknown;
+  seeds?: SeedConfig | null;
+  session?: SessionConfig | null;
+  tags: string[];
+  url?: string;
+  urls?: InputUrl[];
+  webhooks?: WebhookConfig[];
+}
+
+export interface ScrapingResult {
+  allData: unknown[];
+  inputRows: number[];
+  inputTokens: number;
+  outputTokens: number;
+  paginationInfo?: PaginationInfo | null;
+  totalCost: number;
+  url?: string | null;
+}
+
+export interface SeedConfig {
+  changedSince?: string | null;
+  maxUrls?: number;
+  urls: string[];
+}
+
+export interface SeedSpec {
+  /** Sitemaps and feeds to expand into start URLs. */
+  sitemaps?: SeedConfig | null;
+  urls?: InputUrl[];
+}
+
+export type SessionAuth = {
+  csrfField?: string | null;
+  csrfSelector?: string | null;
+  fields?: Record<string, string>;
+  formUrl?: string | null;
+  loginUrl: string;
+  type: "form";
+} | {
+  /** Where the token is sent, such as `https://api.example.com`. */
+  origin: string;
+  token: string;
+  type: "bearer";
+} | {
+  /** Where the credentials are sent, such as `https://example.com`. */
+  origin: string;
+  password?: string | null;
+  type: "basic";
+  username: string;
+} | {
+  cookies: string[];
+  type: "cookies";
+  url: string;
+};
+
+export interface SessionConfig {
+  auth: SessionAuth;
+  loggedOutSelector?: string | null;
+}
+
+export type SpecFormat = "yaml" | "toml" | "json";
+
+export interface SpecSummary {
+  description?: string | null;
+  format: SpecFormat;
+  name: string;
+  updatedAt?: string | null;
+}
+
+export interface StoredSpec {
+  format: SpecFormat;
+  /** The file as uploaded, comments and formatting included. */
+  source: string;
+  spec: JobSpec;
+}
+
+/** A user's limits and what they have used of them this month. */
+export interface Usage {
+  maxConcurrentJobs?: number | null;
+  /** Calendar month (UTC) the spend applies to, e.g. `2024-05`. */
+  month: string;
+  monthlyBudgetUsd?: number | null;
+  runningJobs: number;
+  spentUsd: number;
+  user?: string | null;
+}
+
+export interface UsageMetadata {
+  inputTokens: number;
+  outputTokens: number;
+  totalCost: number;
+}
+
+export interface WebSocketMessage {
+  metadata?: unknown;
+  payload: string;
+  type: MessageType;
+}
+
+/**
+ * An endpoint notified about crawl progress. When `secret` is set, every
+ * request carries an `X-Scrapy-Signature: sha256=<hex>` header holding the
+ * HMAC-SHA256 of `<X-Scrapy-Timestamp>.<body>`.
+ */
+export interface WebhookConfig {
+  /** Number of extracted items sent per `itemsBatch` delivery. */
+  batchSize?: number;
+  events?: WebhookEvent[];
+  retry?: RetryConfig;
+  secret?: string | null;
+  url: string;
+}
+
+export interface WebhookDelivery {
+  attempts: number;
+  error?: string | null;
+  event: WebhookEvent;
+  finishedAt: string;
+  httpStatus?: number | null;
+  id: string;
+  payloadId: string;
+  startedAt: string;
+  status: WebhookDeliveryStatus;
+  url: string;
+}
+
+export type WebhookDeliveryStatus = "delivered" | "failed";
+
+export type WebhookEvent = "jobCompleted" | "jobFailed" | "itemsBatch";
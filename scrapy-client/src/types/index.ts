@@ -14,22 +14,41 @@ import {
   WebSocketMessageSchema,
 } from "@/schemas";
 import { z } from "zod";
+import type * as Api from "./api";
 
 export type ScrapeSchema = z.infer<typeof scrapeSchema>;
 
-export interface ScrapingResult {
-  allData: Record<string, string | number | boolean | null>[];
-  inputTokens: number;
-  outputTokens: number;
-  totalCost: number;
-  paginationInfo: {
-    pageUrls: string[];
-    tokenCounts: {
-      inputTokens: number;
-      outputTokens: number;
-    };
-    price: number;
-  } | null;
+export type {
+  ApiError,
+  BudgetStats,
+  CacheStats,
+  DedupDecision,
+  DedupStats,
+  FailureKind,
+  FieldChange,
+  FrontierStats,
+  JobDiff,
+  PageDiff,
+  PageReport,
+  PageStatus,
+  PaginationInfo,
+  RecordDiff,
+  ScrapeParams,
+} from "./api";
+
+/** A rejected request field, such as `urls[2]`; listed in `ApiError.details`. */
+export interface FieldError {
+  field: string;
+  message: string;
+}
+
+/** A crawl result, with records narrowed to what the results table renders. */
+export interface ScrapingResult extends Omit<Api.ScrapingResult, "allData"> {
+  allData: ScrapedItems;
+}
+
+export interface CrawlResponse extends Omit<Api.CrawlResponse, "results"> {
+  results: ScrapingResult[];
 }
 
 export type ScrapedItems = z.infer<typeof ScrapedItemsSchema>;
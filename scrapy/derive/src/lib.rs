@@ -0,0 +1,385 @@
+//! `#[derive(ApiSchema)]` for the models of the `scrapy` crate.
+//!
+//! The derive reads the same `serde` attributes that shape the JSON, so the
+//! OpenAPI schema of a type cannot disagree with what the server sends and
+//! accepts. It expands to an impl of `crate::openapi::ApiSchema` and is only
+//! meant to be used inside `scrapy`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Error, Expr, Fields, LitStr,
+    Token, Type,
+};
+
+#[proc_macro_derive(ApiSchema)]
+pub fn derive_api_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// The `serde` attributes that change the JSON shape.
+#[derive(Default)]
+struct Serde {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    tag: Option<String>,
+    untagged: bool,
+    transparent: bool,
+    default: bool,
+    skip: bool,
+    deny_unknown_fields: bool,
+}
+
+impl Serde {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut serde = Serde::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                let string = |meta: &syn::meta::ParseNestedMeta| -> syn::Result<String> {
+                    Ok(meta.value()?.parse::<LitStr>()?.value())
+                };
+                let name = meta
+                    .path
+                    .get_ident()
+                    .map_or_else(|| "this".to_string(), ToString::to_string);
+                let unsupported = |name: &str| {
+                    meta.error(format!(
+                        "ApiSchema does not support the serde attribute `{}`",
+                        name
+                    ))
+                };
+                if meta.input.peek(syn::token::Paren) {
+                    // Separate serialize and deserialize names cannot be described.
+                    return Err(unsupported(&format!("{}(...)", name)));
+                }
+                if meta.path.is_ident("rename") {
+                    serde.rename = Some(string(&meta)?);
+                } else if meta.path.is_ident("rename_all") {
+                    serde.rename_all = Some(string(&meta)?);
+                } else if meta.path.is_ident("tag") {
+                    serde.tag = Some(string(&meta)?);
+                } else if meta.path.is_ident("untagged") {
+                    serde.untagged = true;
+                } else if meta.path.is_ident("transparent") {
+                    serde.transparent = true;
+                } else if meta.path.is_ident("default") {
+                    if meta.input.peek(Token![=]) {
+                        string(&meta)?;
+                    }
+                    serde.default = true;
+                } else if meta.path.is_ident("skip") {
+                    serde.skip = true;
+                } else if meta.path.is_ident("deny_unknown_fields") {
+                    serde.deny_unknown_fields = true;
+                } else {
+                    // Anything else (`flatten`, `alias`, `with`, `skip_serializing_if`, ...)
+                    // would make the schema disagree with the JSON.
+                    return Err(unsupported(&name));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(serde)
+    }
+}
+
+/// The doc comment, if any, with the leading space of each line removed.
+fn doc(attrs: &[Attribute]) -> TokenStream2 {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(line),
+                        ..
+                    }),
+                ..
+            }) => Some(line.value().trim().to_string()),
+            _ => None,
+        })
+        .collect();
+    let doc = lines.join("\n").trim().to_string();
+    if doc.is_empty() {
+        quote!(::std::option::Option::None)
+    } else {
+        quote!(::std::option::Option::Some(#doc))
+    }
+}
+
+/// Splits a snake_case field or PascalCase variant name into lowercase words.
+fn words(name: &str) -> Vec<String> {
+    let name = name.strip_prefix("r#").unwrap_or(name);
+    let mut words = Vec::new();
+    for part in name.split('_').filter(|part| !part.is_empty()) {
+        let mut word = String::new();
+        for c in part.chars() {
+            if c.is_uppercase() && !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            word.extend(c.to_lowercase());
+        }
+        words.push(word);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Applies a `rename_all` rule the way serde does.
+fn rename(name: &str, rule: Option<&str>, span: proc_macro2::Span) -> syn::Result<String> {
+    let words = words(name);
+    Ok(match rule {
+        None => name.strip_prefix("r#").unwrap_or(name).to_string(),
+        Some("lowercase") => words.concat(),
+        Some("UPPERCASE") => words.concat().to_uppercase(),
+        Some("snake_case") => words.join("_"),
+        Some("SCREAMING_SNAKE_CASE") => words.join("_").to_uppercase(),
+        Some("kebab-case") => words.join("-"),
+        Some("PascalCase") => words.iter().map(|word| capitalize(word)).collect(),
+        Some("camelCase") => words
+            .iter()
+            .enumerate()
+            .map(|(index, word)| {
+                if index == 0 {
+                    word.clone()
+                } else {
+                    capitalize(word)
+                }
+            })
+            .collect(),
+        Some(rule) => {
+            return Err(Error::new(
+                span,
+                format!("unsupported rename_all rule {:?}", rule),
+            ))
+        }
+    })
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+/// The properties of a struct or struct variant, as `openapi::Property`
+/// values.
+fn properties(
+    fields: &syn::FieldsNamed,
+    rule: Option<&str>,
+    container_default: bool,
+) -> syn::Result<Vec<TokenStream2>> {
+    let mut properties = Vec::new();
+    for field in &fields.named {
+        let serde = Serde::parse(&field.attrs)?;
+        if serde.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().expect("named field");
+        let name = match serde.rename {
+            Some(name) => name,
+            None => rename(&ident.to_string(), rule, ident.span())?,
+        };
+        let ty = &field.ty;
+        let required = !(container_default || serde.default || is_option(ty));
+        let description = doc(&field.attrs);
+        properties.push(quote! {
+            crate::openapi::Property {
+                name: #name,
+                schema: components.reference::<#ty>(),
+                required: #required,
+                description: #description,
+            }
+        });
+    }
+    Ok(properties)
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(
+            input.generics.span(),
+            "ApiSchema does not support generic types",
+        ));
+    }
+    let ident = &input.ident;
+    let serde = Serde::parse(&input.attrs)?;
+    let description = doc(&input.attrs);
+    let rule = serde.rename_all.as_deref();
+    let mut name = Some(ident.to_string());
+
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) if !serde.transparent => {
+                let properties = properties(fields, rule, serde.default)?;
+                let closed = serde.deny_unknown_fields;
+                quote! {
+                    crate::openapi::object(#description, vec![#(#properties),*], #closed)
+                }
+            }
+            fields if fields.len() == 1 => {
+                // Newtypes serialize as their only field and are inlined.
+                name = None;
+                let ty = &fields.iter().next().expect("one field").ty;
+                quote! { components.reference::<#ty>() }
+            }
+            fields => {
+                return Err(Error::new(
+                    fields.span(),
+                    "ApiSchema only supports structs with named fields and newtypes",
+                ))
+            }
+        },
+        Data::Enum(data) => {
+            let mut values = Vec::new();
+            let mut variants = Vec::new();
+            let mut all_unit = true;
+            for variant in &data.variants {
+                let variant_serde = Serde::parse(&variant.attrs)?;
+                if variant_serde.skip {
+                    continue;
+                }
+                let value = match variant_serde.rename {
+                    Some(name) => name,
+                    None => rename(&variant.ident.to_string(), rule, variant.ident.span())?,
+                };
+                let variant_description = doc(&variant.attrs);
+                let content = match &variant.fields {
+                    Fields::Unit => None,
+                    Fields::Named(fields) => {
+                        all_unit = false;
+                        let properties = properties(
+                            fields,
+                            variant_serde.rename_all.as_deref(),
+                            variant_serde.default,
+                        )?;
+                        Some(quote! {
+                            crate::openapi::object(
+                                #variant_description,
+                                vec![#(#properties),*],
+                                false,
+                            )
+                        })
+                    }
+                    Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                        all_unit = false;
+                        let ty = &fields.unnamed[0].ty;
+                        Some(quote! { components.reference::<#ty>() })
+                    }
+                    fields => {
+                        return Err(Error::new(
+                            fields.span(),
+                            "ApiSchema does not support tuple variants",
+                        ))
+                    }
+                };
+                let content = match content {
+                    Some(content) => quote!(::std::option::Option::Some(#content)),
+                    None => quote!(::std::option::Option::None),
+                };
+                variants.push(quote! {
+                    crate::openapi::Variant {
+                        name: #value,
+                        description: #variant_description,
+                        content: #content,
+                    }
+                });
+                values.push(value);
+            }
+
+            if serde.untagged {
+                quote! {
+                    crate::openapi::untagged(#description, vec![#(#variants),*])
+                }
+            } else if let Some(tag) = &serde.tag {
+                quote! {
+                    crate::openapi::internally_tagged(#description, #tag, vec![#(#variants),*])
+                }
+            } else if all_unit {
+                quote! {
+                    crate::openapi::string_enum(#description, &[#(#values),*])
+                }
+            } else {
+                quote! {
+                    crate::openapi::externally_tagged(#description, vec![#(#variants),*])
+                }
+            }
+        }
+        Data::Union(data) => {
+            return Err(Error::new(
+                data.union_token.span(),
+                "ApiSchema does not support unions",
+            ))
+        }
+    };
+
+    let schema_name = match name {
+        Some(name) => quote!(::std::option::Option::Some(#name)),
+        None => quote!(::std::option::Option::None),
+    };
+    Ok(quote! {
+        impl crate::openapi::ApiSchema for #ident {
+            fn schema_name() -> ::std::option::Option<&'static str> {
+                #schema_name
+            }
+
+            #[allow(unused_variables)]
+            fn schema(components: &mut crate::openapi::Components) -> ::serde_json::Value {
+                #body
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    #[test]
+    fn reads_the_supported_serde_attributes() {
+        let attrs: Vec<Attribute> = vec![
+            parse_quote!(#[serde(rename_all = "camelCase", default = "defaults")]),
+            parse_quote!(#[serde(tag = "type", deny_unknown_fields)]),
+        ];
+        let serde = Serde::parse(&attrs).unwrap();
+        assert_eq!(serde.rename_all.as_deref(), Some("camelCase"));
+        assert_eq!(serde.tag.as_deref(), Some("type"));
+        assert!(serde.default && serde.deny_unknown_fields);
+    }
+
+    #[test]
+    fn rejects_serde_attributes_it_does_not_model() {
+        let unsupported: Vec<Attribute> = vec![
+            parse_quote!(#[serde(flatten)]),
+            parse_quote!(#[serde(skip_serializing_if = "Option::is_none")]),
+            parse_quote!(#[serde(skip_deserializing)]),
+            parse_quote!(#[serde(with = "chrono::serde::ts_seconds")]),
+            parse_quote!(#[serde(serialize_with = "to_string")]),
+            parse_quote!(#[serde(alias = "old_name")]),
+            parse_quote!(#[serde(rename(serialize = "a", deserialize = "b"))]),
+        ];
+        for attr in unsupported {
+            let err = Serde::parse(std::slice::from_ref(&attr)).err().unwrap();
+            assert!(err.to_string().contains("does not support"), "{}", err);
+        }
+    }
+}
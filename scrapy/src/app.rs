@@ -0,0 +1,111 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    auth::Authenticator,
+    cache::DiskCache,
+    config::ServerConfig,
+    error::AppError,
+    jobs::JobStore,
+    metrics::Metrics,
+    outbound::OutboundGuard,
+    services::{
+        AIService, AccountService, CrawlerService, GeminiAIProvider, WebSocketService,
+        WebhookService,
+    },
+    vault::CredentialVault,
+    Crawler,
+};
+
+/// Services shared by the web server and the CLI.
+pub struct App {
+    pub websocket_service: Arc<WebSocketService>,
+    pub ai_service: Arc<AIService<GeminiAIProvider>>,
+    pub webhook_service: Arc<WebhookService>,
+    pub crawler_service: Arc<CrawlerService>,
+    pub account_service: Arc<AccountService>,
+    pub vault: Arc<CredentialVault>,
+    pub metrics: Arc<Metrics>,
+}
+
+impl App {
+    pub fn new(config: &ServerConfig) -> Result<Self, AppError> {
+        let cache_config = config.storage.cache();
+        let http_cache = Arc::new(DiskCache::new(
+            cache_config.dir.join("http"),
+            Duration::from_secs(cache_config.http_ttl_secs),
+            cache_config.http_max_bytes,
+        ));
+        let ai_cache = DiskCache::new(
+            cache_config.dir.join("ai"),
+            Duration::from_secs(cache_config.ai_ttl_secs),
+            cache_config.ai_max_bytes,
+        );
+
+        let metrics = Arc::new(Metrics::new());
+        let websocket_service = Arc::new(WebSocketService::new(config.limits.websocket_capacity));
+        let gemini_provider = GeminiAIProvider::new();
+        let ai_service = Arc::new(
+            AIService::new(gemini_provider)
+                .with_cache(ai_cache)
+                .with_metrics(metrics.clone()),
+        );
+
+        let outbound_guard = Arc::new(OutboundGuard::new(&config.outbound)?);
+
+        // The configured webhook is notified about every crawl.
+        let webhook_service = Arc::new(
+            WebhookService::open(
+                config.storage.webhook_deliveries_file(),
+                config.webhook.webhooks(),
+            )?
+            .with_outbound_guard(outbound_guard.clone())?,
+        );
+
+        let authenticator = Arc::new(Authenticator::new(&config.auth));
+        let account_service = Arc::new(AccountService::open(
+            config.storage.usage_file(),
+            authenticator,
+        )?);
+
+        let vault = Arc::new(CredentialVault::open(
+            config.storage.credentials_file(),
+            config.vault.master_key.as_ref(),
+        )?);
+
+        let crawler = Crawler::new(
+            config.crawler.rate_limit(),
+            config.crawler.retry(),
+            config.crawler.crawling_concurrency,
+            config.crawler.processing_concurrency,
+        )
+        .with_outbound_guard(outbound_guard.clone())
+        .with_metrics(metrics.clone());
+        let crawler_service = Arc::new(
+            CrawlerService::new(
+                crawler,
+                websocket_service.clone(),
+                ai_service.clone(),
+                config.crawler.fetch(),
+                Some(http_cache),
+                Arc::new(JobStore::new(config.storage.jobs_dir())),
+                webhook_service.clone(),
+            )
+            .with_default_api_key(config.ai.api_key.clone())
+            .with_default_model(config.ai.default_model.clone())
+            .with_account_service(account_service.clone())
+            .with_vault(vault.clone())
+            .with_outbound_guard(outbound_guard)
+            .with_metrics(metrics.clone()),
+        );
+
+        Ok(Self {
+            websocket_service,
+            ai_service,
+            webhook_service,
+            crawler_service,
+            account_service,
+            vault,
+            metrics,
+        })
+    }
+}
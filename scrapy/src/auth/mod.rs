@@ -0,0 +1,184 @@
+use std::{collections::HashMap, sync::Arc};
+
+use rocket::{
+    request::{FromRequest, Outcome},
+    Request,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{config::AuthSettings, error::AppError};
+
+/// Query parameter carrying the API key where headers cannot be set, as with
+/// the browser's WebSocket and EventSource APIs.
+pub const ACCESS_TOKEN_PARAM: &str = "access_token";
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quota {
+    pub max_concurrent_jobs: Option<usize>,
+    pub monthly_budget_usd: Option<f64>,
+}
+
+/// The caller of an API route, as resolved from its API key.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    /// `None` when authentication is disabled.
+    pub name: Option<String>,
+    pub admin: bool,
+    pub quota: Quota,
+}
+
+impl User {
+    /// The caller when authentication is disabled: an admin without quotas.
+    pub fn anonymous() -> Self {
+        Self {
+            name: None,
+            admin: true,
+            quota: Quota::default(),
+        }
+    }
+
+    /// Whether the user may see or manage something owned by `owner`.
+    pub fn can_access(&self, owner: Option<&str>) -> bool {
+        self.admin || (self.name.is_some() && self.name.as_deref() == owner)
+    }
+}
+
+/// Resolves API keys to users. Keys are only kept as SHA-256 digests.
+pub struct Authenticator {
+    enabled: bool,
+    users: HashMap<String, User>,
+}
+
+impl Authenticator {
+    pub fn new(settings: &AuthSettings) -> Self {
+        let users = settings
+            .users
+            .iter()
+            .map(|user| {
+                let quota = Quota {
+                    max_concurrent_jobs: user.max_concurrent_jobs,
+                    monthly_budget_usd: user.monthly_budget_usd,
+                };
+                let resolved = User {
+                    name: Some(user.name.clone()),
+                    admin: user.admin,
+                    quota,
+                };
+                (digest(user.api_key.expose()), resolved)
+            })
+            .collect();
+        Self {
+            enabled: settings.enabled,
+            users,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn authenticate(&self, api_key: Option<&str>) -> Result<User, AppError> {
+        if !self.enabled {
+            return Ok(User::anonymous());
+        }
+        let api_key = api_key.ok_or_else(|| AppError::Auth("missing API key".to_string()))?;
+        self.users
+            .get(&digest(api_key))
+            .cloned()
+            .ok_or_else(|| AppError::Auth("invalid API key".to_string()))
+    }
+
+    /// The quota of the user called `name`, if they exist.
+    pub fn quota(&self, name: &str) -> Option<Quota> {
+        self.users
+            .values()
+            .find(|user| user.name.as_deref() == Some(name))
+            .map(|user| user.quota)
+    }
+}
+
+fn digest(api_key: &str) -> String {
+    hex::encode(Sha256::digest(api_key.as_bytes()))
+}
+
+/// Reads the API key from `Authorization: Bearer`, `X-API-Key` or the
+/// `access_token` query parameter.
+fn api_key<'r>(request: &'r Request<'_>) -> Option<&'r str> {
+    let headers = request.headers();
+    headers
+        .get_one("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| headers.get_one("X-API-Key"))
+        .map(str::trim)
+        .or_else(|| {
+            request
+                .query_value::<&str>(ACCESS_TOKEN_PARAM)
+                .and_then(Result::ok)
+        })
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for User {
+    type Error = AppError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some(authenticator) = request.rocket().state::<Arc<Authenticator>>() else {
+            return Outcome::Success(User::anonymous());
+        };
+        match authenticator.authenticate(api_key(request)) {
+            Ok(user) => Outcome::Success(user),
+            Err(err) => {
+                err.catch(request);
+                Outcome::Error((err.status(), err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::UserSettings, vault::Secret};
+
+    fn settings(enabled: bool) -> AuthSettings {
+        AuthSettings {
+            enabled,
+            users: vec![UserSettings {
+                name: "alice".to_string(),
+                api_key: Secret::new("alice-secret-key-0001"),
+                admin: false,
+                max_concurrent_jobs: Some(2),
+                monthly_budget_usd: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn resolves_users_by_api_key() {
+        let authenticator = Authenticator::new(&settings(true));
+
+        let alice = authenticator
+            .authenticate(Some("alice-secret-key-0001"))
+            .unwrap();
+        assert_eq!(alice.name.as_deref(), Some("alice"));
+        assert_eq!(alice.quota.max_concurrent_jobs, Some(2));
+        assert!(alice.can_access(Some("alice")));
+        assert!(!alice.can_access(Some("bob")));
+        assert!(!alice.can_access(None));
+
+        assert!(authenticator.authenticate(Some("wrong")).is_err());
+        assert!(authenticator.authenticate(None).is_err());
+    }
+
+    #[test]
+    fn allows_everything_when_disabled() {
+        let authenticator = Authenticator::new(&settings(false));
+
+        let user = authenticator.authenticate(None).unwrap();
+        assert!(user.name.is_none());
+        assert!(user.can_access(Some("alice")));
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use clap::Parser;
+use serde_json::{json, Map, Value};
+
+use scrapy::{
+    app::App,
+    config::ServerConfig,
+    error::AppError,
+    export::{write_records, ExportFormat},
+    models::{InputUrl, MessageType, OutputSpec, ScrapeParams},
+    specs::{self, SpecFormat},
+    vault::Secret,
+};
+
+/// Runs a crawl without the web server. Progress goes to stderr, extracted
+/// records to stdout or the output file.
+#[derive(Parser)]
+#[command(name = "scrapy-cli", version, about)]
+struct Args {
+    /// Start URLs, in addition to the ones in the job file.
+    urls: Vec<String>,
+
+    /// Job spec (YAML, TOML or JSON with a `version` field), or crawl
+    /// parameters as accepted by `POST /api/crawl` in JSON or TOML. Flags
+    /// override values from the file.
+    #[arg(short, long)]
+    job: Option<PathBuf>,
+
+    #[arg(long)]
+    model: Option<String>,
+
+    #[arg(long, env = "GEMINI_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// Information to extract from each page; can be repeated.
+    #[arg(short, long = "tag")]
+    tags: Vec<String>,
+
+    /// Only fetch pages, without AI extraction.
+    #[arg(long)]
+    no_extract: bool,
+
+    /// Follow same-host links found on crawled pages.
+    #[arg(long)]
+    follow_links: bool,
+
+    #[arg(long)]
+    max_depth: Option<u32>,
+
+    #[arg(long)]
+    max_pages: Option<usize>,
+
+    #[arg(long)]
+    ignore_robots_txt: bool,
+
+    #[arg(long)]
+    bypass_cache: bool,
+
+    /// Export format: json, jsonl or csv.
+    #[arg(short, long, default_value = "jsonl")]
+    format: ExportFormat,
+
+    /// Write records to this file instead of stdout or the job spec outputs.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Only report errors on stderr.
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenvy::dotenv().ok();
+    let args = Args::parse();
+
+    let level = if args.quiet { "error" } else { "warn" };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level)).init();
+
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(args: Args) -> Result<(), AppError> {
+    let config = ServerConfig::load()?;
+    let (params, outputs) = build_params(&args, &config)?;
+    if !params.has_start_source() {
+        return Err(AppError::Job(
+            "No start URLs: pass them as arguments or in the job file, or give sitemap seeds"
+                .to_string(),
+        ));
+    }
+
+    let app = App::new(&config)?;
+    if !args.quiet {
+        let mut messages = app.websocket_service.subscribe().await;
+        tokio::spawn(async move {
+            while let Ok(message) = messages.recv().await {
+                let label = match message.r#type {
+                    MessageType::Progress => "scraped",
+                    MessageType::Warning => "skipped",
+                    MessageType::Error => "failed",
+                    _ => continue,
+                };
+                eprintln!("{:>8} {}", label, message.payload);
+            }
+        });
+    }
+
+    let (results, report) = app.crawler_service.crawl(params).await?;
+    let records: usize = results.iter().map(|result| result.records().len()).sum();
+    if !args.quiet {
+        eprintln!(
+            "Crawled {} page(s), extracted {} record(s)",
+            report.pages.len(),
+            records
+        );
+    }
+
+    // `--output` replaces the outputs of a job spec; without either, records
+    // go to stdout.
+    let outputs = match &args.output {
+        Some(path) => vec![OutputSpec {
+            format: args.format,
+            path: path.clone(),
+        }],
+        None => outputs,
+    };
+    if outputs.is_empty() {
+        let mut writer = io::stdout().lock();
+        write_records(args.format, &results, &mut writer)?;
+        writer.flush()?;
+    }
+    for output in outputs {
+        let mut writer = BufWriter::new(File::create(&output.path)?);
+        write_records(output.format, &results, &mut writer)?;
+        writer.flush()?;
+        if !args.quiet {
+            eprintln!("Wrote {:?}", output.path);
+        }
+    }
+    Ok(())
+}
+
+/// Builds crawl parameters from the job file, which is either a job spec or
+/// a `POST /api/crawl` body, and applies the flags on top. Also returns the
+/// outputs listed in a job spec.
+fn build_params(
+    args: &Args,
+    config: &ServerConfig,
+) -> Result<(ScrapeParams, Vec<OutputSpec>), AppError> {
+    let (mut params, outputs) = match &args.job {
+        Some(path) => {
+            let job = read_job_file(path)?;
+            if job.contains_key("version") {
+                let spec = specs::load(path)?;
+                (spec.to_params(), spec.outputs)
+            } else {
+                (request_params(job, config)?, Vec::new())
+            }
+        }
+        None => (request_params(Map::new(), config)?, Vec::new()),
+    };
+
+    if let Some(model) = &args.model {
+        params.model = model.clone();
+    }
+    if let Some(api_key) = &args.api_key {
+        params.api_key = Secret::new(api_key.clone());
+    }
+    if !args.tags.is_empty() {
+        params.tags = args.tags.clone();
+    }
+    params.enable_scraping &= !args.no_extract;
+    params.ignore_robots_txt |= args.ignore_robots_txt;
+    params.bypass_cache |= args.bypass_cache;
+    params
+        .urls
+        .extend(args.urls.iter().cloned().map(InputUrl::Plain));
+
+    let frontier = &mut params.frontier;
+    frontier.follow_links |= args.follow_links;
+    if args.max_depth.is_some() {
+        frontier.max_depth = args.max_depth;
+    }
+    if args.max_pages.is_some() {
+        frontier.max_pages = args.max_pages;
+    }
+
+    Ok((params, outputs))
+}
+
+/// Parses a crawl request body, filling in the fields the web client always
+/// sends.
+fn request_params(
+    mut params: Map<String, Value>,
+    config: &ServerConfig,
+) -> Result<ScrapeParams, AppError> {
+    for (key, default) in [
+        ("model", json!(config.ai.default_model)),
+        ("apiKey", json!("")),
+        ("enableScraping", json!(true)),
+        ("tags", json!([])),
+        ("enablePagination", json!(false)),
+    ] {
+        params.entry(key).or_insert(default);
+    }
+    Ok(serde_json::from_value(Value::Object(params))?)
+}
+
+fn read_job_file(path: &Path) -> Result<Map<String, Value>, AppError> {
+    let content = std::fs::read_to_string(path)?;
+    let invalid = |err: String| AppError::Job(format!("Invalid job file {:?}: {}", path, err));
+
+    let value: Value = match SpecFormat::from_path(path) {
+        Some(SpecFormat::Yaml) => {
+            serde_yaml::from_str(&content).map_err(|err| invalid(err.to_string()))?
+        }
+        Some(SpecFormat::Toml) => {
+            toml::from_str(&content).map_err(|err| invalid(err.to_string()))?
+        }
+        _ => serde_json::from_str(&content)?,
+    };
+    match value {
+        Value::Object(params) => Ok(params),
+        _ => Err(invalid("expected an object".to_string())),
+    }
+}
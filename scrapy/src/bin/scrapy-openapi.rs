@@ -0,0 +1,26 @@
+use clap::Parser;
+
+use scrapy::{openapi, routes};
+
+/// Prints the OpenAPI document of the REST API, as served at
+/// `/api/openapi.json`, or the TypeScript types generated from it.
+#[derive(Parser)]
+#[command(name = "scrapy-openapi", version, about)]
+struct Args {
+    /// Print TypeScript declarations of the schemas instead of the document.
+    #[arg(long)]
+    typescript: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+    let document = routes::openapi_document();
+    if args.typescript {
+        print!("{}", openapi::typescript(&document));
+    } else {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&document).expect("JSON values always serialize")
+        );
+    }
+}
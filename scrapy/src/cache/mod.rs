@@ -0,0 +1,198 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{fs, sync::OnceCell};
+
+/// Fraction of `max_bytes` the cache is trimmed down to once it overflows.
+const EVICTION_TARGET: f64 = 0.9;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry<T> {
+    key: String,
+    stored_at: DateTime<Utc>,
+    value: T,
+}
+
+pub struct Cached<T> {
+    pub value: T,
+    pub fresh: bool,
+}
+
+/// Best-effort key/value store with one JSON file per entry. I/O errors are
+/// logged and treated as misses so a broken cache never fails a crawl.
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: u64,
+    size: OnceCell<AtomicU64>,
+}
+
+impl DiskCache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration, max_bytes: u64) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+            max_bytes,
+            size: OnceCell::new(),
+        }
+    }
+
+    pub fn hash_key(parts: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        for part in parts {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    /// Returns the entry stored under `key`, fresh or not, so callers can
+    /// revalidate stale entries instead of discarding them.
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Option<Cached<T>> {
+        let content = fs::read(self.path(key)).await.ok()?;
+        let entry: Entry<T> = match serde_json::from_slice(&content) {
+            Ok(entry) => entry,
+            Err(err) => {
+                log::warn!("Ignoring corrupt cache entry for {}: {}", key, err);
+                return None;
+            }
+        };
+        if entry.key != key {
+            return None;
+        }
+
+        let age = (Utc::now() - entry.stored_at).to_std().unwrap_or_default();
+        Some(Cached {
+            value: entry.value,
+            fresh: age < self.ttl,
+        })
+    }
+
+    pub async fn put<T: Serialize>(&self, key: &str, value: &T) {
+        if let Err(err) = self.write(key, value).await {
+            log::warn!("Failed to write cache entry for {}: {}", key, err);
+        }
+    }
+
+    async fn write<T: Serialize>(&self, key: &str, value: &T) -> std::io::Result<()> {
+        let entry = Entry {
+            key: key.to_string(),
+            stored_at: Utc::now(),
+            value,
+        };
+        let content = serde_json::to_vec(&entry)?;
+
+        fs::create_dir_all(&self.dir).await?;
+        let path = self.path(key);
+        let temp = path.with_extension(format!("tmp-{}", uuid::Uuid::new_v4()));
+        fs::write(&temp, &content).await?;
+        fs::rename(&temp, &path).await?;
+
+        let size = self
+            .size
+            .get_or_init(|| async { AtomicU64::new(Self::scan(&self.dir).await.0) })
+            .await;
+        if size.fetch_add(content.len() as u64, Ordering::Relaxed) + content.len() as u64
+            > self.max_bytes
+        {
+            size.store(self.evict().await?, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Removes the least recently written entries until the cache is below
+    /// the eviction target and returns the remaining size.
+    async fn evict(&self) -> std::io::Result<u64> {
+        let (mut total, mut files) = Self::scan(&self.dir).await;
+        files.sort_by_key(|(modified, _, _)| *modified);
+
+        let target = (self.max_bytes as f64 * EVICTION_TARGET) as u64;
+        let mut removed = 0;
+        for (_, len, path) in files {
+            if total <= target {
+                break;
+            }
+            fs::remove_file(&path).await?;
+            total -= len;
+            removed += 1;
+        }
+
+        log::info!("Evicted {} entries from cache {:?}", removed, self.dir);
+        Ok(total)
+    }
+
+    async fn scan(dir: &Path) -> (u64, Vec<(SystemTime, u64, PathBuf)>) {
+        let mut total = 0;
+        let mut files = Vec::new();
+        let Ok(mut entries) = fs::read_dir(dir).await else {
+            return (total, files);
+        };
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            if metadata.is_file() {
+                total += metadata.len();
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                files.push((modified, metadata.len(), entry.path()));
+            }
+        }
+        (total, files)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", Self::hash_key(&[key])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("scrapy-cache-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn stores_entries_and_tracks_freshness() {
+        let dir = temp_dir();
+        let cache = DiskCache::new(&dir, Duration::from_secs(60), 1024 * 1024);
+
+        assert!(cache.get::<String>("page").await.is_none());
+        cache.put("page", &"hello".to_string()).await;
+        let cached = cache.get::<String>("page").await.unwrap();
+        assert_eq!(cached.value, "hello");
+        assert!(cached.fresh);
+
+        let expired = DiskCache::new(&dir, Duration::ZERO, 1024 * 1024);
+        assert!(!expired.get::<String>("page").await.unwrap().fresh);
+
+        fs::remove_dir_all(dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn evicts_oldest_entries_over_the_size_limit() {
+        let dir = temp_dir();
+        let cache = DiskCache::new(&dir, Duration::from_secs(60), 1_000);
+
+        for index in 0..10 {
+            cache
+                .put(&format!("page-{}", index), &"x".repeat(200))
+                .await;
+        }
+
+        assert!(DiskCache::scan(&dir).await.0 <= 1_000);
+        assert!(cache.get::<String>("page-9").await.is_some());
+        assert!(cache.get::<String>("page-0").await.is_none());
+
+        fs::remove_dir_all(dir).await.unwrap();
+    }
+}
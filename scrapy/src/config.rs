@@ -0,0 +1,579 @@
+use std::{
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+use rocket::figment::{
+    providers::{Env, Format, Serialized, Toml},
+    Figment,
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    constants::DEFAULT_MODEL,
+    error::AppError,
+    models::{CacheConfig, FetchConfig, RateLimitConfig, RetryConfig, WebhookConfig},
+    outbound::OutboundGuard,
+    utils::{find_static_dir, get_all_models},
+    vault::{parse_master_key, Secret},
+};
+
+/// Config file read when `SCRAPY_CONFIG` is not set.
+pub const DEFAULT_CONFIG_FILE: &str = "Scrapy.toml";
+
+/// Environment variables read before `SCRAPY_*` existed, mapped to their keys.
+const LEGACY_ENV: [(&str, &str); 4] = [
+    ("GEMINI_API_KEY", "ai.api_key"),
+    ("WEBHOOK_URL", "webhook.url"),
+    ("WEBHOOK_SECRET", "webhook.secret"),
+    ("STATIC_DIR", "server.static_dir"),
+];
+
+/// Settings for the server and the CLI. Defaults are overridden by the config
+/// file, then by environment variables such as `SCRAPY_SERVER__PORT=9000` or
+/// `SCRAPY_CRAWLER__CRAWLING_CONCURRENCY=8`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub server: HttpSettings,
+    pub cors: CorsSettings,
+    pub storage: StorageSettings,
+    pub crawler: CrawlerSettings,
+    pub limits: LimitSettings,
+    pub ai: AiSettings,
+    pub webhook: WebhookSettings,
+    pub auth: AuthSettings,
+    pub vault: VaultSettings,
+    pub outbound: OutboundSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpSettings {
+    pub address: IpAddr,
+    pub port: u16,
+    /// Directory holding the built web client. Searched for next to the
+    /// manifest, the executable and the working directory when unset.
+    pub static_dir: Option<PathBuf>,
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            address: IpAddr::from([127, 0, 0, 1]),
+            port: 8000,
+            static_dir: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsSettings {
+    /// Exact origins such as `https://app.example.com`, or `*` for any.
+    pub allowed_origins: Vec<String>,
+    pub allow_credentials: bool,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec!["*".to_string()],
+            allow_credentials: false,
+        }
+    }
+}
+
+impl CorsSettings {
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|origin| origin == "*")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSettings {
+    /// Job runs, schedules, saved specs, usage records and webhook deliveries.
+    pub data_dir: PathBuf,
+    pub cache_dir: PathBuf,
+    pub http_cache_ttl_secs: u64,
+    pub http_cache_max_bytes: u64,
+    pub ai_cache_ttl_secs: u64,
+    pub ai_cache_max_bytes: u64,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        let cache = CacheConfig::default();
+        Self {
+            data_dir: PathBuf::from(".scrapy-data"),
+            cache_dir: cache.dir,
+            http_cache_ttl_secs: cache.http_ttl_secs,
+            http_cache_max_bytes: cache.http_max_bytes,
+            ai_cache_ttl_secs: cache.ai_ttl_secs,
+            ai_cache_max_bytes: cache.ai_max_bytes,
+        }
+    }
+}
+
+impl StorageSettings {
+    pub fn cache(&self) -> CacheConfig {
+        CacheConfig {
+            dir: self.cache_dir.clone(),
+            http_ttl_secs: self.http_cache_ttl_secs,
+            http_max_bytes: self.http_cache_max_bytes,
+            ai_ttl_secs: self.ai_cache_ttl_secs,
+            ai_max_bytes: self.ai_cache_max_bytes,
+        }
+    }
+
+    pub fn jobs_dir(&self) -> PathBuf {
+        self.data_dir.join("jobs")
+    }
+
+    pub fn specs_dir(&self) -> PathBuf {
+        self.data_dir.join("specs")
+    }
+
+    pub fn schedules_file(&self) -> PathBuf {
+        self.data_dir.join("schedules.json")
+    }
+
+    /// Encrypted AI provider credentials.
+    pub fn credentials_file(&self) -> PathBuf {
+        self.data_dir.join("credentials.json")
+    }
+
+    /// Job records and monthly AI spend per user.
+    pub fn usage_file(&self) -> PathBuf {
+        self.data_dir.join("usage.json")
+    }
+
+    pub fn webhook_deliveries_file(&self) -> PathBuf {
+        self.data_dir.join("webhook-deliveries.json")
+    }
+}
+
+/// Defaults for crawls that do not set their own limits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrawlerSettings {
+    pub crawling_concurrency: usize,
+    pub processing_concurrency: usize,
+    pub requests_per_second: f64,
+    pub burst: u32,
+    pub max_concurrent_per_host: usize,
+    pub max_backoff_secs: u64,
+    pub max_retries: u32,
+    pub retry_base_delay_ms: u64,
+    pub retry_max_delay_ms: u64,
+    pub user_agent: Option<String>,
+    pub connect_timeout_ms: u64,
+    pub read_timeout_ms: u64,
+    pub max_redirects: usize,
+    pub max_response_bytes: usize,
+}
+
+impl Default for CrawlerSettings {
+    fn default() -> Self {
+        let rate_limit = RateLimitConfig::default();
+        let retry = RetryConfig::default();
+        let fetch = FetchConfig::default();
+        Self {
+            crawling_concurrency: 2,
+            processing_concurrency: 500,
+            requests_per_second: rate_limit.requests_per_second,
+            burst: rate_limit.burst,
+            max_concurrent_per_host: rate_limit.max_concurrent_per_host,
+            max_backoff_secs: rate_limit.max_backoff_secs,
+            max_retries: retry.max_retries,
+            retry_base_delay_ms: retry.base_delay_ms,
+            retry_max_delay_ms: retry.max_delay_ms,
+            user_agent: fetch.user_agent,
+            connect_timeout_ms: fetch.connect_timeout_ms,
+            read_timeout_ms: fetch.read_timeout_ms,
+            max_redirects: fetch.max_redirects,
+            max_response_bytes: fetch.max_response_bytes,
+        }
+    }
+}
+
+impl CrawlerSettings {
+    pub fn rate_limit(&self) -> RateLimitConfig {
+        RateLimitConfig {
+            requests_per_second: self.requests_per_second,
+            burst: self.burst,
+            max_concurrent_per_host: self.max_concurrent_per_host,
+            max_backoff_secs: self.max_backoff_secs,
+        }
+    }
+
+    pub fn retry(&self) -> RetryConfig {
+        RetryConfig {
+            max_retries: self.max_retries,
+            base_delay_ms: self.retry_base_delay_ms,
+            max_delay_ms: self.retry_max_delay_ms,
+        }
+    }
+
+    pub fn fetch(&self) -> FetchConfig {
+        FetchConfig {
+            user_agent: self.user_agent.clone(),
+            connect_timeout_ms: self.connect_timeout_ms,
+            read_timeout_ms: self.read_timeout_ms,
+            max_redirects: self.max_redirects,
+            max_response_bytes: self.max_response_bytes,
+            ..FetchConfig::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitSettings {
+    /// Messages buffered for slow WebSocket and SSE subscribers.
+    pub websocket_capacity: usize,
+    pub max_json_bytes: u64,
+    pub max_upload_bytes: u64,
+}
+
+impl Default for LimitSettings {
+    fn default() -> Self {
+        Self {
+            websocket_capacity: 1024,
+            max_json_bytes: 1024 * 1024,
+            max_upload_bytes: 16 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AiSettings {
+    /// Key used by the CLI, saved specs and schedules that do not bring their own.
+    pub api_key: Option<Secret>,
+    /// Model for crawl requests that leave `model` empty, and for the CLI.
+    pub default_model: String,
+}
+
+impl Default for AiSettings {
+    fn default() -> Self {
+        Self {
+            api_key: None,
+            default_model: DEFAULT_MODEL.to_string(),
+        }
+    }
+}
+
+/// Webhook notified about every crawl.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebhookSettings {
+    pub url: Option<String>,
+    pub secret: Option<Secret>,
+}
+
+impl WebhookSettings {
+    pub fn webhooks(&self) -> Vec<WebhookConfig> {
+        self.url
+            .iter()
+            .map(|url| WebhookConfig::new(url.clone(), self.secret.clone()))
+            .collect()
+    }
+}
+
+/// API keys for `/api`. When disabled every request acts as an admin.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSettings {
+    pub enabled: bool,
+    pub users: Vec<UserSettings>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserSettings {
+    pub name: String,
+    pub api_key: Secret,
+    /// Admins see and manage every user's jobs and schedules.
+    #[serde(default)]
+    pub admin: bool,
+    pub max_concurrent_jobs: Option<usize>,
+    pub monthly_budget_usd: Option<f64>,
+}
+
+/// Stored AI provider credentials.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VaultSettings {
+    /// 32 random bytes encoded as base64, e.g. from `openssl rand -base64 32`.
+    /// Credentials cannot be stored or used without it.
+    pub master_key: Option<Secret>,
+}
+
+/// Destinations crawls, seeds and webhooks may connect to. Private, loopback
+/// and link-local addresses are blocked unless allowed here.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutboundSettings {
+    pub allow_private_networks: bool,
+    /// Hosts such as `intranet.example.com`, or `*.example.com` for its
+    /// subdomains, that may be reached whatever they resolve to.
+    pub allowed_hosts: Vec<String>,
+    pub denied_hosts: Vec<String>,
+    /// Address ranges such as `10.1.0.0/16` that may be reached.
+    pub allowed_cidrs: Vec<String>,
+    pub denied_cidrs: Vec<String>,
+    /// Lets crawl requests bring their own `fetch.proxies`. A proxy resolves
+    /// the names of the hosts it connects to, so those addresses cannot be
+    /// checked; only the proxy's own address is.
+    pub allow_job_proxies: bool,
+}
+
+/// Shortest API key accepted, so keys cannot be guessed.
+const MIN_API_KEY_LEN: usize = 16;
+
+impl ServerConfig {
+    /// Loads the config file named by `SCRAPY_CONFIG` (or `Scrapy.toml` if it
+    /// exists) and applies environment overrides.
+    pub fn load() -> Result<Self, AppError> {
+        let path = match std::env::var_os("SCRAPY_CONFIG") {
+            Some(path) => {
+                let path = PathBuf::from(path);
+                if !path.is_file() {
+                    return Err(AppError::Config(format!(
+                        "Config file {:?} from SCRAPY_CONFIG does not exist",
+                        path
+                    )));
+                }
+                path
+            }
+            None => PathBuf::from(DEFAULT_CONFIG_FILE),
+        };
+        Self::extract(&Self::figment(&path))
+    }
+
+    pub fn figment(path: &Path) -> Figment {
+        let legacy = Env::raw()
+            .only(&LEGACY_ENV.map(|(name, _)| name))
+            .map(|name| {
+                let key = LEGACY_ENV
+                    .iter()
+                    .find(|(legacy, _)| name.as_str().eq_ignore_ascii_case(legacy))
+                    .map_or(name.as_str(), |(_, key)| *key);
+                key.to_string().into()
+            });
+
+        Figment::from(Serialized::defaults(ServerConfig::default()))
+            .merge(Toml::file(path))
+            .merge(legacy)
+            .merge(Env::prefixed("SCRAPY_").ignore(&["config"]).split("__"))
+    }
+
+    pub fn extract(figment: &Figment) -> Result<Self, AppError> {
+        let config: ServerConfig = figment
+            .extract()
+            .map_err(|err| AppError::Config(err.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), AppError> {
+        let invalid = |message: String| Err(AppError::Config(message));
+        let crawler = &self.crawler;
+
+        if crawler.crawling_concurrency == 0 || crawler.processing_concurrency == 0 {
+            return invalid("crawler concurrency must be at least 1".to_string());
+        }
+        if !(crawler.requests_per_second.is_finite() && crawler.requests_per_second > 0.0) {
+            return invalid(format!(
+                "crawler.requests_per_second must be positive, got {}",
+                crawler.requests_per_second
+            ));
+        }
+        if crawler.max_concurrent_per_host == 0 {
+            return invalid("crawler.max_concurrent_per_host must be at least 1".to_string());
+        }
+        if self.limits.websocket_capacity == 0 {
+            return invalid("limits.websocket_capacity must be at least 1".to_string());
+        }
+
+        let cors = &self.cors;
+        if cors.allowed_origins.is_empty() {
+            return invalid("cors.allowed_origins must not be empty".to_string());
+        }
+        if cors.allows_any_origin() && cors.allow_credentials {
+            return invalid(
+                "cors.allow_credentials requires explicit cors.allowed_origins instead of \"*\""
+                    .to_string(),
+            );
+        }
+        for origin in cors.allowed_origins.iter().filter(|origin| *origin != "*") {
+            if !Url::parse(origin).is_ok_and(|url| url.has_host() && url.path() == "/") {
+                return invalid(format!(
+                    "Invalid CORS origin {:?}: expected scheme and host, e.g. https://app.example.com",
+                    origin
+                ));
+            }
+        }
+
+        if !get_all_models().contains(&self.ai.default_model) {
+            return invalid(format!(
+                "Unknown ai.default_model {:?}, expected one of {:?}",
+                self.ai.default_model,
+                get_all_models()
+            ));
+        }
+        if let Some(url) = &self.webhook.url {
+            Url::parse(url).map_err(|err| {
+                AppError::Config(format!("Invalid webhook.url {:?}: {}", url, err))
+            })?;
+        }
+        if let Some(master_key) = &self.vault.master_key {
+            parse_master_key(master_key)
+                .map_err(|err| AppError::Config(format!("Invalid vault.master_key: {}", err)))?;
+        }
+        OutboundGuard::new(&self.outbound)?;
+        self.validate_auth()
+    }
+
+    fn validate_auth(&self) -> Result<(), AppError> {
+        let invalid = |message: String| Err(AppError::Config(message));
+        let auth = &self.auth;
+
+        if auth.enabled && auth.users.is_empty() {
+            return invalid("auth.enabled requires at least one entry in auth.users".to_string());
+        }
+        for (index, user) in auth.users.iter().enumerate() {
+            let valid_name = !user.name.is_empty()
+                && !user.name.starts_with('.')
+                && user
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'));
+            if !valid_name {
+                return invalid(format!(
+                    "Invalid auth.users name {:?}: use letters, digits, '-', '_', '.' or '@'",
+                    user.name
+                ));
+            }
+            if user.api_key.expose().len() < MIN_API_KEY_LEN {
+                return invalid(format!(
+                    "The API key of user {:?} must be at least {} characters",
+                    user.name, MIN_API_KEY_LEN
+                ));
+            }
+            if user.max_concurrent_jobs == Some(0) {
+                return invalid(format!(
+                    "max_concurrent_jobs of user {:?} must be at least 1",
+                    user.name
+                ));
+            }
+            if user
+                .monthly_budget_usd
+                .is_some_and(|budget| budget.is_nan() || budget < 0.0)
+            {
+                return invalid(format!(
+                    "monthly_budget_usd of user {:?} must not be negative",
+                    user.name
+                ));
+            }
+
+            let others = &auth.users[..index];
+            if others.iter().any(|other| other.name == user.name) {
+                return invalid(format!("Duplicate auth.users name {:?}", user.name));
+            }
+            if others.iter().any(|other| other.api_key == user.api_key) {
+                return invalid(format!(
+                    "User {:?} shares an API key with another user",
+                    user.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The configured static directory, or the first one found next to the
+    /// manifest, the executable or the working directory.
+    pub fn static_dir(&self) -> Result<PathBuf, AppError> {
+        let static_dir = self
+            .server
+            .static_dir
+            .clone()
+            .unwrap_or_else(find_static_dir);
+        if !static_dir.is_dir() {
+            return Err(AppError::Config(format!(
+                "Static directory does not exist: {:?}; build the client or set server.static_dir",
+                static_dir
+            )));
+        }
+        Ok(static_dir)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(toml: &str) -> Result<ServerConfig, AppError> {
+        ServerConfig::extract(
+            &Figment::from(Serialized::defaults(ServerConfig::default())).merge(Toml::string(toml)),
+        )
+    }
+
+    #[test]
+    fn overrides_defaults_from_toml() {
+        let config = extract(
+            r#"
+            [server]
+            port = 9000
+
+            [cors]
+            allowed_origins = ["https://app.example.com"]
+            allow_credentials = true
+
+            [crawler]
+            crawling_concurrency = 4
+            requests_per_second = 1.5
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.server.port, 9000);
+        assert_eq!(config.server.address, IpAddr::from([127, 0, 0, 1]));
+        assert_eq!(config.crawler.crawling_concurrency, 4);
+        assert_eq!(config.crawler.rate_limit().requests_per_second, 1.5);
+        assert_eq!(config.crawler.processing_concurrency, 500);
+        assert_eq!(config.limits.websocket_capacity, 1024);
+        assert_eq!(config.storage.jobs_dir(), Path::new(".scrapy-data/jobs"));
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let cases = [
+            ("[server]\nport = \"eighty\"", "server.port"),
+            ("[crawler]\ncrawling_concurrency = 0", "concurrency"),
+            ("[limits]\nwebsocket_capacity = 0", "websocket_capacity"),
+            ("[cors]\nallow_credentials = true", "allow_credentials"),
+            ("[cors]\nallowed_origins = [\"example.com\"]", "CORS origin"),
+            ("[ai]\ndefault_model = \"gpt\"", "default_model"),
+            ("[crawler]\nconcurrency = 4", "unknown field"),
+            ("[vault]\nmaster_key = \"short\"", "master_key"),
+            (
+                "[outbound]\ndenied_cidrs = [\"10.0.0.0/33\"]",
+                "outbound.denied_cidrs",
+            ),
+        ];
+
+        for (toml, expected) in cases {
+            let err = extract(toml).unwrap_err().to_string();
+            assert!(
+                err.contains(expected),
+                "{:?} does not mention {:?}",
+                err,
+                expected
+            );
+        }
+    }
+}
@@ -1,6 +1,13 @@
 use crate::models::PricingInfo;
 use phf::phf_map;
 
+pub const USER_AGENT: &str = concat!("scrapy/", env!("CARGO_PKG_VERSION"));
+
+pub const DEFAULT_MODEL: &str = "gemini-1.5-flash-latest";
+
+/// Most tokens an extraction may generate.
+pub const MAX_OUTPUT_TOKENS: u64 = 8192;
+
 pub static PRICING_INFO: phf::Map<&'static str, PricingInfo> = phf_map! {
     "gemini-1.5-flash-latest" => PricingInfo {
         input: 0.075 / 1_000_000.0,
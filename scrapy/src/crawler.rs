@@ -1,172 +0,0 @@
-use std::{
-    collections::HashSet,
-    fmt::Display,
-    sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc,
-    },
-    time::Duration,
-};
-
-use futures_util::StreamExt;
-use serde::Serialize;
-use tokio::{
-    sync::{mpsc, Barrier},
-    time::sleep,
-};
-use tokio_stream::wrappers::ReceiverStream;
-
-use crate::{models::ScrapeParams, spider::Spider};
-
-pub struct Crawler {
-    delay: Duration,
-    crawling_concurrency: usize,
-    processing_concurrency: usize,
-    barrier: Arc<Barrier>,
-    active_spiders: Arc<AtomicUsize>,
-}
-
-impl Crawler {
-    pub fn new(
-        delay: Duration,
-        crawling_concurrency: usize,
-        processing_concurrency: usize,
-    ) -> Self {
-        let barrier = Arc::new(Barrier::new(3));
-        let active_spiders = Arc::new(AtomicUsize::new(0));
-        Self {
-            delay,
-            crawling_concurrency,
-            processing_concurrency,
-            barrier,
-            active_spiders,
-        }
-    }
-
-    pub async fn crawl<T, E>(
-        &self,
-        spider: Arc<dyn Spider<Item = T, Error = E>>,
-        params: ScrapeParams,
-    ) where
-        T: Serialize + Send + 'static,
-        E: Display + Send + 'static,
-    {
-        let mut visited_urls = HashSet::<String>::new();
-        let crawling_queue_capacity = self.crawling_concurrency * 400;
-        let processing_queue_capacity = self.processing_concurrency * 10;
-
-        let (urls_to_visit_tx, urls_to_visit_rx) = mpsc::channel::<String>(crawling_queue_capacity);
-        let (items_tx, items_rx) = mpsc::channel(processing_queue_capacity);
-        let (new_urls_tx, mut new_urls_rx) = mpsc::channel(crawling_queue_capacity);
-
-        for url in spider.start_urls() {
-            visited_urls.insert(url.clone());
-            let _ = urls_to_visit_tx.send(url).await;
-        }
-
-        self.launch_processors(spider.clone(), items_rx);
-
-        self.launch_scrapers(
-            spider.clone(),
-            urls_to_visit_rx,
-            new_urls_tx.clone(),
-            items_tx,
-            params,
-        );
-
-        loop {
-            if let Ok((visited_url, new_urls)) = new_urls_rx.try_recv() {
-                visited_urls.insert(visited_url);
-
-                for url in new_urls {
-                    if !visited_urls.contains(&url) {
-                        visited_urls.insert(url.clone());
-                        log::debug!("queueing: {}", url);
-                        let _ = urls_to_visit_tx.send(url).await;
-                    }
-                }
-            }
-
-            if new_urls_tx.capacity() == crawling_queue_capacity
-                && urls_to_visit_tx.capacity() == crawling_queue_capacity
-                && self.active_spiders.load(Ordering::SeqCst) == 0
-            {
-                break;
-            }
-
-            sleep(Duration::from_millis(5)).await;
-        }
-
-        drop(urls_to_visit_tx);
-
-        self.barrier.wait().await;
-    }
-
-    fn launch_processors<T, E>(
-        &self,
-        spider: Arc<dyn Spider<Item = T, Error = E>>,
-        items: mpsc::Receiver<T>,
-    ) where
-        T: Serialize + Send + 'static,
-        E: Send + 'static,
-    {
-        let concurrency = self.processing_concurrency;
-        let barrier = self.barrier.clone();
-        tokio::spawn(async move {
-            ReceiverStream::new(items)
-                .for_each_concurrent(concurrency, |item| async {
-                    let _ = spider.process(item).await;
-                })
-                .await;
-
-            barrier.wait().await;
-        });
-    }
-
-    fn launch_scrapers<T, E>(
-        &self,
-        spider: Arc<dyn Spider<Item = T, Error = E>>,
-        urls_to_visit: mpsc::Receiver<String>,
-        new_urls_tx: mpsc::Sender<(String, Vec<String>)>,
-        items_tx: mpsc::Sender<T>,
-        _params: ScrapeParams,
-    ) where
-        T: Serialize + Send + 'static,
-        E: Display + Send + 'static,
-    {
-        let concurrency = self.crawling_concurrency;
-        let barrier = self.barrier.clone();
-        let delay = self.delay;
-        let active_spiders = self.active_spiders.clone();
-
-        tokio::spawn(async move {
-            tokio_stream::wrappers::ReceiverStream::new(urls_to_visit)
-                .for_each_concurrent(concurrency, |queued_url| {
-                    let queued_url = queued_url.clone();
-                    async {
-                        active_spiders.fetch_add(1, Ordering::SeqCst);
-                        let mut urls = Vec::new();
-                        let res = spider.scrape(queued_url.clone()).await.map_err(|err| {
-                            log::error!("{}", err);
-                            err
-                        });
-
-                        if let Ok((items, new_urls)) = res {
-                            for item in items {
-                                let _ = items_tx.send(item).await;
-                            }
-                            urls = new_urls;
-                        }
-
-                        let _ = new_urls_tx.send((queued_url, urls)).await;
-                        sleep(delay).await;
-                        active_spiders.fetch_sub(1, Ordering::SeqCst);
-                    }
-                })
-                .await;
-
-            drop(items_tx);
-            barrier.wait().await;
-        });
-    }
-}
@@ -0,0 +1,137 @@
+use url::{ParseError, Position, Url};
+
+use crate::models::CanonicalConfig;
+
+/// Normalizes URLs so that trivially different spellings of a page share one
+/// fetch URL and one dedup key.
+#[derive(Clone, Debug)]
+pub struct Canonicalizer {
+    config: CanonicalConfig,
+}
+
+impl Default for Canonicalizer {
+    fn default() -> Self {
+        Self::new(CanonicalConfig::default())
+    }
+}
+
+impl Canonicalizer {
+    pub fn new(config: CanonicalConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn honors_canonical_tag(&self) -> bool {
+        self.config.honor_canonical_tag
+    }
+
+    /// Returns the URL that should be fetched for `raw`. Scheme-less input such
+    /// as `example.com/a` is treated as `http://`; non-HTTP URLs are rejected.
+    pub fn canonicalize(&self, raw: &str) -> Option<Url> {
+        let raw = raw.trim();
+        let mut url = match Url::parse(raw) {
+            Ok(url) => url,
+            Err(ParseError::RelativeUrlWithoutBase) => {
+                Url::parse(&format!("http://{}", raw)).ok()?
+            }
+            Err(_) => return None,
+        };
+        if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+            return None;
+        }
+
+        // Scheme and host case as well as default ports are normalized by the
+        // parser; fragments and tracking parameters are handled here.
+        url.set_fragment(None);
+
+        let mut params: Vec<&str> = url
+            .query()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|param| !param.is_empty())
+            .filter(|param| !self.is_stripped(param.split('=').next().unwrap_or_default()))
+            .collect();
+        if self.config.sort_query {
+            params.sort_unstable();
+        }
+        let query = params.join("&");
+        url.set_query((!query.is_empty()).then_some(query.as_str()));
+
+        Some(url)
+    }
+
+    /// Dedup key for `raw`, or `None` when it is not a crawlable URL.
+    pub fn key(&self, raw: &str) -> Option<String> {
+        self.canonicalize(raw).map(|url| self.key_of(&url))
+    }
+
+    pub fn key_of(&self, url: &Url) -> String {
+        let mut url = url.clone();
+        if self.config.ignore_trailing_slash && url.path() != "/" && url.path().ends_with('/') {
+            let path = url.path().trim_end_matches('/').to_string();
+            url.set_path(&path);
+        }
+
+        if self.config.ignore_scheme {
+            url[Position::BeforeUsername..].to_string()
+        } else {
+            url.to_string()
+        }
+    }
+
+    fn is_stripped(&self, name: &str) -> bool {
+        self.config
+            .strip_params
+            .iter()
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equivalent_spellings_share_a_key() {
+        let canonicalizer = Canonicalizer::default();
+        let keys: Vec<_> = [
+            "http://x.com/a",
+            "https://X.com:443/a/",
+            "x.com/a#top",
+            "x.com/a?utm_source=newsletter&utm_medium=email",
+        ]
+        .into_iter()
+        .map(|url| canonicalizer.key(url).unwrap())
+        .collect();
+
+        assert!(keys.iter().all(|key| key == "x.com/a"), "{:?}", keys);
+        assert_eq!(canonicalizer.key("mailto:someone@x.com"), None);
+    }
+
+    #[test]
+    fn sorts_and_filters_query_params() {
+        let canonicalizer = Canonicalizer::default();
+        assert_eq!(
+            canonicalizer
+                .canonicalize(
+                    "HTTPS://Shop.example.com/list?page=2&gclid=abc&color=red%20blue#reviews"
+                )
+                .unwrap()
+                .as_str(),
+            "https://shop.example.com/list?color=red%20blue&page=2"
+        );
+
+        let strict = Canonicalizer::new(CanonicalConfig {
+            sort_query: false,
+            ignore_scheme: false,
+            ignore_trailing_slash: false,
+            ..CanonicalConfig::default()
+        });
+        assert_eq!(
+            strict.key("http://x.com/a/?b=1&a=2").unwrap(),
+            "http://x.com/a/?b=1&a=2"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap},
+    sync::LazyLock,
+};
+
+use regex::Regex;
+
+use crate::models::{FrontierConfig, FrontierStats};
+
+use super::canonical::Canonicalizer;
+
+static PAGINATION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)([?&](page|p|pg|offset|start)=\d+|/page/\d+/?$)").unwrap());
+
+#[derive(PartialEq, Eq)]
+struct Entry {
+    priority: i32,
+    depth: u32,
+    sequence: u64,
+    url: String,
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.priority, Reverse(self.depth), Reverse(self.sequence)).cmp(&(
+            other.priority,
+            Reverse(other.depth),
+            Reverse(other.sequence),
+        ))
+    }
+}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Priority queue of URLs still to crawl. Higher scores are dequeued first,
+/// shallower URLs win ties and equal entries keep their insertion order.
+pub struct Frontier {
+    config: FrontierConfig,
+    canonicalizer: Canonicalizer,
+    patterns: Vec<(Regex, i32)>,
+    queue: BinaryHeap<Entry>,
+    depths: HashMap<String, u32>,
+    pages_per_domain: HashMap<String, usize>,
+    sequence: u64,
+    stats: FrontierStats,
+}
+
+impl Frontier {
+    pub fn new(
+        config: &FrontierConfig,
+        canonicalizer: Canonicalizer,
+    ) -> Result<Self, regex::Error> {
+        let patterns = config
+            .priority_patterns
+            .iter()
+            .map(|pattern| Ok((Regex::new(&pattern.pattern)?, pattern.boost)))
+            .collect::<Result<_, regex::Error>>()?;
+
+        Ok(Self {
+            config: config.clone(),
+            canonicalizer,
+            patterns,
+            queue: BinaryHeap::new(),
+            depths: HashMap::new(),
+            pages_per_domain: HashMap::new(),
+            sequence: 0,
+            stats: FrontierStats::default(),
+        })
+    }
+
+    /// Queues the canonical form of `url` at `depth` unless an equivalent URL
+    /// was seen before or a limit applies.
+    pub fn push(&mut self, url: String, depth: u32) -> bool {
+        let Some(canonical) = self.canonicalizer.canonicalize(&url) else {
+            log::debug!("Not queueing non-crawlable URL: {}", url);
+            return false;
+        };
+        let key = self.canonicalizer.key_of(&canonical);
+        if self.depths.contains_key(&key) {
+            self.stats.duplicates += 1;
+            return false;
+        }
+        if self.config.max_depth.is_some_and(|max| depth > max) {
+            self.stats.skipped_max_depth += 1;
+            return false;
+        }
+        if self
+            .config
+            .max_pages
+            .is_some_and(|max| self.stats.queued >= max)
+        {
+            self.stats.skipped_max_pages += 1;
+            return false;
+        }
+
+        let domain = canonical.host_str().unwrap_or_default().to_string();
+        let domain_pages = self.pages_per_domain.entry(domain).or_default();
+        if self
+            .config
+            .max_pages_per_domain
+            .is_some_and(|max| *domain_pages >= max)
+        {
+            self.stats.skipped_domain_cap += 1;
+            return false;
+        }
+        *domain_pages += 1;
+
+        let url = canonical.to_string();
+        log::debug!("queueing: {} (depth {})", url, depth);
+        self.depths.insert(key, depth);
+        *self.stats.depth_distribution.entry(depth).or_default() += 1;
+        self.stats.queued += 1;
+        self.sequence += 1;
+        self.queue.push(Entry {
+            priority: self.priority(&url),
+            depth,
+            sequence: self.sequence,
+            url,
+        });
+        self.stats.peak_size = self.stats.peak_size.max(self.queue.len());
+        true
+    }
+
+    /// Queues links discovered on `parent` one level below it.
+    pub fn push_links(&mut self, parent: &str, urls: Vec<String>) {
+        let depth = self
+            .canonicalizer
+            .key(parent)
+            .and_then(|key| self.depths.get(&key))
+            .map_or(0, |depth| depth + 1);
+        for url in urls {
+            self.push(url, depth);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<String> {
+        self.queue.pop().map(|entry| entry.url)
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn stats(&self) -> FrontierStats {
+        FrontierStats {
+            pending: self.queue.len(),
+            ..self.stats.clone()
+        }
+    }
+
+    fn priority(&self, url: &str) -> i32 {
+        let pagination = if PAGINATION.is_match(url) {
+            self.config.pagination_boost
+        } else {
+            0
+        };
+
+        self.patterns
+            .iter()
+            .filter(|(pattern, _)| pattern.is_match(url))
+            .map(|(_, boost)| boost)
+            .sum::<i32>()
+            + pagination
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PriorityPattern;
+
+    #[test]
+    fn orders_by_priority_then_depth() {
+        let mut frontier = Frontier::new(
+            &FrontierConfig {
+                priority_patterns: vec![PriorityPattern {
+                    pattern: "/product/".to_string(),
+                    boost: 5,
+                }],
+                ..FrontierConfig::default()
+            },
+            Canonicalizer::default(),
+        )
+        .unwrap();
+
+        frontier.push("https://example.com/".to_string(), 0);
+        frontier.push_links(
+            "https://example.com/",
+            vec![
+                "https://example.com/about".to_string(),
+                "https://example.com/product/1".to_string(),
+                "https://example.com/list?page=2".to_string(),
+            ],
+        );
+        frontier.push("https://example.com/".to_string(), 0);
+
+        let order: Vec<_> = std::iter::from_fn(|| frontier.pop()).collect();
+        assert_eq!(
+            order,
+            vec![
+                "https://example.com/list?page=2",
+                "https://example.com/product/1",
+                "https://example.com/",
+                "https://example.com/about",
+            ]
+        );
+
+        let stats = frontier.stats();
+        assert_eq!(stats.queued, 4);
+        assert_eq!(stats.duplicates, 1);
+        assert_eq!(stats.depth_distribution, [(0, 1), (1, 3)].into());
+    }
+
+    #[test]
+    fn enforces_depth_page_and_domain_limits() {
+        let mut frontier = Frontier::new(
+            &FrontierConfig {
+                max_depth: Some(1),
+                max_pages: Some(4),
+                max_pages_per_domain: Some(2),
+                ..FrontierConfig::default()
+            },
+            Canonicalizer::default(),
+        )
+        .unwrap();
+
+        assert!(frontier.push("https://a.com/".to_string(), 0));
+        assert!(frontier.push("https://a.com/1".to_string(), 1));
+        assert!(!frontier.push("https://a.com/2".to_string(), 1));
+        assert!(!frontier.push("https://b.com/deep".to_string(), 2));
+        assert!(frontier.push("https://b.com/".to_string(), 0));
+        assert!(frontier.push("https://c.com/".to_string(), 0));
+        assert!(!frontier.push("https://d.com/".to_string(), 0));
+
+        let stats = frontier.stats();
+        assert_eq!(stats.pending, 4);
+        assert_eq!(stats.skipped_domain_cap, 1);
+        assert_eq!(stats.skipped_max_depth, 1);
+        assert_eq!(stats.skipped_max_pages, 1);
+    }
+}
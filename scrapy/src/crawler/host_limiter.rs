@@ -0,0 +1,325 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use tokio::{
+    sync::{Mutex, OwnedSemaphorePermit, Semaphore},
+    time::{sleep, Instant},
+};
+
+use crate::models::RateLimitConfig;
+
+const MIN_REQUESTS_PER_SECOND: f64 = 0.01;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+/// Number of hosts tracked before idle ones are forgotten.
+const MAX_HOSTS: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    burst: f64,
+    rate: f64,
+    max_rate: f64,
+    last_refill: Instant,
+    backoff: Duration,
+    blocked_until: Option<Instant>,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.last_refill = now;
+    }
+
+    /// Whether forgetting the bucket would not loosen any limit.
+    fn is_idle(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.burst
+            && self.backoff.is_zero()
+            && self.blocked_until.is_none_or(|until| until <= now)
+    }
+}
+
+struct HostState {
+    connections: Arc<Semaphore>,
+    bucket: Mutex<Bucket>,
+}
+
+pub struct HostPermit {
+    _connection: OwnedSemaphorePermit,
+}
+
+/// Limits requests per host: a token bucket for the request rate, a cap on
+/// concurrent connections and backoff when the host throttles. The crawler
+/// keeps one for all jobs, so concurrent jobs share each host's limits.
+pub struct HostLimiter {
+    config: RateLimitConfig,
+    hosts: Mutex<HashMap<String, Arc<HostState>>>,
+}
+
+impl HostLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn host(&self, host: &str) -> Arc<HostState> {
+        let mut hosts = self.hosts.lock().await;
+        if hosts.len() >= MAX_HOSTS && !hosts.contains_key(host) {
+            let now = Instant::now();
+            hosts.retain(|_, state| {
+                let unused = Arc::strong_count(state) == 1
+                    && state.connections.available_permits()
+                        == self.config.max_concurrent_per_host.max(1);
+                let idle = unused
+                    && state
+                        .bucket
+                        .try_lock()
+                        .is_ok_and(|mut bucket| bucket.is_idle(now));
+                !idle
+            });
+        }
+        hosts
+            .entry(host.to_string())
+            .or_insert_with(|| {
+                let rate = self.config.requests_per_second.max(MIN_REQUESTS_PER_SECOND);
+                let burst = f64::from(self.config.burst.max(1));
+                Arc::new(HostState {
+                    connections: Arc::new(Semaphore::new(
+                        self.config.max_concurrent_per_host.max(1),
+                    )),
+                    bucket: Mutex::new(Bucket {
+                        tokens: burst,
+                        burst,
+                        rate,
+                        max_rate: rate,
+                        last_refill: Instant::now(),
+                        backoff: Duration::ZERO,
+                        blocked_until: None,
+                    }),
+                })
+            })
+            .clone()
+    }
+
+    pub async fn acquire(&self, host: &str) -> HostPermit {
+        let state = self.host(host).await;
+        let connection = state
+            .connections
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("host semaphore is never closed");
+
+        loop {
+            let wait = {
+                let mut bucket = state.bucket.lock().await;
+                let now = Instant::now();
+                match bucket.blocked_until {
+                    Some(until) if until > now => Some(until - now),
+                    _ => {
+                        bucket.blocked_until = None;
+                        bucket.refill(now);
+                        if bucket.tokens >= 1.0 {
+                            bucket.tokens -= 1.0;
+                            None
+                        } else {
+                            Some(Duration::from_secs_f64((1.0 - bucket.tokens) / bucket.rate))
+                        }
+                    }
+                }
+            };
+
+            match wait {
+                Some(wait) => sleep(wait).await,
+                None => break,
+            }
+        }
+
+        HostPermit {
+            _connection: connection,
+        }
+    }
+
+    pub async fn limit_interval(&self, host: &str, interval: Duration) {
+        if interval.is_zero() {
+            return;
+        }
+
+        let state = self.host(host).await;
+        let mut bucket = state.bucket.lock().await;
+        let max_rate = 1.0 / interval.as_secs_f64();
+        if max_rate < bucket.max_rate {
+            bucket.max_rate = max_rate;
+            bucket.rate = bucket.rate.min(max_rate);
+            bucket.burst = 1.0;
+            bucket.tokens = bucket.tokens.min(1.0);
+        }
+    }
+
+    pub async fn record_success(&self, host: &str) {
+        let state = self.host(host).await;
+        let mut bucket = state.bucket.lock().await;
+        bucket.backoff = Duration::ZERO;
+        bucket.rate = (bucket.rate * 1.25).min(bucket.max_rate);
+    }
+
+    pub async fn record_throttled(&self, host: &str, retry_after: Option<Duration>) {
+        let max_backoff = Duration::from_secs(self.config.max_backoff_secs);
+        let state = self.host(host).await;
+        let mut bucket = state.bucket.lock().await;
+
+        let backoff = if bucket.backoff.is_zero() {
+            INITIAL_BACKOFF
+        } else {
+            bucket.backoff * 2
+        };
+        bucket.backoff = backoff
+            .max(retry_after.unwrap_or_default())
+            .min(max_backoff);
+        bucket.blocked_until = Some(Instant::now() + bucket.backoff);
+        bucket.rate = (bucket.rate / 2.0).max(MIN_REQUESTS_PER_SECOND);
+        bucket.tokens = 0.0;
+
+        log::warn!(
+            "Host {} is throttling requests, backing off for {:?}",
+            host,
+            bucket.backoff
+        );
+    }
+}
+
+/// The crawler's shared limiter plus, when a job sets its own `rateLimit`,
+/// a limiter for that job alone. A job's limits can only slow it down: its
+/// requests also count against the shared limits.
+pub struct JobLimiter {
+    shared: Arc<HostLimiter>,
+    job: Option<HostLimiter>,
+}
+
+pub struct JobPermit {
+    _job: Option<HostPermit>,
+    _shared: HostPermit,
+}
+
+impl JobLimiter {
+    pub fn new(shared: Arc<HostLimiter>, job: Option<RateLimitConfig>) -> Self {
+        Self {
+            shared,
+            job: job.map(HostLimiter::new),
+        }
+    }
+
+    pub async fn acquire(&self, host: &str) -> JobPermit {
+        let job = match &self.job {
+            Some(job) => Some(job.acquire(host).await),
+            None => None,
+        };
+        JobPermit {
+            _job: job,
+            _shared: self.shared.acquire(host).await,
+        }
+    }
+
+    pub async fn limit_interval(&self, host: &str, interval: Duration) {
+        self.shared.limit_interval(host, interval).await;
+        if let Some(job) = &self.job {
+            job.limit_interval(host, interval).await;
+        }
+    }
+
+    pub async fn record_success(&self, host: &str) {
+        self.shared.record_success(host).await;
+        if let Some(job) = &self.job {
+            job.record_success(host).await;
+        }
+    }
+
+    pub async fn record_throttled(&self, host: &str, retry_after: Option<Duration>) {
+        self.shared.record_throttled(host, retry_after).await;
+        if let Some(job) = &self.job {
+            job.record_throttled(host, retry_after).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(
+        requests_per_second: f64,
+        burst: u32,
+        max_concurrent_per_host: usize,
+    ) -> HostLimiter {
+        HostLimiter::new(RateLimitConfig {
+            requests_per_second,
+            burst,
+            max_concurrent_per_host,
+            max_backoff_secs: 60,
+        })
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refills_tokens_at_the_configured_rate() {
+        let limiter = limiter(2.0, 2, 10);
+        let start = Instant::now();
+
+        // The burst is available at once, then one token every 500ms.
+        for _ in 0..4 {
+            limiter.acquire("a.test").await;
+        }
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+
+        // Hosts are limited independently.
+        limiter.acquire("b.test").await;
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn caps_concurrent_connections_per_host() {
+        let limiter = Arc::new(limiter(1000.0, 1000, 2));
+        let first = limiter.acquire("a.test").await;
+        let _second = limiter.acquire("a.test").await;
+
+        let waiting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.acquire("a.test").await }
+        });
+        sleep(Duration::from_secs(5)).await;
+        assert!(!waiting.is_finished());
+
+        drop(first);
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .expect("permit released")
+            .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_for_retry_after_and_shares_limits_across_jobs() {
+        let shared = Arc::new(limiter(100.0, 100, 10));
+        let throttled = JobLimiter::new(shared.clone(), None);
+        let other_job = JobLimiter::new(
+            shared,
+            Some(RateLimitConfig {
+                requests_per_second: 100.0,
+                ..RateLimitConfig::default()
+            }),
+        );
+        let start = Instant::now();
+
+        throttled
+            .record_throttled("a.test", Some(Duration::from_secs(30)))
+            .await;
+        other_job.acquire("a.test").await;
+        assert_eq!(start.elapsed(), Duration::from_secs(30));
+
+        // Without Retry-After the backoff doubles from one second.
+        throttled.record_throttled("b.test", None).await;
+        throttled.record_throttled("b.test", None).await;
+        let start = Instant::now();
+        throttled.acquire("b.test").await;
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+    }
+}
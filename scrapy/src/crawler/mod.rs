@@ -0,0 +1,428 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures_util::StreamExt;
+use reqwest::Client;
+use serde::Serialize;
+use tokio::{
+    sync::{mpsc, Barrier},
+    time::sleep,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use url::Url;
+
+use crate::{
+    constants::USER_AGENT,
+    metrics::Metrics,
+    models::{
+        CrawlReport, FailureKind, PageReport, PageStatus, RateLimitConfig, RetryConfig,
+        ScrapeParams,
+    },
+    outbound::OutboundGuard,
+    spider::Spider,
+};
+
+pub use canonical::Canonicalizer;
+pub use frontier::Frontier;
+use host_limiter::{HostLimiter, JobLimiter};
+pub use retry::{backoff_delay, is_retryable};
+use robots::RobotsCache;
+
+mod canonical;
+mod frontier;
+mod host_limiter;
+mod retry;
+mod robots;
+mod seeds;
+
+/// User agent whose robots.txt rules apply to a crawl.
+fn robots_user_agent(params: &ScrapeParams) -> String {
+    params
+        .fetch
+        .as_ref()
+        .and_then(|fetch| fetch.user_agent.clone())
+        .unwrap_or_else(|| USER_AGENT.to_string())
+}
+
+pub trait FetchError: Display {
+    fn failure_kind(&self) -> FailureKind;
+    fn http_status(&self) -> Option<u16>;
+    fn retry_after(&self) -> Option<Duration>;
+}
+
+pub struct Crawler {
+    host_limiter: Arc<HostLimiter>,
+    retry: RetryConfig,
+    crawling_concurrency: usize,
+    processing_concurrency: usize,
+    barrier: Arc<Barrier>,
+    active_spiders: Arc<AtomicUsize>,
+    robots: Arc<RobotsCache>,
+    metrics: Arc<Metrics>,
+}
+
+impl Crawler {
+    pub fn new(
+        rate_limit: RateLimitConfig,
+        retry: RetryConfig,
+        crawling_concurrency: usize,
+        processing_concurrency: usize,
+    ) -> Self {
+        let barrier = Arc::new(Barrier::new(3));
+        let metrics = Arc::new(Metrics::new());
+        let active_spiders = metrics.active_spiders();
+        let guard = Arc::new(OutboundGuard::default());
+        Self {
+            host_limiter: Arc::new(HostLimiter::new(rate_limit)),
+            retry,
+            crawling_concurrency,
+            processing_concurrency,
+            barrier,
+            active_spiders,
+            robots: Arc::new(RobotsCache::new(Self::client(&guard), guard)),
+            metrics,
+        }
+    }
+
+    /// Records retries and the frontier size into `metrics`, which also
+    /// reports the number of active spiders.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.active_spiders = metrics.active_spiders();
+        self.metrics = metrics;
+        self
+    }
+
+    /// Restricts robots.txt requests to what `guard` allows.
+    pub fn with_outbound_guard(mut self, guard: Arc<OutboundGuard>) -> Self {
+        self.robots = Arc::new(RobotsCache::new(Self::client(&guard), guard));
+        self
+    }
+
+    fn client(guard: &Arc<OutboundGuard>) -> Client {
+        let builder = Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(60));
+        guard.configure(builder, 10).build().unwrap_or_default()
+    }
+
+    pub async fn crawl<T, E>(
+        &self,
+        spider: Arc<dyn Spider<Item = T, Error = E>>,
+        params: ScrapeParams,
+        mut frontier: Frontier,
+        events: mpsc::UnboundedSender<PageReport>,
+    ) -> CrawlReport
+    where
+        T: Serialize + Send + 'static,
+        E: FetchError + Send + 'static,
+    {
+        let mut report = CrawlReport::default();
+        let crawling_queue_capacity = self.crawling_concurrency * 400;
+        let processing_queue_capacity = self.processing_concurrency * 10;
+
+        let (urls_to_visit_tx, urls_to_visit_rx) = mpsc::channel::<String>(crawling_queue_capacity);
+        let (items_tx, items_rx) = mpsc::channel(processing_queue_capacity);
+        let (new_urls_tx, mut new_urls_rx) = mpsc::channel(crawling_queue_capacity);
+
+        let mut start_urls = spider.start_urls();
+        if let Some(seeds) = &params.seeds {
+            let user_agent = robots_user_agent(&params);
+            let robots = (!params.ignore_robots_txt).then(|| (self.robots.as_ref(), &*user_agent));
+            start_urls.extend(seeds::expand(spider.seed_fetcher(), robots, seeds).await);
+        }
+
+        for url in start_urls {
+            frontier.push(url, 0);
+        }
+
+        self.launch_processors(spider.clone(), items_rx);
+
+        self.launch_scrapers(
+            spider.clone(),
+            urls_to_visit_rx,
+            new_urls_tx.clone(),
+            items_tx,
+            params,
+        );
+
+        let mut reported_frontier = 0;
+        loop {
+            while let Ok(permit) = urls_to_visit_tx.try_reserve() {
+                match frontier.pop() {
+                    Some(url) => permit.send(url),
+                    None => break,
+                }
+            }
+
+            if let Ok((page, new_urls)) = new_urls_rx.try_recv() {
+                frontier.push_links(&page.url, new_urls);
+                let _ = events.send(page.clone());
+                report.pages.push(page);
+            }
+
+            let pending = frontier.len() as i64;
+            if pending != reported_frontier {
+                self.metrics.add_frontier_urls(pending - reported_frontier);
+                reported_frontier = pending;
+            }
+
+            if frontier.is_empty()
+                && new_urls_tx.capacity() == crawling_queue_capacity
+                && urls_to_visit_tx.capacity() == crawling_queue_capacity
+                && self.active_spiders.load(Ordering::SeqCst) == 0
+            {
+                break;
+            }
+
+            sleep(Duration::from_millis(5)).await;
+        }
+
+        self.metrics.add_frontier_urls(-reported_frontier);
+        drop(urls_to_visit_tx);
+
+        self.barrier.wait().await;
+
+        report.frontier = frontier.stats();
+        report
+    }
+
+    fn launch_processors<T, E>(
+        &self,
+        spider: Arc<dyn Spider<Item = T, Error = E>>,
+        items: mpsc::Receiver<T>,
+    ) where
+        T: Serialize + Send + 'static,
+        E: Send + 'static,
+    {
+        let concurrency = self.processing_concurrency;
+        let barrier = self.barrier.clone();
+        tokio::spawn(async move {
+            ReceiverStream::new(items)
+                .for_each_concurrent(concurrency, |item| async {
+                    let _ = spider.process(item).await;
+                })
+                .await;
+
+            barrier.wait().await;
+        });
+    }
+
+    fn launch_scrapers<T, E>(
+        &self,
+        spider: Arc<dyn Spider<Item = T, Error = E>>,
+        urls_to_visit: mpsc::Receiver<String>,
+        new_urls_tx: mpsc::Sender<(PageReport, Vec<String>)>,
+        items_tx: mpsc::Sender<T>,
+        params: ScrapeParams,
+    ) where
+        T: Serialize + Send + 'static,
+        E: FetchError + Send + 'static,
+    {
+        let concurrency = self.crawling_concurrency;
+        let barrier = self.barrier.clone();
+        let active_spiders = self.active_spiders.clone();
+        let metrics = self.metrics.clone();
+        let robots = self.robots.clone();
+        let respect_robots_txt = !params.ignore_robots_txt;
+        let user_agent = robots_user_agent(&params);
+        let host_limiter = Arc::new(JobLimiter::new(
+            self.host_limiter.clone(),
+            params.rate_limit,
+        ));
+        let retry_config = params.retry.unwrap_or(self.retry);
+
+        tokio::spawn(async move {
+            tokio_stream::wrappers::ReceiverStream::new(urls_to_visit)
+                .for_each_concurrent(concurrency, |queued_url| {
+                    let queued_url = queued_url.clone();
+                    async {
+                        active_spiders.fetch_add(1, Ordering::SeqCst);
+
+                        let url = Url::parse(&queued_url).ok();
+                        let host = url
+                            .as_ref()
+                            .and_then(|url| url.host_str())
+                            .unwrap_or_default()
+                            .to_string();
+
+                        if let (true, Some(url)) = (respect_robots_txt, url.as_ref()) {
+                            let rules = robots.get(url, &user_agent).await;
+                            if !rules.is_allowed(&user_agent, &robots::robots_path(url)) {
+                                log::info!("Skipping {} (disallowed by robots.txt)", queued_url);
+                                let page =
+                                    PageReport::new(queued_url, PageStatus::DisallowedByRobots)
+                                        .with_reason("Disallowed by robots.txt");
+                                let _ = new_urls_tx.send((page, Vec::new())).await;
+                                active_spiders.fetch_sub(1, Ordering::SeqCst);
+                                return;
+                            }
+
+                            if let Some(crawl_delay) = rules.crawl_delay(&user_agent) {
+                                host_limiter.limit_interval(&host, crawl_delay).await;
+                            }
+                        }
+
+                        let mut attempts = 0;
+                        let res = loop {
+                            attempts += 1;
+                            let permit = host_limiter.acquire(&host).await;
+                            let res = spider.scrape(queued_url.clone()).await;
+                            drop(permit);
+
+                            let err = match res {
+                                Ok(scraped) => {
+                                    host_limiter.record_success(&host).await;
+                                    break Ok(scraped);
+                                }
+                                Err(err) => err,
+                            };
+
+                            let status = err.http_status();
+                            if matches!(status, Some(429 | 503)) {
+                                host_limiter
+                                    .record_throttled(&host, err.retry_after())
+                                    .await;
+                            }
+
+                            let kind = err.failure_kind();
+                            if attempts > retry_config.max_retries
+                                || !retry::is_retryable(kind, status)
+                            {
+                                break Err((err, kind, status));
+                            }
+
+                            let delay =
+                                retry::retry_delay(&retry_config, attempts, err.retry_after());
+                            log::warn!(
+                                "Attempt {} for {} failed ({}), retrying in {:?}",
+                                attempts,
+                                queued_url,
+                                err,
+                                delay
+                            );
+                            metrics.record_retry();
+                            sleep(delay).await;
+                        };
+
+                        let mut urls = Vec::new();
+                        let page = match res {
+                            Ok((items, new_urls)) => {
+                                for item in items {
+                                    let _ = items_tx.send(item).await;
+                                }
+                                urls = new_urls;
+                                PageReport::new(queued_url, PageStatus::Scraped)
+                            }
+                            Err((err, kind, status)) => {
+                                log::error!("{}", err);
+                                PageReport::new(queued_url, PageStatus::Failed)
+                                    .with_reason(err.to_string())
+                                    .with_failure(kind, status)
+                            }
+                        }
+                        .with_attempts(attempts);
+
+                        let _ = new_urls_tx.send((page, urls)).await;
+                        active_spiders.fetch_sub(1, Ordering::SeqCst);
+                    }
+                })
+                .await;
+
+            drop(items_tx);
+            barrier.wait().await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicU32;
+
+    use async_trait::async_trait;
+    use tokio::time::Instant;
+
+    use super::*;
+    use crate::{error::AppError, models::FetchConfig, spider::HttpFetcher};
+
+    /// Answers the first request with a `503` asking to come back in decades.
+    struct ThrottledSpider {
+        http: HttpFetcher,
+        attempts: AtomicU32,
+    }
+
+    #[async_trait]
+    impl Spider for ThrottledSpider {
+        type Item = String;
+        type Error = AppError;
+
+        fn name(&self) -> String {
+            String::from("throttled")
+        }
+
+        fn start_urls(&self) -> Vec<String> {
+            vec!["https://throttled.test/".to_string()]
+        }
+
+        fn seed_fetcher(&self) -> &HttpFetcher {
+            &self.http
+        }
+
+        async fn scrape(&self, url: String) -> Result<(Vec<String>, Vec<String>), AppError> {
+            if self.attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(AppError::HttpStatus {
+                    url,
+                    status: 503,
+                    retry_after: Some(Duration::from_secs(999_999_999)),
+                });
+            }
+            Ok((Vec::new(), Vec::new()))
+        }
+
+        async fn process(&self, _item: String) -> Result<(), AppError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn caps_retry_after_delays() {
+        let retry = RetryConfig::default();
+        let crawler = Crawler::new(RateLimitConfig::default(), retry, 2, 2);
+        let spider = Arc::new(ThrottledSpider {
+            http: HttpFetcher::new(
+                &FetchConfig::default(),
+                Arc::new(OutboundGuard::unrestricted()),
+            )
+            .unwrap(),
+            attempts: AtomicU32::new(0),
+        });
+        let params: ScrapeParams = serde_json::from_value(serde_json::json!({
+            "url": "https://throttled.test/",
+            "enableScraping": true,
+            "tags": [],
+            "enablePagination": false,
+            "ignoreRobotsTxt": true,
+        }))
+        .unwrap();
+        let frontier = Frontier::new(
+            &params.frontier,
+            Canonicalizer::new(params.canonical.clone()),
+        )
+        .unwrap();
+
+        let started = Instant::now();
+        let (events, _) = mpsc::unbounded_channel();
+        let report = crawler.crawl(spider, params, frontier, events).await;
+
+        assert_eq!(report.pages[0].status, PageStatus::Scraped);
+        assert_eq!(report.pages[0].attempts, 2);
+        let max_backoff = Duration::from_secs(RateLimitConfig::default().max_backoff_secs);
+        assert!(started.elapsed() <= max_backoff + Duration::from_secs(1));
+    }
+}
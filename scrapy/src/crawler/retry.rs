@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::models::{FailureKind, RetryConfig};
+
+pub fn is_retryable(kind: FailureKind, status: Option<u16>) -> bool {
+    match kind {
+        FailureKind::Timeout | FailureKind::Connect | FailureKind::ServerError => true,
+        FailureKind::ClientError => matches!(status, Some(408 | 429)),
+        FailureKind::Dns | FailureKind::Tls | FailureKind::Blocked | FailureKind::Other => false,
+    }
+}
+
+pub fn backoff_delay(config: &RetryConfig, attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    let ceiling = config
+        .base_delay_ms
+        .saturating_mul(1 << exponent)
+        .min(config.max_delay_ms);
+    let half = ceiling / 2;
+    let jitter = rand::thread_rng().gen_range(0..=ceiling - half);
+    Duration::from_millis(half + jitter)
+}
+
+/// Delay before retrying `attempt`, honouring the server's `Retry-After` up
+/// to the configured maximum delay.
+pub fn retry_delay(config: &RetryConfig, attempt: u32, retry_after: Option<Duration>) -> Duration {
+    let max_delay = Duration::from_millis(config.max_delay_ms);
+    backoff_delay(config, attempt).max(retry_after.unwrap_or_default().min(max_delay))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_and_is_capped() {
+        let config = RetryConfig {
+            max_retries: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 1_000,
+        };
+
+        for _ in 0..100 {
+            let first = backoff_delay(&config, 1);
+            assert!(first >= Duration::from_millis(50) && first <= Duration::from_millis(100));
+
+            let third = backoff_delay(&config, 3);
+            assert!(third >= Duration::from_millis(200) && third <= Duration::from_millis(400));
+
+            let capped = backoff_delay(&config, 30);
+            assert!(capped >= Duration::from_millis(500) && capped <= Duration::from_millis(1_000));
+        }
+
+        let retry_after = Some(Duration::from_secs(999_999_999));
+        assert_eq!(
+            retry_delay(&config, 1, retry_after),
+            Duration::from_millis(1_000)
+        );
+    }
+
+    #[test]
+    fn only_transient_failures_are_retried() {
+        assert!(is_retryable(FailureKind::Timeout, None));
+        assert!(is_retryable(FailureKind::ServerError, Some(502)));
+        assert!(is_retryable(FailureKind::ClientError, Some(429)));
+        assert!(!is_retryable(FailureKind::ClientError, Some(404)));
+        assert!(!is_retryable(FailureKind::Dns, None));
+        assert!(!is_retryable(FailureKind::Tls, None));
+    }
+}
@@ -0,0 +1,462 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use reqwest::{header, Client, Response};
+use tokio::sync::{Mutex, OnceCell};
+use url::Url;
+
+use crate::outbound::OutboundGuard;
+
+const MAX_ROBOTS_TXT_BYTES: usize = 500 * 1024;
+/// How long a fetched robots.txt is used before it is fetched again.
+const ROBOTS_TXT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct RobotsTxt {
+    groups: Vec<Group>,
+}
+
+impl RobotsTxt {
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    pub fn disallow_all() -> Self {
+        Self {
+            groups: vec![Group {
+                agents: vec!["*".to_string()],
+                rules: vec![Rule {
+                    allow: false,
+                    pattern: "/".to_string(),
+                }],
+                crawl_delay: None,
+            }],
+        }
+    }
+
+    pub fn parse(body: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        let mut current: Option<Group> = None;
+        let mut in_agent_block = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            match key.as_str() {
+                "user-agent" => {
+                    if !in_agent_block {
+                        groups.extend(current.take());
+                    }
+                    let group = current.get_or_insert_with(Group::default);
+                    group.agents.push(value.to_ascii_lowercase());
+                    in_agent_block = true;
+                }
+                "allow" | "disallow" => {
+                    in_agent_block = false;
+                    if let Some(group) = current.as_mut() {
+                        if !value.is_empty() {
+                            group.rules.push(Rule {
+                                allow: key == "allow",
+                                pattern: value.to_string(),
+                            });
+                        }
+                    }
+                }
+                "crawl-delay" => {
+                    in_agent_block = false;
+                    if let Some(group) = current.as_mut() {
+                        group.crawl_delay = value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                            .map(Duration::from_secs_f64);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        groups.extend(current);
+        Self { groups }
+    }
+
+    fn groups_for(&self, user_agent: &str) -> Vec<&Group> {
+        let token = user_agent
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        let specific: Vec<&Group> = self
+            .groups
+            .iter()
+            .filter(|group| group.agents.contains(&token))
+            .collect();
+
+        if !specific.is_empty() {
+            return specific;
+        }
+
+        self.groups
+            .iter()
+            .filter(|group| group.agents.iter().any(|agent| agent == "*"))
+            .collect()
+    }
+
+    pub fn is_allowed(&self, user_agent: &str, path: &str) -> bool {
+        if path == "/robots.txt" {
+            return true;
+        }
+
+        let mut best: Option<&Rule> = None;
+
+        for rule in self
+            .groups_for(user_agent)
+            .into_iter()
+            .flat_map(|group| &group.rules)
+        {
+            if !matches(&rule.pattern, path) {
+                continue;
+            }
+
+            best = match best {
+                Some(current)
+                    if current.pattern.len() > rule.pattern.len()
+                        || (current.pattern.len() == rule.pattern.len() && current.allow) =>
+                {
+                    Some(current)
+                }
+                _ => Some(rule),
+            };
+        }
+
+        best.is_none_or(|rule| rule.allow)
+    }
+
+    pub fn crawl_delay(&self, user_agent: &str) -> Option<Duration> {
+        self.groups_for(user_agent)
+            .into_iter()
+            .filter_map(|group| group.crawl_delay)
+            .max()
+    }
+}
+
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    if parts.is_empty() {
+        return !anchored || rest.is_empty();
+    }
+
+    for (i, part) in parts.iter().enumerate() {
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+pub fn robots_path(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+struct Entry {
+    created: Instant,
+    robots: OnceCell<Arc<RobotsTxt>>,
+}
+
+/// robots.txt files by origin and user agent, shared by every crawl and
+/// refetched once they are older than the TTL. Each file is fetched with the
+/// user agent its rules are looked up for, as sites may serve bots different
+/// files.
+pub struct RobotsCache {
+    client: Client,
+    guard: Arc<OutboundGuard>,
+    ttl: Duration,
+    entries: Mutex<HashMap<String, Arc<Entry>>>,
+}
+
+impl RobotsCache {
+    pub fn new(client: Client, guard: Arc<OutboundGuard>) -> Self {
+        Self {
+            client,
+            guard,
+            ttl: ROBOTS_TXT_TTL,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn get(&self, url: &Url, user_agent: &str) -> Arc<RobotsTxt> {
+        let origin = url.origin().ascii_serialization();
+        let key = format!("{} {}", origin, user_agent);
+        let entry = {
+            let mut entries = self.entries.lock().await;
+            let now = Instant::now();
+            let fresh = |entry: &Entry| now.duration_since(entry.created) < self.ttl;
+            match entries.get(&key).filter(|entry| fresh(entry)) {
+                Some(entry) => entry.clone(),
+                None => {
+                    entries.retain(|_, entry| fresh(entry));
+                    let entry = Arc::new(Entry {
+                        created: now,
+                        robots: OnceCell::new(),
+                    });
+                    entries.insert(key, entry.clone());
+                    entry
+                }
+            }
+        };
+
+        entry
+            .robots
+            .get_or_init(|| async { Arc::new(self.fetch(&origin, user_agent).await) })
+            .await
+            .clone()
+    }
+
+    async fn fetch(&self, origin: &str, user_agent: &str) -> RobotsTxt {
+        let robots_url = format!("{}/robots.txt", origin);
+        log::debug!("Fetching {}", robots_url);
+
+        // The page itself is refused by the fetcher and reported there.
+        if let Err(err) = self.guard.check(&robots_url) {
+            log::warn!("Not fetching {}: {}", robots_url, err);
+            return RobotsTxt::allow_all();
+        }
+
+        let request = self
+            .client
+            .get(&robots_url)
+            .header(header::USER_AGENT, user_agent);
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(err) => {
+                log::warn!("Failed to fetch {}: {}", robots_url, err);
+                return RobotsTxt::allow_all();
+            }
+        };
+
+        let status = response.status();
+        if status.is_server_error() {
+            log::warn!(
+                "{} returned {}, treating host as disallowed",
+                robots_url,
+                status
+            );
+            return RobotsTxt::disallow_all();
+        }
+        if !status.is_success() {
+            return RobotsTxt::allow_all();
+        }
+
+        match read_capped(response).await {
+            Ok(body) => RobotsTxt::parse(&String::from_utf8_lossy(&body)),
+            Err(err) => {
+                log::warn!("Failed to read {}: {}", robots_url, err);
+                RobotsTxt::allow_all()
+            }
+        }
+    }
+}
+
+/// Reads the body up to [`MAX_ROBOTS_TXT_BYTES`], ignoring the rest without
+/// downloading it.
+async fn read_capped(mut response: Response) -> reqwest::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        let room = MAX_ROBOTS_TXT_BYTES - body.len();
+        body.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        if body.len() == MAX_ROBOTS_TXT_BYTES {
+            break;
+        }
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    const ROBOTS: &str = r#"
+User-agent: *
+Disallow: /private/
+Allow: /private/public-*.html$
+Crawl-delay: 2
+
+User-agent: Scrapy
+User-agent: other-bot
+Disallow: /search
+Crawl-delay: 0.5
+"#;
+
+    #[test]
+    fn matches_most_specific_group() {
+        let robots = RobotsTxt::parse(ROBOTS);
+
+        assert!(!robots.is_allowed("scrapy/0.1", "/search?q=rust"));
+        assert!(robots.is_allowed("scrapy/0.1", "/private/page"));
+        assert_eq!(
+            robots.crawl_delay("scrapy/0.1"),
+            Some(Duration::from_millis(500))
+        );
+
+        assert!(!robots.is_allowed("unknown/1.0", "/private/page"));
+        assert!(robots.is_allowed("unknown/1.0", "/private/public-1.html"));
+        assert!(!robots.is_allowed("unknown/1.0", "/private/public-1.html?x"));
+        assert_eq!(
+            robots.crawl_delay("unknown/1.0"),
+            Some(Duration::from_secs(2))
+        );
+    }
+
+    #[test]
+    fn empty_disallow_allows_everything() {
+        let robots = RobotsTxt::parse("User-agent: *\nDisallow:\n");
+        assert!(robots.is_allowed("scrapy", "/anything"));
+        assert!(!RobotsTxt::disallow_all().is_allowed("scrapy", "/anything"));
+    }
+
+    /// Serves a robots.txt disallowing `/v<n>`, where `n` counts the requests.
+    async fn counting_server() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut requests = 0;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                requests += 1;
+                let body = format!("User-agent: *\nDisallow: /v{}\n", requests);
+                tokio::spawn(async move {
+                    let mut request = [0u8; 4096];
+                    let _ = socket.read(&mut request).await;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        Url::parse(&format!("http://{}/", address)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn refetches_robots_txt_once_it_expires() {
+        let guard = Arc::new(OutboundGuard::unrestricted());
+        let site = counting_server().await;
+
+        let cache = RobotsCache::new(Client::new(), guard.clone());
+        assert!(!cache.get(&site, "scrapy").await.is_allowed("scrapy", "/v1"));
+        assert!(!cache.get(&site, "scrapy").await.is_allowed("scrapy", "/v1"));
+
+        let expiring = RobotsCache {
+            ttl: Duration::ZERO,
+            ..RobotsCache::new(Client::new(), guard)
+        };
+        assert!(!expiring
+            .get(&site, "scrapy")
+            .await
+            .is_allowed("scrapy", "/v2"));
+        assert!(!expiring
+            .get(&site, "scrapy")
+            .await
+            .is_allowed("scrapy", "/v3"));
+    }
+
+    /// Serves a robots.txt disallowing the requesting bot's name, padded past
+    /// the size limit and followed by a rule that must not be read. The
+    /// response announces more than it sends and is never finished.
+    async fn oversized_server() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = [0u8; 4096];
+                    let read = socket.read(&mut request).await.unwrap_or_default();
+                    let request = String::from_utf8_lossy(&request[..read]).to_string();
+                    let bot = request
+                        .lines()
+                        .find_map(|line| line.strip_prefix("user-agent: "))
+                        .and_then(|agent| agent.split('/').next())
+                        .unwrap_or_default()
+                        .to_string();
+
+                    let mut body = format!("User-agent: *\nDisallow: /{}\n", bot);
+                    while body.len() <= MAX_ROBOTS_TXT_BYTES {
+                        body.push_str(&format!("#{}\n", "-".repeat(1023)));
+                    }
+                    body.push_str("Disallow: /past-the-limit\n");
+                    let head = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                        body.len() * 4
+                    );
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(body.as_bytes()).await;
+                    let _ = socket.read(&mut [0u8; 1]).await;
+                });
+            }
+        });
+        Url::parse(&format!("http://{}/", address)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn fetches_with_the_job_user_agent_and_stops_at_the_size_limit() {
+        let site = oversized_server().await;
+        let cache = RobotsCache::new(Client::new(), Arc::new(OutboundGuard::unrestricted()));
+
+        let robots = tokio::time::timeout(Duration::from_secs(10), cache.get(&site, "my-bot/2.0"))
+            .await
+            .expect("robots.txt read past the size limit");
+        assert!(!robots.is_allowed("my-bot/2.0", "/my-bot"));
+        assert!(robots.is_allowed("my-bot/2.0", "/past-the-limit"));
+
+        let robots = cache.get(&site, "other-bot/1.0").await;
+        assert!(!robots.is_allowed("other-bot/1.0", "/other-bot"));
+        assert!(robots.is_allowed("other-bot/1.0", "/my-bot"));
+    }
+}
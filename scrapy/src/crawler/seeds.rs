@@ -0,0 +1,418 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    io::Read,
+};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use flate2::read::GzDecoder;
+use roxmltree::{Document, Node};
+use url::Url;
+
+use super::robots::{self, RobotsCache};
+use crate::{error::AppError, models::SeedConfig, spider::HttpFetcher};
+
+const MAX_DOCUMENT_BYTES: usize = 50 * 1024 * 1024;
+const MAX_INDEX_DEPTH: usize = 3;
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+#[derive(Debug, PartialEq)]
+struct SeedEntry {
+    url: String,
+    modified: Option<DateTime<Utc>>,
+}
+
+impl SeedEntry {
+    fn changed_since(&self, since: Option<DateTime<Utc>>) -> bool {
+        match (self.modified, since) {
+            (Some(modified), Some(since)) => modified >= since,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum SeedDocument {
+    SitemapIndex(Vec<SeedEntry>),
+    Pages(Vec<SeedEntry>),
+}
+
+/// Expands sitemaps, sitemap indexes and RSS/Atom feeds into page URLs.
+/// Entries without a modification date are always kept. With `robots`, seed
+/// documents disallowed for the given user agent are skipped.
+pub async fn expand(
+    http: &HttpFetcher,
+    robots: Option<(&RobotsCache, &str)>,
+    config: &SeedConfig,
+) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut seen = HashSet::new();
+    let mut pending: VecDeque<(String, usize)> =
+        config.urls.iter().map(|url| (url.clone(), 0)).collect();
+
+    while let Some((source, depth)) = pending.pop_front() {
+        if !seen.insert(source.clone()) {
+            continue;
+        }
+
+        let document = match fetch_document(http, robots, &source).await {
+            Ok(document) => document,
+            Err(err) => {
+                log::warn!("Skipping seed {}: {}", source, err);
+                continue;
+            }
+        };
+
+        match document {
+            SeedDocument::SitemapIndex(entries) => {
+                if depth >= MAX_INDEX_DEPTH {
+                    log::warn!("Skipping sitemap index {} (nested too deeply)", source);
+                    continue;
+                }
+                pending.extend(
+                    entries
+                        .into_iter()
+                        .filter(|entry| entry.changed_since(config.changed_since))
+                        .map(|entry| (entry.url, depth + 1)),
+                );
+            }
+            SeedDocument::Pages(entries) => {
+                for entry in entries {
+                    if urls.len() >= config.max_urls {
+                        log::warn!("Seed limit of {} URLs reached", config.max_urls);
+                        return urls;
+                    }
+                    if entry.changed_since(config.changed_since) {
+                        urls.push(entry.url);
+                    }
+                }
+            }
+        }
+    }
+
+    log::info!(
+        "Expanded {} seed documents into {} URLs",
+        seen.len(),
+        urls.len()
+    );
+    urls
+}
+
+async fn fetch_document(
+    http: &HttpFetcher,
+    robots: Option<(&RobotsCache, &str)>,
+    url: &str,
+) -> Result<SeedDocument, AppError> {
+    let seed_error = |reason: String| AppError::Seed {
+        url: url.to_string(),
+        reason,
+    };
+    let too_large = || AppError::ResponseTooLarge {
+        url: url.to_string(),
+        limit: MAX_DOCUMENT_BYTES,
+    };
+
+    let source = Url::parse(url).map_err(|err| seed_error(err.to_string()))?;
+    if let Some((cache, user_agent)) = robots {
+        let rules = cache.get(&source, user_agent).await;
+        if !rules.is_allowed(user_agent, &robots::robots_path(&source)) {
+            return Err(seed_error("disallowed by robots.txt".to_string()));
+        }
+    }
+    let mut res = http.get(url).await?;
+    if !res.status().is_success() {
+        return Err(AppError::HttpStatus {
+            url: url.to_string(),
+            status: res.status().as_u16(),
+            retry_after: None,
+        });
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = res.chunk().await? {
+        if body.len() + chunk.len() > MAX_DOCUMENT_BYTES {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    if body.starts_with(&GZIP_MAGIC) {
+        let mut decoded = Vec::new();
+        GzDecoder::new(body.as_slice())
+            .take(MAX_DOCUMENT_BYTES as u64 + 1)
+            .read_to_end(&mut decoded)?;
+        if decoded.len() > MAX_DOCUMENT_BYTES {
+            return Err(too_large());
+        }
+        body = decoded;
+    }
+
+    parse_document(&source, &String::from_utf8_lossy(&body)).map_err(seed_error)
+}
+
+fn parse_document(source: &Url, body: &str) -> Result<SeedDocument, String> {
+    let document = Document::parse(body).map_err(|err| err.to_string())?;
+    let root = document.root_element();
+
+    let entries = match root.tag_name().name() {
+        "sitemapindex" => {
+            return Ok(SeedDocument::SitemapIndex(sitemap_entries(
+                source, root, "sitemap",
+            )))
+        }
+        "urlset" => sitemap_entries(source, root, "url"),
+        "rss" | "RDF" => rss_entries(source, root),
+        "feed" => atom_entries(source, root),
+        other => return Err(format!("unsupported root element <{}>", other)),
+    };
+
+    Ok(SeedDocument::Pages(entries))
+}
+
+fn sitemap_entries(source: &Url, root: Node, element: &str) -> Vec<SeedEntry> {
+    elements(root, element)
+        .filter_map(|node| {
+            Some(SeedEntry {
+                url: resolve(source, child_text(node, "loc")?)?,
+                modified: child_text(node, "lastmod").and_then(parse_date),
+            })
+        })
+        .collect()
+}
+
+fn rss_entries(source: &Url, root: Node) -> Vec<SeedEntry> {
+    root.descendants()
+        .filter(|node| node.tag_name().name() == "item")
+        .filter_map(|node| {
+            Some(SeedEntry {
+                url: resolve(source, child_text(node, "link")?)?,
+                modified: ["pubDate", "date", "updated"]
+                    .into_iter()
+                    .find_map(|name| child_text(node, name).and_then(parse_date)),
+            })
+        })
+        .collect()
+}
+
+fn atom_entries(source: &Url, root: Node) -> Vec<SeedEntry> {
+    elements(root, "entry")
+        .filter_map(|node| {
+            let href = elements(node, "link")
+                .find(|link| link.attribute("rel").is_none_or(|rel| rel == "alternate"))?
+                .attribute("href")?;
+            Some(SeedEntry {
+                url: resolve(source, href)?,
+                modified: ["updated", "published"]
+                    .into_iter()
+                    .find_map(|name| child_text(node, name).and_then(parse_date)),
+            })
+        })
+        .collect()
+}
+
+fn elements<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.is_element() && child.tag_name().name() == name)?
+        .text()
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+fn resolve(source: &Url, link: &str) -> Option<String> {
+    let url = source.join(link).ok()?;
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) =
+        DateTime::parse_from_rfc3339(value).or_else(|_| DateTime::parse_from_rfc2822(value))
+    {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(date) = DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M%:z") {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S") {
+        return Some(date.and_utc());
+    }
+
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)
+        .map(|date| date.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use chrono::TimeZone;
+    use reqwest::Client;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    use super::*;
+    use crate::{models::FetchConfig, outbound::OutboundGuard};
+
+    fn source() -> Url {
+        Url::parse("https://example.com/sitemap.xml").unwrap()
+    }
+
+    #[test]
+    fn parses_sitemaps_and_indexes() {
+        let sitemap = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <url><loc>https://example.com/a</loc><lastmod>2024-03-01</lastmod></url>
+              <url><loc> /b </loc></url>
+            </urlset>"#;
+        assert_eq!(
+            parse_document(&source(), sitemap).unwrap(),
+            SeedDocument::Pages(vec![
+                SeedEntry {
+                    url: "https://example.com/a".to_string(),
+                    modified: Some(Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap()),
+                },
+                SeedEntry {
+                    url: "https://example.com/b".to_string(),
+                    modified: None,
+                },
+            ])
+        );
+
+        let index = r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+              <sitemap><loc>https://example.com/posts.xml.gz</loc><lastmod>2024-03-01T10:00:00+00:00</lastmod></sitemap>
+            </sitemapindex>"#;
+        assert!(matches!(
+            parse_document(&source(), index).unwrap(),
+            SeedDocument::SitemapIndex(entries) if entries[0].url == "https://example.com/posts.xml.gz"
+        ));
+
+        assert!(parse_document(&source(), "<html></html>").is_err());
+    }
+
+    #[test]
+    fn parses_feeds_and_filters_by_date() {
+        let rss = r#"<rss version="2.0"><channel>
+              <item><link>https://example.com/old</link><pubDate>Mon, 01 Jan 2024 00:00:00 GMT</pubDate></item>
+              <item><link>https://example.com/new</link><pubDate>Fri, 01 Mar 2024 00:00:00 GMT</pubDate></item>
+            </channel></rss>"#;
+        let atom = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+              <entry>
+                <link rel="edit" href="/edit/1"/>
+                <link href="/posts/1"/>
+                <updated>2024-03-02T12:00:00Z</updated>
+              </entry>
+            </feed>"#;
+        let since = Some(Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap());
+
+        let changed = |document| match document {
+            SeedDocument::Pages(entries) => entries
+                .into_iter()
+                .filter(|entry| entry.changed_since(since))
+                .map(|entry| entry.url)
+                .collect::<Vec<_>>(),
+            SeedDocument::SitemapIndex(_) => panic!("expected pages"),
+        };
+
+        assert_eq!(
+            changed(parse_document(&source(), rss).unwrap()),
+            vec!["https://example.com/new"]
+        );
+        assert_eq!(
+            changed(parse_document(&source(), atom).unwrap()),
+            vec!["https://example.com/posts/1"]
+        );
+    }
+
+    /// Serves a robots.txt, a sitemap index and its sitemaps, and forwards
+    /// each request head to the returned receiver.
+    async fn sitemap_server() -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let (requests, received) = mpsc::unbounded_channel();
+        let site = base.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let requests = requests.clone();
+                let site = site.clone();
+                tokio::spawn(async move {
+                    let mut request = [0u8; 4096];
+                    let read = socket.read(&mut request).await.unwrap_or(0);
+                    let head = String::from_utf8_lossy(&request[..read]).to_lowercase();
+                    let path = head
+                        .split_whitespace()
+                        .nth(1)
+                        .unwrap_or_default()
+                        .to_string();
+                    let _ = requests.send(head);
+                    let body = match path.as_str() {
+                        "/robots.txt" => "User-agent: *\nDisallow: /private\n".to_string(),
+                        "/index.xml" => format!(
+                            "<sitemapindex><sitemap><loc>{0}/pages.xml</loc></sitemap>\
+                             <sitemap><loc>{0}/private.xml</loc></sitemap></sitemapindex>",
+                            site
+                        ),
+                        _ => format!("<urlset><url><loc>{}/page</loc></url></urlset>", site),
+                    };
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        (base, received)
+    }
+
+    #[tokio::test]
+    async fn fetches_seeds_with_the_job_settings_and_robots_rules() {
+        let (site, mut requests) = sitemap_server().await;
+        let guard = Arc::new(OutboundGuard::unrestricted());
+        let http = HttpFetcher::new(
+            &FetchConfig {
+                headers: BTreeMap::from([("x-job".to_string(), "1".to_string())]),
+                user_agent: Some("job-agent/1.0".to_string()),
+                ..FetchConfig::default()
+            },
+            guard.clone(),
+        )
+        .unwrap();
+        let robots = RobotsCache::new(Client::new(), guard);
+        let config = SeedConfig {
+            urls: vec![format!("{}/index.xml", site)],
+            changed_since: None,
+            max_urls: 10,
+        };
+
+        let urls = expand(&http, Some((&robots, "job-agent/1.0")), &config).await;
+        assert_eq!(urls, vec![format!("{}/page", site)]);
+
+        let mut fetched = Vec::new();
+        while let Ok(head) = requests.try_recv() {
+            let path = head
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .to_string();
+            if path != "/robots.txt" {
+                assert!(head.contains("user-agent: job-agent/1.0"), "{}", head);
+                assert!(head.contains("x-job: 1"), "{}", head);
+            }
+            fetched.push(path);
+        }
+        assert!(!fetched.contains(&"/private.xml".to_string()));
+        assert!(fetched.contains(&"/pages.xml".to_string()));
+    }
+}
@@ -1,11 +1,92 @@
+use std::time::Duration;
+
 use thiserror::Error;
 
-use crate::models::WebSocketMessage;
+use crate::{
+    crawler::FetchError,
+    models::{FailureKind, WebSocketMessage},
+    outbound::Blocked,
+    validation::{self, FieldError},
+};
+
+pub use response::{api_catcher, ApiError, RequestId};
+
+mod response;
 
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("HTTP error: {0}")]
-    Http(#[from] reqwest::Error),
+    Http(reqwest::Error),
+
+    #[error("Blocked request to {url}: {reason}")]
+    Blocked { url: String, reason: String },
+
+    #[error("HTTP status {status} from {url}")]
+    HttpStatus {
+        url: String,
+        status: u16,
+        retry_after: Option<Duration>,
+    },
+
+    #[error("Response from {url} exceeds {limit} bytes")]
+    ResponseTooLarge { url: String, limit: usize },
+
+    #[error("Invalid fetch configuration: {0}")]
+    FetchConfig(String),
+
+    #[error("Session error: {0}")]
+    Session(String),
+
+    #[error("Browser error: {0}")]
+    Browser(String),
+
+    #[error("Timed out rendering {0}")]
+    RenderTimeout(String),
+
+    #[error("Invalid seed document {url}: {reason}")]
+    Seed { url: String, reason: String },
+
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+
+    #[error("Invalid pattern: {0}")]
+    Pattern(#[from] regex::Error),
+
+    #[error("Invalid request: {0}")]
+    BadRequest(String),
+
+    #[error("Invalid crawl parameters: {}", validation::describe(.0))]
+    Validation(Vec<FieldError>),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Job error: {0}")]
+    Job(String),
+
+    #[error("Invalid schedule: {0}")]
+    Schedule(String),
+
+    #[error("Invalid job spec: {0}")]
+    Spec(String),
+
+    #[error("Invalid configuration: {0}")]
+    Config(String),
+
+    #[error("Unauthorized: {0}")]
+    Auth(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Quota exceeded: {0}")]
+    Quota(String),
+
+    #[error("Budget exhausted: {0}")]
+    Budget(String),
+
+    #[error("Credential vault error: {0}")]
+    Vault(String),
 
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -20,6 +101,102 @@ pub enum AppError {
     WebSocket(#[from] WebSocketError),
 }
 
+fn classify_http_error(err: &reqwest::Error) -> FailureKind {
+    if let Some(status) = err.status() {
+        return classify_status(status.as_u16());
+    }
+    if err.is_timeout() {
+        return FailureKind::Timeout;
+    }
+
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+            if io.kind() == std::io::ErrorKind::TimedOut {
+                return FailureKind::Timeout;
+            }
+        }
+
+        let message = cause.to_string().to_ascii_lowercase();
+        if message.contains("dns error") || message.contains("failed to lookup address") {
+            return FailureKind::Dns;
+        }
+        if message.contains("certificate")
+            || message.contains("tls")
+            || message.contains("ssl")
+            || message.contains("handshake")
+        {
+            return FailureKind::Tls;
+        }
+        source = cause.source();
+    }
+
+    if err.is_connect() {
+        FailureKind::Connect
+    } else {
+        FailureKind::Other
+    }
+}
+
+fn classify_status(status: u16) -> FailureKind {
+    match status {
+        400..=499 => FailureKind::ClientError,
+        500..=599 => FailureKind::ServerError,
+        _ => FailureKind::Other,
+    }
+}
+
+impl FetchError for AppError {
+    fn failure_kind(&self) -> FailureKind {
+        match self {
+            AppError::Http(err) => classify_http_error(err),
+            AppError::HttpStatus { status, .. } => classify_status(*status),
+            AppError::RenderTimeout(_) => FailureKind::Timeout,
+            AppError::Blocked { .. } => FailureKind::Blocked,
+            _ => FailureKind::Other,
+        }
+    }
+
+    fn http_status(&self) -> Option<u16> {
+        match self {
+            AppError::Http(err) => err.status().map(|status| status.as_u16()),
+            AppError::HttpStatus { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            AppError::HttpStatus { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    /// Connections refused by the outbound guard surface as `reqwest` errors
+    /// and are turned back into [`AppError::Blocked`].
+    fn from(err: reqwest::Error) -> Self {
+        let mut source = std::error::Error::source(&err);
+        while let Some(cause) = source {
+            if let Some(blocked) = cause.downcast_ref::<Blocked>() {
+                return AppError::Blocked {
+                    url: err.url().map(|url| url.to_string()).unwrap_or_default(),
+                    reason: blocked.to_string(),
+                };
+            }
+            source = cause.source();
+        }
+        AppError::Http(err)
+    }
+}
+
+impl From<chromiumoxide::error::CdpError> for AppError {
+    fn from(err: chromiumoxide::error::CdpError) -> Self {
+        AppError::Browser(err.to_string())
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum WebSocketError {
     #[error("Failed to send message: {0}")]
@@ -0,0 +1,347 @@
+use rocket::{
+    catch,
+    fairing::AdHoc,
+    http::{Header, Status},
+    response::{self, Responder},
+    serde::json::Json,
+    Request,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{crawler::FetchError, models::FailureKind, openapi::ApiSchema};
+
+use super::AppError;
+
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Identifies a request in error bodies, the `X-Request-Id` response header
+/// and the server log. A well-formed `X-Request-Id` sent by the client is
+/// kept, so requests can be traced through a proxy.
+pub struct RequestId(String);
+
+impl RequestId {
+    pub fn of<'r>(request: &'r Request<'_>) -> &'r str {
+        &request
+            .local_cache(|| {
+                let id = request
+                    .headers()
+                    .get_one(REQUEST_ID_HEADER)
+                    .filter(|id| {
+                        !id.is_empty()
+                            && id.len() <= 64
+                            && id
+                                .chars()
+                                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+                    })
+                    .map_or_else(|| Uuid::new_v4().to_string(), String::from);
+                RequestId(id)
+            })
+            .0
+    }
+
+    /// Sets the `X-Request-Id` header on every response.
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_response("Request ID", |request, response| {
+            Box::pin(async move {
+                response.set_header(Header::new(
+                    REQUEST_ID_HEADER,
+                    RequestId::of(request).to_string(),
+                ));
+            })
+        })
+    }
+}
+
+/// The JSON body of every failed API request.
+#[derive(Debug, Clone, Serialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiError {
+    /// Stable, machine-readable error code such as `quota_exceeded`.
+    pub code: String,
+    pub message: String,
+    pub details: Option<Value>,
+    pub request_id: String,
+}
+
+/// An error kept by a request guard for [`api_catcher`].
+struct CaughtError(Option<ApiError>);
+
+impl AppError {
+    pub fn status(&self) -> Status {
+        match self {
+            AppError::BadRequest(_)
+            | AppError::Validation(_)
+            | AppError::FetchConfig(_)
+            | AppError::Csv(_)
+            | AppError::Pattern(_)
+            | AppError::Job(_)
+            | AppError::Schedule(_)
+            | AppError::Spec(_)
+            | AppError::Vault(_) => Status::BadRequest,
+            AppError::Auth(_) => Status::Unauthorized,
+            AppError::Forbidden(_) | AppError::Blocked { .. } => Status::Forbidden,
+            AppError::NotFound(_) => Status::NotFound,
+            AppError::Budget(_) => Status::PaymentRequired,
+            AppError::Quota(_) => Status::TooManyRequests,
+            AppError::RenderTimeout(_) => Status::GatewayTimeout,
+            AppError::Http(_) if self.failure_kind() == FailureKind::Timeout => {
+                Status::GatewayTimeout
+            }
+            AppError::Http(_)
+            | AppError::HttpStatus { .. }
+            | AppError::ResponseTooLarge { .. }
+            | AppError::Session(_)
+            | AppError::Browser(_)
+            | AppError::Seed { .. }
+            | AppError::AI(_) => Status::BadGateway,
+            AppError::Config(_)
+            | AppError::Io(_)
+            | AppError::Serialization(_)
+            | AppError::WebSocket(_) => Status::InternalServerError,
+        }
+    }
+
+    /// A stable identifier of the kind of error, for clients to match on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Http(_) if self.failure_kind() == FailureKind::Timeout => "upstream_timeout",
+            AppError::Http(_) => "upstream_unreachable",
+            AppError::Blocked { .. } => "destination_blocked",
+            AppError::HttpStatus { .. } => "upstream_status",
+            AppError::ResponseTooLarge { .. } => "upstream_response_too_large",
+            AppError::FetchConfig(_) => "invalid_fetch_config",
+            AppError::Session(_) => "session_failed",
+            AppError::Browser(_) => "browser_failed",
+            AppError::RenderTimeout(_) => "render_timeout",
+            AppError::Seed { .. } => "invalid_seed",
+            AppError::Csv(_) => "invalid_csv",
+            AppError::Pattern(_) => "invalid_pattern",
+            AppError::BadRequest(_) => "invalid_request",
+            AppError::Validation(_) => "validation_failed",
+            AppError::NotFound(_) => "not_found",
+            AppError::Job(_) => "invalid_job",
+            AppError::Schedule(_) => "invalid_schedule",
+            AppError::Spec(_) => "invalid_spec",
+            AppError::Config(_) => "invalid_config",
+            AppError::Auth(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::Quota(_) => "quota_exceeded",
+            AppError::Budget(_) => "budget_exhausted",
+            AppError::Vault(_) => "credential_error",
+            AppError::Io(_) => "io_error",
+            AppError::Serialization(_) => "serialization_error",
+            AppError::AI(_) => "ai_provider_error",
+            AppError::WebSocket(_) => "websocket_error",
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        match self {
+            AppError::Http(err) => err.url().map(|url| json!({ "url": url.as_str() })),
+            AppError::HttpStatus {
+                url,
+                status,
+                retry_after,
+            } => Some(json!({
+                "url": url,
+                "status": status,
+                "retryAfterSecs": retry_after.map(|delay| delay.as_secs()),
+            })),
+            AppError::ResponseTooLarge { url, limit } => {
+                Some(json!({ "url": url, "limit": limit }))
+            }
+            AppError::RenderTimeout(url) => Some(json!({ "url": url })),
+            AppError::Blocked { url, reason } => Some(json!({ "url": url, "reason": reason })),
+            AppError::Seed { url, reason } => Some(json!({ "url": url, "reason": reason })),
+            AppError::Validation(errors) => Some(json!({ "fields": errors })),
+            _ => None,
+        }
+    }
+
+    /// The response body for this error. Server-side failures are not
+    /// described to the client; their cause is only logged.
+    pub fn to_api_error(&self, request: &Request<'_>) -> ApiError {
+        let internal = self.status() == Status::InternalServerError;
+        ApiError {
+            code: self.code().to_string(),
+            message: if internal {
+                "Internal server error".to_string()
+            } else {
+                self.to_string()
+            },
+            details: if internal { None } else { self.details() },
+            request_id: RequestId::of(request).to_string(),
+        }
+    }
+
+    /// Keeps this error for [`api_catcher`], since Rocket does not pass the
+    /// errors of failed request guards on to catchers.
+    pub fn catch(&self, request: &Request<'_>) {
+        request.local_cache(|| CaughtError(Some(self.to_api_error(request))));
+    }
+}
+
+fn log(request: &Request<'_>, status: Status, message: &dyn std::fmt::Display) {
+    let id = RequestId::of(request);
+    if status.code >= 500 {
+        log::error!(
+            "[{}] {} {} failed: {}",
+            id,
+            request.method(),
+            request.uri(),
+            message
+        );
+    } else {
+        log::warn!(
+            "[{}] {} {} rejected: {}",
+            id,
+            request.method(),
+            request.uri(),
+            message
+        );
+    }
+}
+
+impl<'r> Responder<'r, 'static> for AppError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let status = self.status();
+        log(request, status, &self);
+        (status, Json(self.to_api_error(request))).respond_to(request)
+    }
+}
+
+/// Answers requests no route handled, and failed request guards or bodies,
+/// with an [`ApiError`] body.
+#[catch(default)]
+pub fn api_catcher(status: Status, request: &Request<'_>) -> (Status, Json<ApiError>) {
+    if let CaughtError(Some(error)) = request.local_cache(|| CaughtError(None)) {
+        log(request, status, &error.message);
+        return (status, Json(error.clone()));
+    }
+
+    let message = match status.code {
+        404 => format!("No route for {} {}", request.method(), request.uri()),
+        413 => "The request body is too large".to_string(),
+        400 | 422 => {
+            "The request body is malformed or does not match the expected format".to_string()
+        }
+        _ => status.reason_lossy().to_string(),
+    };
+    log(request, status, &message);
+    let error = ApiError {
+        code: status
+            .reason_lossy()
+            .to_ascii_lowercase()
+            .replace([' ', '-'], "_"),
+        message,
+        details: None,
+        request_id: RequestId::of(request).to_string(),
+    };
+    (status, Json(error))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rocket::{catchers, get, http::Header, local::asynchronous::Client, routes};
+
+    use super::*;
+
+    #[get("/budget")]
+    fn budget() -> Result<Json<()>, AppError> {
+        Err(AppError::Budget(
+            "monthly AI budget of $1.00 used up".to_string(),
+        ))
+    }
+
+    #[get("/io")]
+    fn io() -> Result<Json<()>, AppError> {
+        Err(std::io::Error::other("disk /var/secret is full").into())
+    }
+
+    async fn client() -> Client {
+        let rocket = rocket::build()
+            .mount("/api", routes![budget, io])
+            .register("/api", catchers![api_catcher])
+            .attach(RequestId::fairing());
+        Client::tracked(rocket).await.unwrap()
+    }
+
+    #[test]
+    fn maps_variants_to_statuses_and_codes() {
+        let cases = [
+            (
+                AppError::BadRequest("no URLs".into()),
+                400,
+                "invalid_request",
+            ),
+            (
+                AppError::Auth("missing API key".into()),
+                401,
+                "unauthorized",
+            ),
+            (AppError::Forbidden("admins only".into()), 403, "forbidden"),
+            (AppError::Budget("used up".into()), 402, "budget_exhausted"),
+            (AppError::Quota("1 job".into()), 429, "quota_exceeded"),
+            (AppError::AI("no response".into()), 502, "ai_provider_error"),
+            (
+                AppError::HttpStatus {
+                    url: "https://example.com".into(),
+                    status: 503,
+                    retry_after: Some(Duration::from_secs(5)),
+                },
+                502,
+                "upstream_status",
+            ),
+            (
+                AppError::RenderTimeout("https://example.com".into()),
+                504,
+                "render_timeout",
+            ),
+            (AppError::Config("bad".into()), 500, "invalid_config"),
+        ];
+        for (err, status, code) in cases {
+            assert_eq!(err.status().code, status, "{}", err);
+            assert_eq!(err.code(), code, "{}", err);
+        }
+    }
+
+    #[rocket::async_test]
+    async fn responds_with_json_error_bodies() {
+        let client = client().await;
+
+        let response = client
+            .get("/api/budget")
+            .header(Header::new(REQUEST_ID_HEADER, "trace-1"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::PaymentRequired);
+        assert_eq!(
+            response.headers().get_one(REQUEST_ID_HEADER),
+            Some("trace-1")
+        );
+        let body: Value = response.into_json().await.unwrap();
+        assert_eq!(body["code"], "budget_exhausted");
+        assert_eq!(body["requestId"], "trace-1");
+        assert!(body["message"].as_str().unwrap().contains("$1.00"));
+
+        let response = client.get("/api/io").dispatch().await;
+        assert_eq!(response.status(), Status::InternalServerError);
+        let id = response
+            .headers()
+            .get_one(REQUEST_ID_HEADER)
+            .unwrap()
+            .to_string();
+        let body: Value = response.into_json().await.unwrap();
+        assert_eq!(body["message"], "Internal server error");
+        assert_eq!(body["requestId"], id);
+
+        let response = client.get("/api/missing").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+        let body: Value = response.into_json().await.unwrap();
+        assert_eq!(body["code"], "not_found");
+    }
+}
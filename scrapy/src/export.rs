@@ -0,0 +1,186 @@
+use std::{io::Write, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{error::AppError, models::AiScrapingResult, openapi::ApiSchema};
+
+/// Name of the CSV column holding the page a record was extracted from.
+const SOURCE_COLUMN: &str = "source_url";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// A single JSON array.
+    Json,
+    /// One JSON object per line.
+    Jsonl,
+    /// One row per record with a column per top-level field.
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "jsonl" | "ndjson" => Ok(ExportFormat::Jsonl),
+            "csv" => Ok(ExportFormat::Csv),
+            other => Err(format!(
+                "unknown export format {:?}, expected json, jsonl or csv",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportRow<'a> {
+    url: Option<&'a str>,
+    input_rows: &'a [u64],
+    record: Value,
+}
+
+fn rows(results: &[AiScrapingResult]) -> impl Iterator<Item = ExportRow<'_>> {
+    results.iter().flat_map(|result| {
+        result.records().into_iter().map(|record| ExportRow {
+            url: result.url.as_deref(),
+            input_rows: &result.input_rows,
+            record,
+        })
+    })
+}
+
+/// Writes every extracted record in `format`.
+pub fn write_records(
+    format: ExportFormat,
+    results: &[AiScrapingResult],
+    mut writer: impl Write,
+) -> Result<(), AppError> {
+    match format {
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, &rows(results).collect::<Vec<_>>())?;
+            writeln!(writer)?;
+        }
+        ExportFormat::Jsonl => {
+            for row in rows(results) {
+                serde_json::to_writer(&mut writer, &row)?;
+                writeln!(writer)?;
+            }
+        }
+        ExportFormat::Csv => write_csv(results, writer)?,
+    }
+    Ok(())
+}
+
+fn write_csv(results: &[AiScrapingResult], writer: impl Write) -> Result<(), AppError> {
+    // Columns follow the order in which fields first appear; records that are
+    // not objects end up in a single `value` column.
+    let mut columns: Vec<String> = Vec::new();
+    for row in rows(results) {
+        let fields: Vec<&str> = match &row.record {
+            Value::Object(fields) => fields.keys().map(String::as_str).collect(),
+            _ => vec!["value"],
+        };
+        for field in fields {
+            if !columns.iter().any(|column| column == field) {
+                columns.push(field.to_string());
+            }
+        }
+    }
+
+    let mut csv = csv::Writer::from_writer(writer);
+    csv.write_record(std::iter::once(SOURCE_COLUMN).chain(columns.iter().map(String::as_str)))?;
+    for row in rows(results) {
+        let cell = |column: &str| match &row.record {
+            Value::Object(fields) => fields.get(column).map(to_cell).unwrap_or_default(),
+            value if column == "value" => to_cell(value),
+            _ => String::new(),
+        };
+        let source = row.url.unwrap_or_default().to_string();
+        csv.write_record(std::iter::once(source).chain(columns.iter().map(|column| cell(column))))?;
+    }
+    csv.flush()?;
+    Ok(())
+}
+
+fn to_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::UsageMetadata;
+
+    fn result(url: &str, data: Value) -> AiScrapingResult {
+        AiScrapingResult {
+            model: "gemini-1.5-flash-latest".to_string(),
+            start_time: Utc::now(),
+            end_time: None,
+            data,
+            usage_metadata: UsageMetadata {
+                input_tokens: 0,
+                output_tokens: 0,
+                total_cost: 0.0,
+            },
+            url: Some(url.to_string()),
+            input_rows: vec![3],
+            cached: false,
+        }
+    }
+
+    fn export(format: ExportFormat, results: &[AiScrapingResult]) -> String {
+        let mut output = Vec::new();
+        write_records(format, results, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn writes_one_json_line_per_record() {
+        let results = [
+            result("https://x.com/a", json!([{"name": "A"}, {"name": "B"}])),
+            result("https://x.com/b", Value::Null),
+        ];
+
+        let output = export(ExportFormat::Jsonl, &results);
+
+        let lines: Vec<Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                json!({"url": "https://x.com/a", "inputRows": [3], "record": {"name": "A"}}),
+                json!({"url": "https://x.com/a", "inputRows": [3], "record": {"name": "B"}}),
+            ]
+        );
+    }
+
+    #[test]
+    fn writes_csv_columns_in_first_seen_order() {
+        let results = [
+            result("https://x.com/a", json!([{"name": "A", "price": 1.5}])),
+            result("https://x.com/b", json!({"name": "B, Inc.", "tags": ["x"]})),
+        ];
+
+        let output = export(ExportFormat::Csv, &results);
+
+        assert_eq!(
+            output,
+            "source_url,name,price,tags\n\
+             https://x.com/a,A,1.5,\n\
+             https://x.com/b,\"B, Inc.\",,\"[\"\"x\"\"]\"\n"
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::models::{FieldChange, JobDiff, JobRun, PageDiff, RecordDiff};
+
+/// Compares two runs of a job page by page. Records are matched by
+/// `record_key` when they carry it and by their position on the page otherwise.
+pub fn diff_runs(previous: Option<&JobRun>, current: &JobRun, record_key: Option<&str>) -> JobDiff {
+    let mut diff = JobDiff {
+        run_id: current.run_id,
+        previous_run_id: previous.map(|run| run.run_id),
+        ..JobDiff::default()
+    };
+    let empty = BTreeMap::new();
+    let previous_pages = previous.map_or(&empty, |run| &run.pages);
+
+    for (url, page) in &current.pages {
+        let Some(before) = previous_pages.get(url) else {
+            diff.added_pages.push(url.clone());
+            continue;
+        };
+
+        let page_diff = diff_records(url, &before.records, &page.records, record_key);
+        if page_diff.added_records.is_empty()
+            && page_diff.removed_records.is_empty()
+            && page_diff.changed_records.is_empty()
+        {
+            diff.unchanged_pages += 1;
+        } else {
+            diff.changed_pages.push(page_diff);
+        }
+    }
+
+    diff.removed_pages = previous_pages
+        .keys()
+        .filter(|url| !current.pages.contains_key(*url))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+fn diff_records(
+    url: &str,
+    before: &[Value],
+    after: &[Value],
+    record_key: Option<&str>,
+) -> PageDiff {
+    let before = keyed(before, record_key);
+    let mut after = keyed(after, record_key);
+    let mut page = PageDiff {
+        url: url.to_string(),
+        added_records: Vec::new(),
+        removed_records: Vec::new(),
+        changed_records: Vec::new(),
+    };
+
+    for (key, old) in before {
+        let Some(new) = after.remove(&key) else {
+            page.removed_records.push(old.clone());
+            continue;
+        };
+
+        let fields = diff_fields(old, new);
+        if !fields.is_empty() {
+            page.changed_records.push(RecordDiff { key, fields });
+        }
+    }
+    page.added_records = after.into_values().cloned().collect();
+
+    page
+}
+
+fn keyed<'a>(records: &'a [Value], record_key: Option<&str>) -> BTreeMap<String, &'a Value> {
+    records
+        .iter()
+        .enumerate()
+        .map(|(index, record)| {
+            let key = record_key
+                .and_then(|field| record.get(field))
+                .map(|value| match value {
+                    Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                })
+                .unwrap_or_else(|| format!("#{}", index));
+            (key, record)
+        })
+        .collect()
+}
+
+fn diff_fields(before: &Value, after: &Value) -> BTreeMap<String, FieldChange> {
+    let change = |before: Option<&Value>, after: Option<&Value>| FieldChange {
+        before: before.cloned().unwrap_or(Value::Null),
+        after: after.cloned().unwrap_or(Value::Null),
+    };
+
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => before
+            .keys()
+            .chain(after.keys())
+            .filter(|field| before.get(*field) != after.get(*field))
+            .map(|field| (field.clone(), change(before.get(field), after.get(field))))
+            .collect(),
+        (before, after) if before != after => {
+            BTreeMap::from([(String::new(), change(Some(before), Some(after)))])
+        }
+        _ => BTreeMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::models::PageSnapshot;
+
+    fn run(pages: &[(&str, Value)]) -> JobRun {
+        JobRun {
+            job_id: "catalog".to_string(),
+            run_id: Uuid::new_v4(),
+            started_at: Utc::now(),
+            finished_at: Utc::now(),
+            pages: pages
+                .iter()
+                .map(|(url, records)| {
+                    let page = PageSnapshot {
+                        content_hash: None,
+                        records: records.as_array().unwrap().clone(),
+                    };
+                    (url.to_string(), page)
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn reports_page_and_field_level_changes() {
+        let previous = run(&[
+            (
+                "x.com/a",
+                json!([{"sku": "1", "price": 10, "name": "Widget"}, {"sku": "2", "price": 5}]),
+            ),
+            ("x.com/b", json!([{"sku": "3", "price": 1}])),
+            ("x.com/gone", json!([])),
+        ]);
+        let current = run(&[
+            (
+                "x.com/a",
+                json!([{"sku": "2", "price": 5}, {"sku": "1", "price": 12, "name": "Widget"}, {"sku": "4"}]),
+            ),
+            ("x.com/b", json!([{"sku": "3", "price": 1}])),
+            ("x.com/new", json!([])),
+        ]);
+
+        let diff = diff_runs(Some(&previous), &current, Some("sku"));
+
+        assert_eq!(diff.previous_run_id, Some(previous.run_id));
+        assert_eq!(diff.added_pages, vec!["x.com/new"]);
+        assert_eq!(diff.removed_pages, vec!["x.com/gone"]);
+        assert_eq!(diff.unchanged_pages, 1);
+        assert_eq!(
+            diff.changed_pages,
+            vec![PageDiff {
+                url: "x.com/a".to_string(),
+                added_records: vec![json!({"sku": "4"})],
+                removed_records: Vec::new(),
+                changed_records: vec![RecordDiff {
+                    key: "1".to_string(),
+                    fields: BTreeMap::from([(
+                        "price".to_string(),
+                        FieldChange {
+                            before: json!(10),
+                            after: json!(12),
+                        }
+                    )]),
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn first_run_adds_every_page() {
+        let current = run(&[("x.com/a", json!([{"price": 1}]))]);
+        let diff = diff_runs(None, &current, None);
+
+        assert_eq!(diff.added_pages, vec!["x.com/a"]);
+        assert!(diff.changed_pages.is_empty() && diff.removed_pages.is_empty());
+    }
+}
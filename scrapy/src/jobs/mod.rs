@@ -0,0 +1,250 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use tokio::fs;
+
+use crate::{
+    crawler::Canonicalizer,
+    error::AppError,
+    models::{AiScrapingResult, CrawlReport, JobRun, PageSnapshot, PageStatus},
+    spider::PageState,
+    utils::owner_dir,
+};
+
+pub use diff::diff_runs;
+
+mod diff;
+
+const LATEST_RUN: &str = "latest.json";
+
+/// Stores job runs as JSON files: `<dir>/<job id>/runs/<run id>.json` plus a
+/// copy of the most recent run in `latest.json`. Each user's jobs live in
+/// their own directory, so job ids only need to be unique per user.
+pub struct JobStore {
+    dir: PathBuf,
+}
+
+impl JobStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub async fn latest(
+        &self,
+        owner: Option<&str>,
+        job_id: &str,
+    ) -> Result<Option<JobRun>, AppError> {
+        let path = self.job_dir(owner, job_id)?.join(LATEST_RUN);
+        match fs::read(&path).await {
+            Ok(content) => Ok(Some(serde_json::from_slice(&content)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub async fn save(&self, owner: Option<&str>, run: &JobRun) -> Result<(), AppError> {
+        let job_dir = self.job_dir(owner, &run.job_id)?;
+        let runs_dir = job_dir.join("runs");
+        fs::create_dir_all(&runs_dir).await?;
+
+        let content = serde_json::to_vec_pretty(run)?;
+        fs::write(runs_dir.join(format!("{}.json", run.run_id)), &content).await?;
+
+        let temp = job_dir.join(format!("{}.tmp", run.run_id));
+        fs::write(&temp, &content).await?;
+        fs::rename(&temp, job_dir.join(LATEST_RUN)).await?;
+        Ok(())
+    }
+
+    fn job_dir(&self, owner: Option<&str>, job_id: &str) -> Result<PathBuf, AppError> {
+        let valid = !job_id.is_empty()
+            && job_id.len() <= 128
+            && job_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            && !job_id.starts_with('.');
+        if !valid {
+            return Err(AppError::Job(format!(
+                "Invalid job id {:?}: use letters, digits, '-', '_' or '.'",
+                job_id
+            )));
+        }
+        Ok(owner_dir(&self.dir, owner).join(job_id))
+    }
+}
+
+/// Whether `url` had the same content in `previous`, in which case its
+/// records are carried over instead of extracted again.
+pub fn is_unchanged(previous: Option<&JobRun>, url: &str, content_hash: Option<&str>) -> bool {
+    content_hash.is_some()
+        && previous
+            .and_then(|run| run.pages.get(url))
+            .is_some_and(|page| page.content_hash.as_deref() == content_hash)
+}
+
+/// Fills `run` with the records extracted per page. Pages that were
+/// skipped as unchanged, or that failed transiently, keep their records
+/// from the previous run so they do not show up as removed.
+pub fn snapshot_pages(
+    run: &mut JobRun,
+    previous: Option<&JobRun>,
+    page_states: BTreeMap<String, PageState>,
+    results: &[AiScrapingResult],
+    report: &CrawlReport,
+    canonicalizer: &Canonicalizer,
+) {
+    let previous_page = |url: &str| previous.and_then(|run| run.pages.get(url)).cloned();
+
+    for (url, state) in page_states {
+        let page = match previous_page(&url).filter(|_| state.unchanged) {
+            Some(page) => page,
+            None => PageSnapshot {
+                content_hash: state.content_hash,
+                records: Vec::new(),
+            },
+        };
+        run.pages.insert(url, page);
+    }
+
+    for result in results {
+        let Some(page) = result.url.as_ref().and_then(|url| run.pages.get_mut(url)) else {
+            continue;
+        };
+        page.records.extend(result.records());
+    }
+
+    for page in &report.pages {
+        let gone = matches!(page.http_status, Some(404 | 410));
+        if page.status != PageStatus::Failed || gone {
+            continue;
+        }
+        let Some(url) = canonicalizer.canonicalize(&page.url).map(String::from) else {
+            continue;
+        };
+        if let Some(snapshot) = previous_page(&url) {
+            run.pages.entry(url).or_insert(snapshot);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::models::{CanonicalConfig, PageReport};
+
+    fn run(pages: &[(&str, &str, serde_json::Value)]) -> JobRun {
+        JobRun {
+            job_id: "catalog".to_string(),
+            run_id: Uuid::new_v4(),
+            started_at: Utc::now(),
+            finished_at: Utc::now(),
+            pages: pages
+                .iter()
+                .map(|(url, hash, record)| {
+                    let page = PageSnapshot {
+                        content_hash: Some(hash.to_string()),
+                        records: vec![record.clone()],
+                    };
+                    (url.to_string(), page)
+                })
+                .collect(),
+        }
+    }
+
+    fn state(hash: &str, unchanged: bool) -> PageState {
+        PageState {
+            content_hash: Some(hash.to_string()),
+            unchanged,
+        }
+    }
+
+    #[test]
+    fn skips_only_pages_with_the_same_content() {
+        let previous = run(&[("https://shop.test/a", "aaaa", json!({ "name": "A" }))]);
+
+        assert!(is_unchanged(
+            Some(&previous),
+            "https://shop.test/a",
+            Some("aaaa")
+        ));
+        assert!(!is_unchanged(
+            Some(&previous),
+            "https://shop.test/a",
+            Some("bbbb")
+        ));
+        assert!(!is_unchanged(
+            Some(&previous),
+            "https://shop.test/b",
+            Some("aaaa")
+        ));
+        assert!(!is_unchanged(Some(&previous), "https://shop.test/a", None));
+        assert!(!is_unchanged(None, "https://shop.test/a", Some("aaaa")));
+    }
+
+    #[test]
+    fn snapshots_new_unchanged_and_failed_pages() {
+        let previous = run(&[
+            ("https://shop.test/a", "aaaa", json!({ "name": "A" })),
+            ("https://shop.test/b", "bbbb", json!({ "name": "B" })),
+            ("https://shop.test/c", "cccc", json!({ "name": "C" })),
+            ("https://shop.test/d", "dddd", json!({ "name": "D" })),
+        ]);
+        let page_states = BTreeMap::from([
+            ("https://shop.test/a".to_string(), state("aaaa", true)),
+            ("https://shop.test/e".to_string(), state("eeee", false)),
+        ]);
+        let results: Vec<AiScrapingResult> = vec![serde_json::from_value(json!({
+            "model": "gemini-1.5-flash-latest",
+            "start_time": Utc::now(),
+            "end_time": null,
+            "data": [{ "name": "E1" }, { "name": "E2" }],
+            "usage_metadata": { "inputTokens": 1, "outputTokens": 1, "totalCost": 0.0 },
+            "url": "https://shop.test/e",
+            "input_rows": [],
+        }))
+        .unwrap()];
+        let mut failed = PageReport::new("https://shop.test/b".to_string(), PageStatus::Failed);
+        failed.http_status = Some(503);
+        let mut gone = PageReport::new("https://shop.test/c".to_string(), PageStatus::Failed);
+        gone.http_status = Some(404);
+        let report = CrawlReport {
+            pages: vec![failed, gone],
+            ..Default::default()
+        };
+
+        let mut current = run(&[]);
+        snapshot_pages(
+            &mut current,
+            Some(&previous),
+            page_states,
+            &results,
+            &report,
+            &Canonicalizer::new(CanonicalConfig::default()),
+        );
+
+        let records = |url: &str| current.pages.get(url).map(|page| page.records.clone());
+        // Unchanged and transiently failing pages keep their records.
+        assert_eq!(
+            records("https://shop.test/a"),
+            Some(vec![json!({ "name": "A" })])
+        );
+        assert_eq!(
+            records("https://shop.test/b"),
+            Some(vec![json!({ "name": "B" })])
+        );
+        // Gone and unvisited pages are dropped.
+        assert_eq!(records("https://shop.test/c"), None);
+        assert_eq!(records("https://shop.test/d"), None);
+        assert_eq!(
+            records("https://shop.test/e"),
+            Some(vec![json!({ "name": "E1" }), json!({ "name": "E2" })])
+        );
+        assert_eq!(
+            current.pages["https://shop.test/e"].content_hash.as_deref(),
+            Some("eeee")
+        );
+    }
+}
@@ -0,0 +1,24 @@
+mod ai;
+pub mod app;
+pub mod auth;
+pub mod cache;
+pub mod config;
+pub mod constants;
+pub mod crawler;
+pub mod error;
+pub mod export;
+pub mod jobs;
+pub mod metrics;
+pub mod models;
+pub mod openapi;
+pub mod outbound;
+pub mod routes;
+pub mod scheduler;
+pub mod services;
+pub mod specs;
+pub mod spider;
+pub mod utils;
+pub mod validation;
+pub mod vault;
+
+pub use crawler::Crawler;
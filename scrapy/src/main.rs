@@ -1,73 +1,124 @@
-use crate::crawler::Crawler;
-use rocket::{fs::FileServer, routes};
-use rocket_cors::{AllowedHeaders, AllowedOrigins};
-use services::{AIService, CrawlerService, GeminiAIProvider, WebSocketService};
-use std::sync::Arc;
-use std::time::Duration;
-use utils::find_static_dir;
+use rocket::{
+    catchers,
+    data::{ByteUnit, Limits},
+    fairing::AdHoc,
+    fs::FileServer,
+    Build, Rocket,
+};
+use rocket_cors::{AllowedHeaders, AllowedOrigins, Cors};
+use scrapy::{
+    app::App,
+    config::{CorsSettings, ServerConfig},
+    error::{self, AppError, RequestId},
+    routes,
+    scheduler::Scheduler,
+    specs::SpecStore,
+};
+use std::{process::ExitCode, sync::Arc};
 
-mod ai;
-mod constants;
-mod crawler;
-mod error;
-mod models;
-mod routes;
-mod services;
-mod spider;
-mod utils;
-
-#[rocket::launch]
-fn rocket() -> _ {
+#[rocket::main]
+async fn main() -> ExitCode {
     dotenvy::dotenv().ok();
 
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    let websocket_service = Arc::new(WebSocketService::new(1024));
-    let gemini_provider = GeminiAIProvider::new();
-    let ai_service = Arc::new(AIService::new(gemini_provider));
-
-    let crawler = Crawler::new(Duration::from_millis(200), 2, 500);
-    let crawler_service = Arc::new(CrawlerService::new(
-        crawler,
-        websocket_service.clone(),
-        ai_service.clone(),
-    ));
+    let rocket = match ServerConfig::load().and_then(|config| build(&config)) {
+        Ok(rocket) => rocket,
+        Err(err) => {
+            log::error!("Failed to start server: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
 
-    let cors = rocket_cors::CorsOptions {
-        allowed_origins: AllowedOrigins::all(),
-        allowed_methods: vec![rocket::http::Method::Get, rocket::http::Method::Post]
-            .into_iter()
-            .map(From::from)
-            .collect(),
-        allowed_headers: AllowedHeaders::some(&["Authorization", "Accept", "Content-Type"]),
-        allow_credentials: true,
-        ..Default::default()
+    match rocket.launch().await {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(err) => {
+            log::error!("Server error: {}", err);
+            ExitCode::FAILURE
+        }
     }
-    .to_cors()
-    .expect("Failed to create CORS");
-
-    let static_dir = find_static_dir();
+}
 
-    if !static_dir.is_dir() {
-        panic!("Static directory does not exist: {:?}", static_dir);
+fn build(config: &ServerConfig) -> Result<Rocket<Build>, AppError> {
+    let App {
+        websocket_service,
+        ai_service,
+        webhook_service,
+        crawler_service,
+        account_service,
+        vault,
+        metrics,
+    } = App::new(config)?;
+    if !config.auth.enabled {
+        log::warn!("Authentication is disabled: every request has admin access");
     }
+    let scheduler = Arc::new(Scheduler::open(
+        config.storage.schedules_file(),
+        crawler_service.clone(),
+    )?);
 
+    let static_dir = config.static_dir()?;
     log::info!("Using static directory: {:?}", static_dir);
 
-    rocket::build()
-        .mount(
-            "/api",
-            routes![
-                routes::index,
-                routes::crawl,
-                routes::websocket,
-                routes::sse_events,
-                routes::get_models
-            ],
-        )
+    let limits = Limits::default()
+        .limit("json", ByteUnit::from(config.limits.max_json_bytes))
+        .limit("file", ByteUnit::from(config.limits.max_upload_bytes))
+        .limit("data-form", ByteUnit::from(config.limits.max_upload_bytes));
+    let figment = rocket::Config::figment()
+        .merge(("address", config.server.address))
+        .merge(("port", config.server.port))
+        .merge(("limits", limits));
+
+    Ok(rocket::custom(figment)
+        .mount("/api", routes::api())
+        .register("/api", catchers![error::api_catcher])
+        .mount("/", rocket::routes![routes::get_metrics])
         .mount("/", FileServer::from(static_dir))
         .manage(websocket_service)
         .manage(crawler_service)
         .manage(ai_service)
-        .attach(cors)
+        .manage(webhook_service)
+        .manage(account_service.authenticator().clone())
+        .manage(account_service)
+        .manage(vault)
+        .manage(metrics)
+        .manage(Arc::new(SpecStore::new(config.storage.specs_dir())))
+        .manage(scheduler.clone())
+        .attach(AdHoc::on_liftoff("Scheduler", |_| {
+            Box::pin(async move { scheduler.spawn() })
+        }))
+        .attach(RequestId::fairing())
+        .attach(cors(&config.cors)?))
+}
+
+fn cors(settings: &CorsSettings) -> Result<Cors, AppError> {
+    let allowed_origins = if settings.allows_any_origin() {
+        AllowedOrigins::all()
+    } else {
+        AllowedOrigins::some_exact(&settings.allowed_origins)
+    };
+
+    rocket_cors::CorsOptions {
+        allowed_origins,
+        allowed_methods: vec![
+            rocket::http::Method::Get,
+            rocket::http::Method::Post,
+            rocket::http::Method::Put,
+            rocket::http::Method::Delete,
+        ]
+        .into_iter()
+        .map(From::from)
+        .collect(),
+        allowed_headers: AllowedHeaders::some(&[
+            "Authorization",
+            "Accept",
+            "Content-Type",
+            "X-Request-Id",
+        ]),
+        expose_headers: ["X-Request-Id".to_string()].into(),
+        allow_credentials: settings.allow_credentials,
+        ..Default::default()
+    }
+    .to_cors()
+    .map_err(|err| AppError::Config(format!("Invalid CORS settings: {}", err)))
 }
@@ -0,0 +1,444 @@
+//! Prometheus metrics for crawls, AI usage and connected clients, rendered
+//! in the text exposition format served at `/metrics`.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use crate::models::{CacheStats, UsageMetadata};
+
+/// Bucket upper bounds, in seconds, of the page fetch latency histogram.
+const FETCH_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+/// Bucket upper bounds, in seconds, of the AI request latency histogram.
+const AI_BUCKETS: &[f64] = &[0.5, 1.0, 2.5, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0];
+
+/// Series of one metric, keyed by their label values.
+struct Family<S> {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    series: Mutex<BTreeMap<Vec<String>, S>>,
+}
+
+impl<S: Default> Family<S> {
+    fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        // A metric without labels has a single series, reported from the start.
+        let mut series = BTreeMap::new();
+        if labels.is_empty() {
+            series.insert(Vec::new(), S::default());
+        }
+        Self {
+            name,
+            help,
+            labels,
+            series: Mutex::new(series),
+        }
+    }
+
+    fn update(&self, values: &[&str], update: impl FnOnce(&mut S)) {
+        debug_assert_eq!(values.len(), self.labels.len(), "labels of {}", self.name);
+        let key = values.iter().map(|value| value.to_string()).collect();
+        let mut series = self.series.lock().unwrap_or_else(|err| err.into_inner());
+        update(series.entry(key).or_default());
+    }
+
+    fn header(&self, out: &mut String, kind: &str) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} {}", self.name, kind);
+    }
+
+    /// `{name="value",...}`, or nothing for a metric without labels.
+    fn label_set(&self, values: &[String], extra: Option<(&str, &str)>) -> String {
+        let pairs: Vec<String> = self
+            .labels
+            .iter()
+            .zip(values)
+            .map(|(name, value)| (*name, value.as_str()))
+            .chain(extra)
+            .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+            .collect();
+        if pairs.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", pairs.join(","))
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// A counter, which only goes up, or a gauge, as `kind` says.
+struct Scalar {
+    family: Family<f64>,
+    kind: &'static str,
+}
+
+impl Scalar {
+    fn counter(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self {
+            family: Family::new(name, help, labels),
+            kind: "counter",
+        }
+    }
+
+    fn gauge(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self {
+            family: Family::new(name, help, labels),
+            kind: "gauge",
+        }
+    }
+
+    fn add(&self, values: &[&str], amount: f64) {
+        self.family.update(values, |value| *value += amount);
+    }
+
+    fn render(&self, out: &mut String) {
+        self.family.header(out, self.kind);
+        let series = self
+            .family
+            .series
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        for (values, value) in series.iter() {
+            let labels = self.family.label_set(values, None);
+            let _ = writeln!(out, "{}{} {}", self.family.name, labels, value);
+        }
+    }
+}
+
+#[derive(Default)]
+struct Buckets {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+struct Histogram {
+    family: Family<Buckets>,
+    bounds: &'static [f64],
+}
+
+impl Histogram {
+    fn new(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+        bounds: &'static [f64],
+    ) -> Self {
+        Self {
+            family: Family::new(name, help, labels),
+            bounds,
+        }
+    }
+
+    fn observe(&self, values: &[&str], value: f64) {
+        self.family.update(values, |buckets| {
+            buckets.counts.resize(self.bounds.len(), 0);
+            if let Some(index) = self.bounds.iter().position(|bound| value <= *bound) {
+                buckets.counts[index] += 1;
+            }
+            buckets.sum += value;
+            buckets.count += 1;
+        });
+    }
+
+    fn render(&self, out: &mut String) {
+        self.family.header(out, "histogram");
+        let name = self.family.name;
+        let series = self
+            .family
+            .series
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        for (values, buckets) in series.iter() {
+            let mut cumulative = 0;
+            for (bound, count) in self.bounds.iter().zip(&buckets.counts) {
+                cumulative += count;
+                let labels = self
+                    .family
+                    .label_set(values, Some(("le", &bound.to_string())));
+                let _ = writeln!(out, "{}_bucket{} {}", name, labels, cumulative);
+            }
+            let labels = self.family.label_set(values, Some(("le", "+Inf")));
+            let _ = writeln!(out, "{}_bucket{} {}", name, labels, buckets.count);
+            let labels = self.family.label_set(values, None);
+            let _ = writeln!(out, "{}_sum{} {}", name, labels, buckets.sum);
+            let _ = writeln!(out, "{}_count{} {}", name, labels, buckets.count);
+        }
+    }
+}
+
+/// The class of an HTTP status code, such as `4xx`, used as a label value.
+pub fn status_class(status: u16) -> &'static str {
+    match status {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        500..=599 => "5xx",
+        _ => "error",
+    }
+}
+
+/// Counters and histograms of the whole server. Every service records into
+/// one shared instance; those built without one get a private instance that
+/// nothing reads.
+pub struct Metrics {
+    pages_fetched: Scalar,
+    fetch_duration: Histogram,
+    bytes_downloaded: Scalar,
+    fetch_retries: Scalar,
+    frontier_urls: Scalar,
+    active_spiders: Arc<AtomicUsize>,
+    cache_lookups: Scalar,
+    ai_requests: Scalar,
+    ai_tokens: Scalar,
+    ai_cost: Scalar,
+    ai_duration: Histogram,
+    stream_clients: Scalar,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            pages_fetched: Scalar::counter(
+                "scrapy_pages_fetched_total",
+                "Page fetches by fetcher and HTTP status class.",
+                &["fetcher", "status"],
+            ),
+            fetch_duration: Histogram::new(
+                "scrapy_fetch_duration_seconds",
+                "Time taken to fetch a page, including failed fetches.",
+                &["fetcher"],
+                FETCH_BUCKETS,
+            ),
+            bytes_downloaded: Scalar::counter(
+                "scrapy_downloaded_bytes_total",
+                "Size of the fetched page bodies.",
+                &["fetcher"],
+            ),
+            fetch_retries: Scalar::counter(
+                "scrapy_fetch_retries_total",
+                "Failed fetches that were retried.",
+                &[],
+            ),
+            frontier_urls: Scalar::gauge(
+                "scrapy_frontier_urls",
+                "URLs queued in the frontiers of running crawls.",
+                &[],
+            ),
+            active_spiders: Arc::new(AtomicUsize::new(0)),
+            cache_lookups: Scalar::counter(
+                "scrapy_cache_lookups_total",
+                "HTTP and AI cache lookups by result.",
+                &["cache", "result"],
+            ),
+            ai_requests: Scalar::counter(
+                "scrapy_ai_requests_total",
+                "Requests sent to the AI provider by model and outcome.",
+                &["model", "outcome"],
+            ),
+            ai_tokens: Scalar::counter(
+                "scrapy_ai_tokens_total",
+                "Tokens sent to and received from the AI provider.",
+                &["model", "direction"],
+            ),
+            ai_cost: Scalar::counter(
+                "scrapy_ai_cost_usd_total",
+                "Estimated AI spend in US dollars.",
+                &["model"],
+            ),
+            ai_duration: Histogram::new(
+                "scrapy_ai_request_duration_seconds",
+                "Time taken by the AI provider to answer.",
+                &["model"],
+                AI_BUCKETS,
+            ),
+            stream_clients: Scalar::gauge(
+                "scrapy_stream_clients",
+                "Clients connected to the progress streams.",
+                &["transport"],
+            ),
+        }
+    }
+
+    /// Records a page fetch that ended with `status`, a [`status_class`] or
+    /// `error` when no response was received.
+    pub fn record_fetch(&self, fetcher: &str, status: &str, bytes: usize, elapsed: Duration) {
+        self.pages_fetched.add(&[fetcher, status], 1.0);
+        self.fetch_duration
+            .observe(&[fetcher], elapsed.as_secs_f64());
+        self.bytes_downloaded.add(&[fetcher], bytes as f64);
+    }
+
+    pub fn record_retry(&self) {
+        self.fetch_retries.add(&[], 1.0);
+    }
+
+    /// Adjusts the frontier gauge by the change in size of one crawl's frontier.
+    pub fn add_frontier_urls(&self, delta: i64) {
+        self.frontier_urls.add(&[], delta as f64);
+    }
+
+    /// The number of URLs being scraped, shared with the [`Crawler`](crate::Crawler).
+    pub fn active_spiders(&self) -> Arc<AtomicUsize> {
+        self.active_spiders.clone()
+    }
+
+    /// Adds the cache lookups of a finished crawl.
+    pub fn record_cache(&self, stats: &CacheStats) {
+        for (cache, result, count) in [
+            ("http", "hit", stats.http_hits),
+            ("http", "revalidated", stats.http_revalidated),
+            ("http", "miss", stats.http_misses),
+            ("ai", "hit", stats.ai_hits),
+            ("ai", "miss", stats.ai_misses),
+        ] {
+            self.cache_lookups.add(&[cache, result], count as f64);
+        }
+    }
+
+    /// Records a request to the AI provider, with its usage when it succeeded.
+    pub fn record_ai_request(&self, model: &str, usage: Option<&UsageMetadata>, elapsed: Duration) {
+        let outcome = if usage.is_some() { "success" } else { "error" };
+        self.ai_requests.add(&[model, outcome], 1.0);
+        self.ai_duration.observe(&[model], elapsed.as_secs_f64());
+        if let Some(usage) = usage {
+            self.ai_tokens
+                .add(&[model, "input"], usage.input_tokens as f64);
+            self.ai_tokens
+                .add(&[model, "output"], usage.output_tokens as f64);
+            self.ai_cost.add(&[model], usage.total_cost);
+        }
+    }
+
+    /// Counts a client connected over `transport` until the returned guard
+    /// is dropped.
+    pub fn connect_client(self: &Arc<Self>, transport: &'static str) -> ConnectedClient {
+        self.stream_clients.add(&[transport], 1.0);
+        ConnectedClient {
+            metrics: self.clone(),
+            transport,
+        }
+    }
+
+    /// All metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.pages_fetched.render(&mut out);
+        self.fetch_duration.render(&mut out);
+        self.bytes_downloaded.render(&mut out);
+        self.fetch_retries.render(&mut out);
+        self.frontier_urls.render(&mut out);
+        let _ = writeln!(
+            out,
+            "# HELP scrapy_active_spiders URLs being scraped right now.\n\
+             # TYPE scrapy_active_spiders gauge\n\
+             scrapy_active_spiders {}",
+            self.active_spiders.load(Ordering::SeqCst)
+        );
+        self.cache_lookups.render(&mut out);
+        self.ai_requests.render(&mut out);
+        self.ai_tokens.render(&mut out);
+        self.ai_cost.render(&mut out);
+        self.ai_duration.render(&mut out);
+        self.stream_clients.render(&mut out);
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A client connected to the WebSocket or SSE stream.
+pub struct ConnectedClient {
+    metrics: Arc<Metrics>,
+    transport: &'static str,
+}
+
+impl Drop for ConnectedClient {
+    fn drop(&mut self) {
+        self.metrics.stream_clients.add(&[self.transport], -1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_labeled_counters_and_gauges() {
+        let metrics = Arc::new(Metrics::new());
+        metrics.record_fetch("http", status_class(404), 512, Duration::from_millis(20));
+        metrics.record_fetch("http", "error", 0, Duration::from_millis(20));
+        let usage = UsageMetadata {
+            input_tokens: 1200,
+            output_tokens: 300,
+            total_cost: 0.25,
+        };
+        metrics.record_ai_request("gemini-1.5-pro", Some(&usage), Duration::from_secs(2));
+        let client = metrics.connect_client("sse");
+        metrics.connect_client("websocket");
+
+        let text = metrics.render();
+        for line in [
+            "# TYPE scrapy_pages_fetched_total counter",
+            "scrapy_pages_fetched_total{fetcher=\"http\",status=\"4xx\"} 1",
+            "scrapy_pages_fetched_total{fetcher=\"http\",status=\"error\"} 1",
+            "scrapy_downloaded_bytes_total{fetcher=\"http\"} 512",
+            "scrapy_ai_requests_total{model=\"gemini-1.5-pro\",outcome=\"success\"} 1",
+            "scrapy_ai_tokens_total{model=\"gemini-1.5-pro\",direction=\"input\"} 1200",
+            "scrapy_ai_cost_usd_total{model=\"gemini-1.5-pro\"} 0.25",
+            "scrapy_stream_clients{transport=\"sse\"} 1",
+            "scrapy_stream_clients{transport=\"websocket\"} 0",
+            "scrapy_active_spiders 0",
+        ] {
+            assert!(
+                text.lines().any(|l| l == line),
+                "{} missing from\n{}",
+                line,
+                text
+            );
+        }
+
+        drop(client);
+        assert!(metrics
+            .render()
+            .contains("scrapy_stream_clients{transport=\"sse\"} 0"));
+    }
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let metrics = Metrics::new();
+        for millis in [30, 80, 80, 70_000] {
+            metrics.record_fetch("browser", "2xx", 0, Duration::from_millis(millis));
+        }
+
+        let text = metrics.render();
+        let bucket = |le: &str| {
+            format!(
+                "scrapy_fetch_duration_seconds_bucket{{fetcher=\"browser\",le=\"{}\"}}",
+                le
+            )
+        };
+        assert!(text.contains(&format!("{} 1\n", bucket("0.05"))));
+        assert!(text.contains(&format!("{} 3\n", bucket("0.1"))));
+        assert!(text.contains(&format!("{} 3\n", bucket("60"))));
+        assert!(text.contains(&format!("{} 4\n", bucket("+Inf"))));
+        assert!(text.contains("scrapy_fetch_duration_seconds_count{fetcher=\"browser\"} 4\n"));
+    }
+}
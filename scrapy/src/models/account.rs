@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::openapi::ApiSchema;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// One crawl, recorded against the user who started it.
+#[derive(Debug, Clone, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRecord {
+    pub id: Uuid,
+    /// `None` for crawls started without authentication.
+    pub owner: Option<String>,
+    pub job_id: Option<String>,
+    pub status: JobStatus,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub start_urls: usize,
+    pub pages: usize,
+    pub results: usize,
+    pub tokens: u64,
+    pub cost_usd: f64,
+    pub error: Option<String>,
+}
+
+/// A user's limits and what they have used of them this month.
+#[derive(Debug, Clone, Serialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    pub user: Option<String>,
+    /// Calendar month (UTC) the spend applies to, e.g. `2024-05`.
+    pub month: String,
+    pub spent_usd: f64,
+    pub monthly_budget_usd: Option<f64>,
+    pub running_jobs: usize,
+    pub max_concurrent_jobs: Option<usize>,
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::openapi::ApiSchema;
+
+use super::JobDiff;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum PageStatus {
+    Scraped,
+    Failed,
+    DisallowedByRobots,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum FailureKind {
+    Dns,
+    Connect,
+    Timeout,
+    Tls,
+    ClientError,
+    ServerError,
+    /// Refused by the outbound guard.
+    Blocked,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PageReport {
+    pub url: String,
+    pub status: PageStatus,
+    pub reason: Option<String>,
+    pub failure: Option<FailureKind>,
+    pub http_status: Option<u16>,
+    pub attempts: u32,
+    /// Page this one was skipped in favour of, through `rel=canonical` or
+    /// duplicate detection.
+    pub duplicate_of: Option<String>,
+}
+
+impl PageReport {
+    pub fn new(url: String, status: PageStatus) -> Self {
+        Self {
+            url,
+            status,
+            reason: None,
+            failure: None,
+            http_status: None,
+            attempts: 0,
+            duplicate_of: None,
+        }
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    pub fn with_failure(mut self, failure: FailureKind, http_status: Option<u16>) -> Self {
+        self.failure = Some(failure);
+        self.http_status = http_status;
+        self
+    }
+
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlReport {
+    pub pages: Vec<PageReport>,
+    pub frontier: FrontierStats,
+    pub dedup: DedupStats,
+    pub cache: CacheStats,
+    pub budget: BudgetStats,
+    pub diff: Option<JobDiff>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FrontierStats {
+    pub queued: usize,
+    pub pending: usize,
+    pub peak_size: usize,
+    pub duplicates: usize,
+    pub skipped_max_depth: usize,
+    pub skipped_max_pages: usize,
+    pub skipped_domain_cap: usize,
+    pub depth_distribution: BTreeMap<u32, usize>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateKind {
+    Exact,
+    Near,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupDecision {
+    pub url: String,
+    pub duplicate_of: String,
+    pub kind: DuplicateKind,
+    pub distance: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DedupStats {
+    pub unique: usize,
+    pub exact_duplicates: usize,
+    pub near_duplicates: usize,
+    pub decisions: Vec<DedupDecision>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStats {
+    pub tokens: u64,
+    pub cost_usd: f64,
+    /// Pages not sent for extraction because the budget was used up.
+    pub skipped_extractions: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub http_hits: u64,
+    pub http_revalidated: u64,
+    pub http_misses: u64,
+    pub ai_hits: u64,
+    pub ai_misses: u64,
+}
@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{openapi::ApiSchema, vault::Secret};
+
+fn default_provider() -> String {
+    "gemini".to_string()
+}
+
+/// Body of `PUT /credentials/<name>`.
+#[derive(Debug, Clone, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialRequest {
+    #[serde(default = "default_provider")]
+    pub provider: String,
+    pub api_key: Secret,
+}
+
+/// A stored credential as shown to API clients, without its value.
+#[derive(Debug, Clone, Serialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Credential {
+    pub name: String,
+    pub owner: Option<String>,
+    pub provider: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::openapi::ApiSchema;
+
+/// Persisted outcome of one run of a job, keyed by canonical page URL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobRun {
+    pub job_id: String,
+    pub run_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub pages: BTreeMap<String, PageSnapshot>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PageSnapshot {
+    pub content_hash: Option<String>,
+    pub records: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JobDiff {
+    pub run_id: Uuid,
+    pub previous_run_id: Option<Uuid>,
+    pub added_pages: Vec<String>,
+    pub removed_pages: Vec<String>,
+    pub unchanged_pages: usize,
+    pub changed_pages: Vec<PageDiff>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PageDiff {
+    pub url: String,
+    pub added_records: Vec<serde_json::Value>,
+    pub removed_records: Vec<serde_json::Value>,
+    pub changed_records: Vec<RecordDiff>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordDiff {
+    /// Value of the configured record key, or the record's position on the page.
+    pub key: String,
+    pub fields: BTreeMap<String, FieldChange>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldChange {
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize, Clone, Eq, Hash, PartialEq)]
+use crate::openapi::ApiSchema;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, Hash, PartialEq, ApiSchema)]
 #[serde(rename_all = "camelCase")]
 pub enum MessageType {
     Progress,
@@ -11,11 +13,14 @@ pub enum MessageType {
     Warning,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, ApiSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct WebSocketMessage {
     #[serde(rename = "type")]
     pub r#type: MessageType,
     pub payload: String,
     pub metadata: Option<serde_json::Value>,
+    /// User whose crawl produced the message; only they and admins receive it.
+    #[serde(skip)]
+    pub owner: Option<String>,
 }
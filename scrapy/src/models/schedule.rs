@@ -0,0 +1,98 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::openapi::ApiSchema;
+
+/// When a schedule fires. Cron expressions are evaluated in UTC and accept
+/// either the standard five fields or a leading seconds field.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ApiSchema)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ScheduleTrigger {
+    Cron {
+        expression: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Interval {
+        every_secs: u64,
+    },
+}
+
+/// What to do when a schedule fires while its previous run is still going.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum OverlapPolicy {
+    /// Record the run as skipped.
+    #[default]
+    Skip,
+    /// Start one more run as soon as the current one finishes.
+    Queue,
+}
+
+/// Body of the create and update schedule routes. `params` is a crawl
+/// request as accepted by `POST /crawl`, except that it may not carry an
+/// `apiKey`: schedules name a stored `credential` instead.
+#[derive(Debug, Clone, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleRequest {
+    pub name: String,
+    pub trigger: ScheduleTrigger,
+    pub params: serde_json::Value,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub overlap: OverlapPolicy,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    pub id: Uuid,
+    pub name: String,
+    pub trigger: ScheduleTrigger,
+    pub params: serde_json::Value,
+    pub enabled: bool,
+    pub overlap: OverlapPolicy,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub running: bool,
+    /// User the scheduled crawls run for; `None` when created without
+    /// authentication.
+    #[serde(default)]
+    pub owner: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ScheduleRunStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum RunTrigger {
+    Scheduled,
+    Manual,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleRun {
+    pub id: Uuid,
+    pub schedule_id: Uuid,
+    pub trigger: RunTrigger,
+    pub status: ScheduleRunStatus,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub pages: usize,
+    pub results: usize,
+    pub error: Option<String>,
+}
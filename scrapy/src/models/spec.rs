@@ -0,0 +1,184 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{constants::DEFAULT_MODEL, export::ExportFormat, openapi::ApiSchema, vault::Secret};
+
+use super::{
+    BudgetConfig, CanonicalConfig, DedupConfig, FetchOverrides, FetcherConfig, FrontierConfig,
+    InputUrl, PriorityPattern, RateLimitConfig, RetryConfig, ScrapeParams, SeedConfig,
+    SessionConfig, WebhookConfig,
+};
+
+/// Latest version of the job spec format.
+pub const JOB_SPEC_VERSION: u32 = 1;
+
+/// A crawl described as a YAML, TOML or JSON file so it can be reviewed and
+/// versioned. The spec name doubles as the job id, so every run is diffed
+/// against the previous one.
+#[derive(Debug, Clone, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct JobSpec {
+    pub version: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub seeds: SeedSpec,
+    #[serde(default)]
+    pub links: LinkSpec,
+    #[serde(default)]
+    pub fetch: FetchSpec,
+    #[serde(default)]
+    pub extraction: ExtractionSpec,
+    #[serde(default)]
+    pub budget: BudgetSpec,
+    /// Files `scrapy-cli --job` writes the records to. Runs started through
+    /// the API return the records in the response and write no files.
+    #[serde(default)]
+    pub outputs: Vec<OutputSpec>,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    /// Skip extraction for pages unchanged since the previous run.
+    #[serde(default)]
+    pub incremental: bool,
+    /// Record field used to match records between runs.
+    pub record_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SeedSpec {
+    #[serde(default)]
+    pub urls: Vec<InputUrl>,
+    /// Sitemaps and feeds to expand into start URLs.
+    pub sitemaps: Option<SeedConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct LinkSpec {
+    pub follow: bool,
+    pub max_depth: Option<u32>,
+    pub max_pages_per_domain: Option<usize>,
+    pub pagination_boost: i32,
+    pub priority_patterns: Vec<PriorityPattern>,
+    pub canonical: CanonicalConfig,
+}
+
+impl Default for LinkSpec {
+    fn default() -> Self {
+        let frontier = FrontierConfig::default();
+        Self {
+            follow: frontier.follow_links,
+            max_depth: frontier.max_depth,
+            max_pages_per_domain: frontier.max_pages_per_domain,
+            pagination_boost: frontier.pagination_boost,
+            priority_patterns: frontier.priority_patterns,
+            canonical: CanonicalConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct FetchSpec {
+    pub fetcher: FetcherConfig,
+    pub http: Option<FetchOverrides>,
+    pub rate_limit: Option<RateLimitConfig>,
+    pub retry: Option<RetryConfig>,
+    pub session: Option<SessionConfig>,
+    pub ignore_robots_txt: bool,
+    pub bypass_cache: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct ExtractionSpec {
+    pub enabled: bool,
+    pub model: String,
+    /// Information to extract from each page.
+    pub fields: Vec<String>,
+    /// JSON schema the extracted records should follow.
+    pub schema: Option<serde_json::Value>,
+    pub dedup: DedupConfig,
+    /// Stored credential holding the AI provider key.
+    pub credential: Option<String>,
+}
+
+impl Default for ExtractionSpec {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            model: DEFAULT_MODEL.to_string(),
+            fields: Vec::new(),
+            schema: None,
+            dedup: DedupConfig::default(),
+            credential: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct BudgetSpec {
+    pub max_pages: Option<usize>,
+    pub max_cost_usd: Option<f64>,
+    pub max_tokens: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct OutputSpec {
+    pub format: ExportFormat,
+    pub path: PathBuf,
+}
+
+impl JobSpec {
+    /// Crawl parameters for a run of this spec. The API key is not part of
+    /// the spec so that specs can be committed: runs use the stored
+    /// credential named in the spec or the server's default key.
+    pub fn to_params(&self) -> ScrapeParams {
+        let links = &self.links;
+        let fetch = &self.fetch;
+        let extraction = &self.extraction;
+
+        ScrapeParams {
+            model: extraction.model.clone(),
+            api_key: Secret::default(),
+            credential: extraction.credential.clone(),
+            url: String::new(),
+            urls: self.seeds.urls.clone(),
+            enable_scraping: extraction.enabled,
+            tags: extraction.fields.clone(),
+            enable_pagination: false,
+            pagination_details: None,
+            ignore_robots_txt: fetch.ignore_robots_txt,
+            rate_limit: fetch.rate_limit,
+            retry: fetch.retry,
+            fetch: fetch.http.clone(),
+            session: fetch.session.clone(),
+            fetcher: fetch.fetcher.clone(),
+            seeds: self.seeds.sitemaps.clone(),
+            frontier: FrontierConfig {
+                follow_links: links.follow,
+                max_depth: links.max_depth,
+                max_pages: self.budget.max_pages,
+                max_pages_per_domain: links.max_pages_per_domain,
+                pagination_boost: links.pagination_boost,
+                priority_patterns: links.priority_patterns.clone(),
+            },
+            canonical: links.canonical.clone(),
+            dedup: extraction.dedup,
+            bypass_cache: fetch.bypass_cache,
+            job_id: Some(self.name.clone()),
+            incremental: self.incremental,
+            record_key: self.record_key.clone(),
+            webhooks: self.webhooks.clone(),
+            schema: extraction.schema.clone(),
+            budget: BudgetConfig {
+                max_cost_usd: self.budget.max_cost_usd,
+                max_tokens: self.budget.max_tokens,
+            },
+            owner: None,
+        }
+    }
+}
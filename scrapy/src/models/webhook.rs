@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{openapi::ApiSchema, vault::Secret};
+
+use super::RetryConfig;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum WebhookEvent {
+    JobCompleted,
+    JobFailed,
+    ItemsBatch,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::JobCompleted => "jobCompleted",
+            WebhookEvent::JobFailed => "jobFailed",
+            WebhookEvent::ItemsBatch => "itemsBatch",
+        }
+    }
+}
+
+/// An endpoint notified about crawl progress. When `secret` is set, every
+/// request carries an `X-Scrapy-Signature: sha256=<hex>` header holding the
+/// HMAC-SHA256 of `<X-Scrapy-Timestamp>.<body>`.
+#[derive(Debug, Clone, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookConfig {
+    pub url: String,
+    pub secret: Option<Secret>,
+    #[serde(default = "all_events")]
+    pub events: Vec<WebhookEvent>,
+    /// Number of extracted items sent per `itemsBatch` delivery.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    #[serde(default)]
+    pub retry: RetryConfig,
+}
+
+fn all_events() -> Vec<WebhookEvent> {
+    vec![
+        WebhookEvent::JobCompleted,
+        WebhookEvent::JobFailed,
+        WebhookEvent::ItemsBatch,
+    ]
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+
+impl WebhookConfig {
+    pub fn new(url: String, secret: Option<Secret>) -> Self {
+        Self {
+            url,
+            secret,
+            events: all_events(),
+            batch_size: default_batch_size(),
+            retry: RetryConfig::default(),
+        }
+    }
+
+    pub fn subscribes_to(&self, event: WebhookEvent) -> bool {
+        self.events.contains(&event)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    pub id: Uuid,
+    pub event: WebhookEvent,
+    pub crawl_id: Uuid,
+    pub job_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum WebhookDeliveryStatus {
+    Delivered,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub payload_id: Uuid,
+    pub url: String,
+    pub event: WebhookEvent,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: u32,
+    pub http_status: Option<u16>,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
@@ -0,0 +1,562 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use chrono::{DateTime, Utc};
+use rocket::http::Method;
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+pub use scrapy_derive::ApiSchema;
+pub use typescript::typescript;
+
+mod typescript;
+
+/// A type with a JSON schema in the OpenAPI document. Derive it with
+/// `#[derive(ApiSchema)]` next to the serde derives; the schema follows the
+/// same `rename_all`, `tag`, `default` and `skip` attributes.
+pub trait ApiSchema {
+    /// Name under `#/components/schemas`. Types without one are inlined.
+    fn schema_name() -> Option<&'static str> {
+        None
+    }
+
+    fn schema(components: &mut Components) -> Value;
+}
+
+/// The named schemas collected while a document is built.
+#[derive(Default)]
+pub struct Components {
+    schemas: BTreeMap<String, Value>,
+}
+
+impl Components {
+    /// The schema of `T`: a `$ref` for named types, which are added to the
+    /// components on first use, and the schema itself otherwise.
+    pub fn reference<T: ApiSchema + ?Sized>(&mut self) -> Value {
+        let Some(name) = T::schema_name() else {
+            return T::schema(self);
+        };
+        if !self.schemas.contains_key(name) {
+            // Reserve the name first so recursive types terminate.
+            self.schemas.insert(name.to_string(), Value::Null);
+            let schema = T::schema(self);
+            self.schemas.insert(name.to_string(), schema);
+        }
+        json!({ "$ref": format!("#/components/schemas/{}", name) })
+    }
+}
+
+/// A field of a generated object schema.
+pub struct Property {
+    pub name: &'static str,
+    pub schema: Value,
+    pub required: bool,
+    pub description: Option<&'static str>,
+}
+
+/// A variant of a generated enum schema; `content` is `None` for unit variants.
+pub struct Variant {
+    pub name: &'static str,
+    pub description: Option<&'static str>,
+    pub content: Option<Value>,
+}
+
+fn with_description(mut schema: Value, description: Option<&str>) -> Value {
+    if let (Some(description), Some(object)) = (description, schema.as_object_mut()) {
+        object.insert("description".to_string(), description.into());
+    }
+    schema
+}
+
+pub fn object(description: Option<&str>, properties: Vec<Property>, closed: bool) -> Value {
+    let mut schema = json!({ "type": "object" });
+    let required: Vec<&str> = properties
+        .iter()
+        .filter(|property| property.required)
+        .map(|property| property.name)
+        .collect();
+    schema["properties"] = properties
+        .into_iter()
+        .map(|property| {
+            (
+                property.name.to_string(),
+                with_description(property.schema, property.description),
+            )
+        })
+        .collect::<Map<_, _>>()
+        .into();
+    if !required.is_empty() {
+        schema["required"] = required.into();
+    }
+    if closed {
+        schema["additionalProperties"] = false.into();
+    }
+    with_description(schema, description)
+}
+
+pub fn string_enum(description: Option<&str>, values: &[&str]) -> Value {
+    with_description(json!({ "type": "string", "enum": values }), description)
+}
+
+/// An enum serialized as `{ "<tag>": "<variant>", ...fields }`.
+pub fn internally_tagged(description: Option<&str>, tag: &str, variants: Vec<Variant>) -> Value {
+    let variants = variants
+        .into_iter()
+        .map(|variant| {
+            let tag_only = json!({
+                "type": "object",
+                "properties": { tag: { "type": "string", "const": variant.name } },
+                "required": [tag],
+            });
+            let schema = match variant.content {
+                None => tag_only,
+                Some(mut content) if content.get("properties").is_some() => {
+                    content["properties"][tag] = tag_only["properties"][tag].clone();
+                    let mut required = vec![Value::from(tag)];
+                    if let Some(Value::Array(fields)) = content.get("required") {
+                        required.extend(fields.iter().cloned());
+                    }
+                    content["required"] = required.into();
+                    content
+                }
+                Some(content) => json!({ "allOf": [content, tag_only] }),
+            };
+            with_description(schema, variant.description)
+        })
+        .collect::<Vec<_>>();
+    with_description(
+        json!({ "oneOf": variants, "discriminator": { "propertyName": tag } }),
+        description,
+    )
+}
+
+/// An enum serialized as `"<variant>"` for unit variants and
+/// `{ "<variant>": content }` otherwise.
+pub fn externally_tagged(description: Option<&str>, variants: Vec<Variant>) -> Value {
+    let variants = variants
+        .into_iter()
+        .map(|variant| {
+            let schema = match variant.content {
+                None => json!({ "type": "string", "const": variant.name }),
+                Some(content) => json!({
+                    "type": "object",
+                    "properties": { variant.name: content },
+                    "required": [variant.name],
+                    "additionalProperties": false,
+                }),
+            };
+            with_description(schema, variant.description)
+        })
+        .collect::<Vec<_>>();
+    with_description(json!({ "oneOf": variants }), description)
+}
+
+/// An enum serialized as the content of its variant.
+pub fn untagged(description: Option<&str>, variants: Vec<Variant>) -> Value {
+    let variants = variants
+        .into_iter()
+        .map(|variant| {
+            let schema = variant.content.unwrap_or(json!({ "type": "null" }));
+            with_description(schema, variant.description)
+        })
+        .collect::<Vec<_>>();
+    with_description(json!({ "oneOf": variants }), description)
+}
+
+macro_rules! primitive {
+    ($schema:tt => $($ty:ty),+) => {
+        $(impl ApiSchema for $ty {
+            fn schema(_: &mut Components) -> Value {
+                json!($schema)
+            }
+        })+
+    };
+}
+
+primitive!({ "type": "string" } => String, str, PathBuf);
+primitive!({ "type": "boolean" } => bool);
+primitive!({ "type": "number" } => f32, f64);
+primitive!({ "type": "integer", "minimum": 0 } => u8, u16, u32, u64, usize);
+primitive!({ "type": "integer" } => i8, i16, i32, i64, isize);
+primitive!({ "type": "string", "format": "date-time" } => DateTime<Utc>);
+primitive!({ "type": "string", "format": "uuid" } => Uuid);
+primitive!({} => Value);
+
+impl<T: ApiSchema> ApiSchema for Option<T> {
+    fn schema(components: &mut Components) -> Value {
+        let schema = components.reference::<T>();
+        match schema.get("type").cloned() {
+            Some(Value::String(ty)) if schema.get("enum").is_none() => {
+                let mut schema = schema;
+                schema["type"] = json!([ty, "null"]);
+                schema
+            }
+            // Any JSON value already includes `null`.
+            _ if schema.as_object().is_some_and(Map::is_empty) => schema,
+            _ => json!({ "anyOf": [schema, { "type": "null" }] }),
+        }
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for Vec<T> {
+    fn schema(components: &mut Components) -> Value {
+        json!({ "type": "array", "items": components.reference::<T>() })
+    }
+}
+
+impl<T: ApiSchema> ApiSchema for Box<T> {
+    fn schema(components: &mut Components) -> Value {
+        components.reference::<T>()
+    }
+}
+
+impl<K, V: ApiSchema> ApiSchema for BTreeMap<K, V> {
+    fn schema(components: &mut Components) -> Value {
+        json!({ "type": "object", "additionalProperties": components.reference::<V>() })
+    }
+}
+
+impl<K, V: ApiSchema, S> ApiSchema for HashMap<K, V, S> {
+    fn schema(components: &mut Components) -> Value {
+        BTreeMap::<K, V>::schema(components)
+    }
+}
+
+type SchemaFn = fn(&mut Components) -> Value;
+
+struct Content {
+    media_type: &'static str,
+    schema: SchemaFn,
+}
+
+struct Response {
+    status: u16,
+    description: &'static str,
+    content: Option<Content>,
+}
+
+/// One documented route. Path parameters are taken from the `{name}`
+/// segments of the path.
+pub struct Operation {
+    method: Method,
+    path: &'static str,
+    id: &'static str,
+    tag: &'static str,
+    summary: &'static str,
+    description: Option<&'static str>,
+    public: bool,
+    query: Vec<(&'static str, &'static str)>,
+    body: Option<Content>,
+    response: Response,
+}
+
+impl Operation {
+    pub fn new(
+        method: Method,
+        path: &'static str,
+        id: &'static str,
+        tag: &'static str,
+        summary: &'static str,
+    ) -> Self {
+        Self {
+            method,
+            path,
+            id,
+            tag,
+            summary,
+            description: None,
+            public: false,
+            query: Vec::new(),
+            body: None,
+            response: Response {
+                status: 200,
+                description: "OK",
+                content: None,
+            },
+        }
+    }
+
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+
+    pub fn with_description(mut self, description: &'static str) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    /// Marks the route as reachable without an API key.
+    pub fn public(mut self) -> Self {
+        self.public = true;
+        self
+    }
+
+    pub fn with_query(mut self, name: &'static str, description: &'static str) -> Self {
+        self.query.push((name, description));
+        self
+    }
+
+    pub fn with_json_body<T: ApiSchema>(self) -> Self {
+        self.with_body("application/json", Components::reference::<T>)
+    }
+
+    pub fn with_body(mut self, media_type: &'static str, schema: SchemaFn) -> Self {
+        self.body = Some(Content { media_type, schema });
+        self
+    }
+
+    pub fn with_json_response<T: ApiSchema>(self) -> Self {
+        self.with_response(200, "OK", "application/json", Components::reference::<T>)
+    }
+
+    pub fn with_response(
+        mut self,
+        status: u16,
+        description: &'static str,
+        media_type: &'static str,
+        schema: SchemaFn,
+    ) -> Self {
+        self.response = Response {
+            status,
+            description,
+            content: Some(Content { media_type, schema }),
+        };
+        self
+    }
+
+    /// A success response without a body, such as `204 No Content`.
+    pub fn with_empty_response(mut self, status: u16, description: &'static str) -> Self {
+        self.response = Response {
+            status,
+            description,
+            content: None,
+        };
+        self
+    }
+
+    fn to_value(&self, components: &mut Components, error: &Value) -> Value {
+        let content = |content: &Content, components: &mut Components| json!({ content.media_type: { "schema": (content.schema)(components) } });
+
+        let mut parameters: Vec<Value> = self
+            .path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                })
+            })
+            .collect();
+        parameters.extend(self.query.iter().map(|(name, description)| {
+            json!({
+                "name": name,
+                "in": "query",
+                "description": description,
+                "schema": { "type": "string" },
+            })
+        }));
+
+        let mut success = json!({ "description": self.response.description });
+        if let Some(body) = &self.response.content {
+            success["content"] = content(body, components);
+        }
+        let mut operation = json!({
+            "operationId": self.id,
+            "tags": [self.tag],
+            "summary": self.summary,
+            "responses": {
+                self.response.status.to_string(): success,
+                "default": {
+                    "description": "Error",
+                    "content": { "application/json": { "schema": error } },
+                },
+            },
+        });
+        if let Some(description) = self.description {
+            operation["description"] = description.into();
+        }
+        if !parameters.is_empty() {
+            operation["parameters"] = parameters.into();
+        }
+        if let Some(body) = &self.body {
+            operation["requestBody"] = json!({
+                "required": true,
+                "content": content(body, components),
+            });
+        }
+        if self.public {
+            operation["security"] = json!([]);
+        }
+        operation
+    }
+}
+
+/// Builds an OpenAPI 3.1 document for `operations`, served under `base`.
+/// Failed requests are described by `E`, the body of every error response.
+pub fn document<E: ApiSchema>(title: &str, base: &str, operations: &[Operation]) -> Value {
+    let mut components = Components::default();
+    let error = components.reference::<E>();
+
+    let mut paths = Map::new();
+    for operation in operations {
+        let item = paths
+            .entry(operation.path)
+            .or_insert_with(|| Value::Object(Map::new()));
+        item[operation.method.as_str().to_ascii_lowercase()] =
+            operation.to_value(&mut components, &error);
+    }
+
+    json!({
+        "openapi": "3.1.0",
+        "info": { "title": title, "version": env!("CARGO_PKG_VERSION") },
+        "servers": [{ "url": base }],
+        "paths": paths,
+        "components": {
+            "schemas": components.schemas,
+            "securitySchemes": {
+                "apiKey": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "An API key from the server configuration",
+                },
+            },
+        },
+        "security": [{ "apiKey": [] }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    /// A test record.
+    #[derive(Serialize, Deserialize, ApiSchema)]
+    #[serde(rename_all = "camelCase")]
+    struct Record {
+        /// Where it came from.
+        source_url: String,
+        page_count: Option<u32>,
+        #[serde(default)]
+        tags: Vec<String>,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        owner: Option<String>,
+        kind: Kind,
+        fetcher: Fetcher,
+    }
+
+    #[derive(Serialize, Deserialize, ApiSchema)]
+    #[serde(rename_all = "camelCase")]
+    enum Kind {
+        Exact,
+        NearDuplicate,
+    }
+
+    #[derive(Serialize, Deserialize, ApiSchema)]
+    #[serde(rename_all = "camelCase", tag = "backend")]
+    enum Fetcher {
+        Http,
+        #[serde(rename_all = "camelCase")]
+        Browser {
+            timeout_ms: u64,
+        },
+    }
+
+    #[test]
+    fn derives_schemas_from_serde_attributes() {
+        let mut components = Components::default();
+        assert_eq!(
+            components.reference::<Record>(),
+            json!({ "$ref": "#/components/schemas/Record" })
+        );
+        let schemas = components.schemas;
+
+        let record = &schemas["Record"];
+        assert_eq!(record["description"], "A test record.");
+        assert_eq!(
+            record["properties"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            ["fetcher", "kind", "pageCount", "sourceUrl", "tags"]
+        );
+        assert_eq!(record["required"], json!(["sourceUrl", "kind", "fetcher"]));
+        assert_eq!(
+            record["properties"]["sourceUrl"],
+            json!({ "type": "string", "description": "Where it came from." })
+        );
+        assert_eq!(
+            record["properties"]["pageCount"]["type"],
+            json!(["integer", "null"])
+        );
+
+        assert_eq!(schemas["Kind"]["enum"], json!(["exact", "nearDuplicate"]));
+
+        let fetcher = &schemas["Fetcher"];
+        assert_eq!(fetcher["discriminator"]["propertyName"], "backend");
+        assert_eq!(
+            fetcher["oneOf"][0]["properties"]["backend"]["const"],
+            "http"
+        );
+        assert_eq!(
+            fetcher["oneOf"][1]["required"],
+            json!(["backend", "timeoutMs"])
+        );
+    }
+
+    #[test]
+    fn documents_operations() {
+        let operations = [
+            Operation::new(Method::Get, "/records/{id}", "get_record", "records", "Get")
+                .with_json_response::<Record>(),
+            Operation::new(
+                Method::Delete,
+                "/records/{id}",
+                "delete_record",
+                "records",
+                "Delete",
+            )
+            .with_empty_response(204, "Deleted"),
+            Operation::new(
+                Method::Post,
+                "/records",
+                "create_record",
+                "records",
+                "Create",
+            )
+            .with_json_body::<Record>()
+            .with_json_response::<Record>()
+            .public(),
+        ];
+        let document = document::<Kind>("Test", "/api", &operations);
+
+        let item = &document["paths"]["/records/{id}"];
+        assert_eq!(item["get"]["parameters"][0]["name"], "id");
+        assert_eq!(
+            item["get"]["responses"]["200"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/Record"
+        );
+        assert_eq!(
+            item["delete"]["responses"]["default"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/Kind"
+        );
+        assert!(item["delete"]["responses"]["204"].get("content").is_none());
+
+        let create = &document["paths"]["/records"]["post"];
+        assert_eq!(create["security"], json!([]));
+        assert!(create["requestBody"]["content"]["application/json"].is_object());
+        assert!(document["components"]["schemas"]["Fetcher"].is_object());
+    }
+}
@@ -0,0 +1,218 @@
+use std::fmt::Write;
+
+use serde_json::Value;
+
+/// TypeScript declarations for the component schemas of an OpenAPI
+/// document, for the web client to import instead of hand-written types.
+/// Only the subset of JSON schema that [`ApiSchema`](super::ApiSchema)
+/// generates is understood; anything else becomes `unknown`.
+pub fn typescript(document: &Value) -> String {
+    let mut out = String::from(
+        "// Generated from the server's OpenAPI document by `make api-types`.\n\
+         // Do not edit: change the Rust models and regenerate instead.\n",
+    );
+    let Some(schemas) = document["components"]["schemas"].as_object() else {
+        return out;
+    };
+    for (name, schema) in schemas {
+        out.push('\n');
+        comment(&mut out, schema, "");
+        if schema.get("properties").is_some() {
+            let _ = writeln!(out, "export interface {} {}", name, object(schema, ""));
+        } else {
+            let _ = writeln!(out, "export type {} = {};", name, ty(schema, ""));
+        }
+    }
+    out
+}
+
+fn comment(out: &mut String, schema: &Value, indent: &str) {
+    let Some(description) = schema.get("description").and_then(Value::as_str) else {
+        return;
+    };
+    let lines: Vec<&str> = description.lines().collect();
+    if let [line] = lines.as_slice() {
+        let _ = writeln!(out, "{}/** {} */", indent, line);
+    } else {
+        let _ = writeln!(out, "{}/**", indent);
+        for line in lines {
+            let _ = writeln!(out, "{} * {}", indent, line);
+        }
+        let _ = writeln!(out, "{} */", indent);
+    }
+}
+
+fn object(schema: &Value, indent: &str) -> String {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let inner = format!("{}  ", indent);
+    let mut out = String::from("{\n");
+    if let Some(properties) = schema["properties"].as_object() {
+        for (name, property) in properties {
+            comment(&mut out, property, &inner);
+            let optional = if required.contains(&name.as_str()) {
+                ""
+            } else {
+                "?"
+            };
+            let _ = writeln!(
+                out,
+                "{}{}{}: {};",
+                inner,
+                name,
+                optional,
+                ty(property, &inner)
+            );
+        }
+    }
+    let _ = write!(out, "{}}}", indent);
+    out
+}
+
+fn union(schemas: &[Value], indent: &str, separator: &str) -> String {
+    schemas
+        .iter()
+        .map(|schema| {
+            if is_compound(schema) {
+                format!("({})", ty(schema, indent))
+            } else {
+                ty(schema, indent)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+/// Whether the type of `schema` is a union or intersection, which needs
+/// parentheses inside another one.
+fn is_compound(schema: &Value) -> bool {
+    if schema.get("$ref").is_some() || schema.get("const").is_some() {
+        return false;
+    }
+    ["oneOf", "anyOf", "allOf", "enum", "type"]
+        .iter()
+        .any(|key| schema[key].as_array().is_some_and(|items| items.len() > 1))
+}
+
+fn ty(schema: &Value, indent: &str) -> String {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference
+            .rsplit('/')
+            .next()
+            .unwrap_or(reference)
+            .to_string();
+    }
+    if let Some(value) = schema.get("const") {
+        return value.to_string();
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return values
+            .iter()
+            .map(Value::to_string)
+            .collect::<Vec<_>>()
+            .join(" | ");
+    }
+    if let Some(schemas) = schema
+        .get("oneOf")
+        .or_else(|| schema.get("anyOf"))
+        .and_then(Value::as_array)
+    {
+        return union(schemas, indent, " | ");
+    }
+    if let Some(schemas) = schema.get("allOf").and_then(Value::as_array) {
+        return union(schemas, indent, " & ");
+    }
+    match &schema["type"] {
+        Value::Array(types) => {
+            let types: Vec<Value> = types
+                .iter()
+                .map(|ty| {
+                    let mut schema = schema.clone();
+                    schema["type"] = ty.clone();
+                    schema
+                })
+                .collect();
+            union(&types, indent, " | ")
+        }
+        Value::String(name) => match name.as_str() {
+            "string" => "string".to_string(),
+            "integer" | "number" => "number".to_string(),
+            "boolean" => "boolean".to_string(),
+            "null" => "null".to_string(),
+            "array" => {
+                let items = union(std::slice::from_ref(&schema["items"]), indent, "");
+                format!("{}[]", items)
+            }
+            "object" if schema.get("properties").is_some() => object(schema, indent),
+            "object" => match schema.get("additionalProperties") {
+                Some(values) if values.is_object() => {
+                    format!("Record<string, {}>", ty(values, indent))
+                }
+                _ => "Record<string, unknown>".to_string(),
+            },
+            _ => "unknown".to_string(),
+        },
+        _ => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn declares_component_schemas() {
+        let document = json!({
+            "components": { "schemas": {
+                "Kind": { "type": "string", "enum": ["exact", "near"] },
+                "Page": {
+                    "type": "object",
+                    "description": "A crawled page.",
+                    "properties": {
+                        "url": { "type": "string" },
+                        "status": { "type": ["integer", "null"] },
+                        "kinds": { "type": "array", "items": { "anyOf": [
+                            { "$ref": "#/components/schemas/Kind" },
+                            { "type": "null" },
+                        ] } },
+                        "fields": { "type": "object", "additionalProperties": {} },
+                    },
+                    "required": ["url", "kinds"],
+                },
+                "Fetcher": { "oneOf": [
+                    { "type": "object", "properties": { "backend": { "const": "http" } } },
+                    { "allOf": [
+                        { "$ref": "#/components/schemas/Page" },
+                        { "type": "object", "properties": { "backend": { "const": "browser" } } },
+                    ] },
+                ] },
+            } },
+        });
+
+        assert_eq!(
+            typescript(&document).lines().skip(2).collect::<Vec<_>>(),
+            [
+                "",
+                "export type Fetcher = {",
+                "  backend?: \"http\";",
+                "} | (Page & {",
+                "  backend?: \"browser\";",
+                "});",
+                "",
+                "export type Kind = \"exact\" | \"near\";",
+                "",
+                "/** A crawled page. */",
+                "export interface Page {",
+                "  fields?: Record<string, unknown>;",
+                "  kinds: (Kind | null)[];",
+                "  status?: number | null;",
+                "  url: string;",
+                "}",
+            ]
+        );
+    }
+}
@@ -0,0 +1,471 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    sync::Arc,
+};
+
+use ipnet::IpNet;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect, ClientBuilder,
+};
+use thiserror::Error;
+use url::{Host, Url};
+
+use crate::{config::OutboundSettings, error::AppError};
+
+/// Why a connection was refused. Kept as the source of the `reqwest` error so
+/// it can be told apart from other connection failures.
+#[derive(Debug, Error)]
+#[error("{0}")]
+pub struct Blocked(String);
+
+/// Decides which hosts the server may connect to on behalf of a crawl, so
+/// requests cannot reach the server's own network. Names are checked against
+/// every address they resolve to, including after redirects.
+#[derive(Debug)]
+pub struct OutboundGuard {
+    allow_private_networks: bool,
+    allowed_hosts: Vec<String>,
+    denied_hosts: Vec<String>,
+    allowed_cidrs: Vec<IpNet>,
+    denied_cidrs: Vec<IpNet>,
+    allow_job_proxies: bool,
+}
+
+impl Default for OutboundGuard {
+    /// Blocks private, loopback and link-local addresses.
+    fn default() -> Self {
+        Self::new(&OutboundSettings::default()).expect("default outbound settings are valid")
+    }
+}
+
+impl OutboundGuard {
+    pub fn new(settings: &OutboundSettings) -> Result<Self, AppError> {
+        let cidrs = |key: &str, values: &[String]| {
+            values
+                .iter()
+                .map(|value| {
+                    value.parse::<IpNet>().map_err(|_| {
+                        AppError::Config(format!(
+                            "Invalid outbound.{} entry {:?}: expected a CIDR such as 10.0.0.0/8",
+                            key, value
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let hosts = |values: &[String]| {
+            values
+                .iter()
+                .map(|host| host.trim().trim_end_matches('.').to_ascii_lowercase())
+                .collect()
+        };
+
+        Ok(Self {
+            allow_private_networks: settings.allow_private_networks,
+            allowed_hosts: hosts(&settings.allowed_hosts),
+            denied_hosts: hosts(&settings.denied_hosts),
+            allowed_cidrs: cidrs("allowed_cidrs", &settings.allowed_cidrs)?,
+            denied_cidrs: cidrs("denied_cidrs", &settings.denied_cidrs)?,
+            allow_job_proxies: settings.allow_job_proxies,
+        })
+    }
+
+    /// Allows every destination, for tests against local servers.
+    pub fn unrestricted() -> Self {
+        Self {
+            allow_private_networks: true,
+            allowed_hosts: Vec::new(),
+            denied_hosts: Vec::new(),
+            allowed_cidrs: Vec::new(),
+            denied_cidrs: Vec::new(),
+            allow_job_proxies: true,
+        }
+    }
+
+    /// Sets up `builder` to resolve names through the guard and to check
+    /// every redirect target.
+    pub fn configure(
+        self: &Arc<Self>,
+        builder: ClientBuilder,
+        max_redirects: usize,
+    ) -> ClientBuilder {
+        let guard = self.clone();
+        let policy = redirect::Policy::custom(move |attempt| {
+            if max_redirects == 0 {
+                return attempt.stop();
+            }
+            if attempt.previous().len() > max_redirects {
+                return attempt.error(format!("too many redirects (limit {})", max_redirects));
+            }
+            match guard.check_url(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(blocked) => attempt.error(blocked),
+            }
+        });
+        builder
+            .dns_resolver(Arc::new(GuardedResolver(self.clone())))
+            .redirect(policy)
+    }
+
+    /// Checks `url` before a request. Hosts given as IP addresses are not
+    /// resolved, so this is their only check.
+    pub fn check(&self, url: &str) -> Result<(), AppError> {
+        let parsed = Url::parse(url).map_err(|err| AppError::Blocked {
+            url: url.to_string(),
+            reason: format!("invalid URL: {}", err),
+        })?;
+        self.check_url(&parsed)
+            .map_err(|blocked| AppError::Blocked {
+                url: url.to_string(),
+                reason: blocked.0,
+            })
+    }
+
+    /// Like [`check`](Self::check), but also resolves host names, for
+    /// fetchers that do not connect through a guarded client.
+    pub async fn check_resolved(&self, url: &str) -> Result<(), AppError> {
+        self.check(url)?;
+        let Ok(parsed) = Url::parse(url) else {
+            return Ok(());
+        };
+        self.check_addresses(url, &parsed).await
+    }
+
+    /// Checks the proxies a crawl request brings. Hosts reached through a
+    /// proxy are resolved by the proxy, so they are only allowed when the
+    /// server opts in, and the proxy itself must be a permitted destination.
+    pub async fn check_job_proxies(&self, proxies: &[String]) -> Result<(), AppError> {
+        if proxies.is_empty() {
+            return Ok(());
+        }
+        if !self.allow_job_proxies {
+            return Err(AppError::FetchConfig(
+                "proxies are not allowed in crawl requests; set outbound.allow_job_proxies"
+                    .to_string(),
+            ));
+        }
+        for proxy in proxies {
+            let blocked = |reason: String| AppError::Blocked {
+                url: proxy.clone(),
+                reason,
+            };
+            let parsed =
+                Url::parse(proxy).map_err(|err| blocked(format!("invalid URL: {}", err)))?;
+            match parsed.host() {
+                Some(Host::Domain(_)) => {}
+                Some(Host::Ipv4(ip)) => self
+                    .check_ip(&ip.to_string(), IpAddr::V4(ip))
+                    .map_err(|err| blocked(err.0))?,
+                Some(Host::Ipv6(ip)) => self
+                    .check_ip(&ip.to_string(), IpAddr::V6(ip))
+                    .map_err(|err| blocked(err.0))?,
+                None => return Err(blocked("URL has no host".to_string())),
+            }
+            self.check_addresses(proxy, &parsed).await?;
+        }
+        Ok(())
+    }
+
+    /// Checks every address the host name of `parsed` resolves to.
+    async fn check_addresses(&self, url: &str, parsed: &Url) -> Result<(), AppError> {
+        let Some(Host::Domain(host)) = parsed.host() else {
+            return Ok(());
+        };
+        let port = parsed.port_or_known_default().unwrap_or(80);
+        let blocked = |reason: String| AppError::Blocked {
+            url: url.to_string(),
+            reason,
+        };
+        if self.check_host(host).map_err(|err| blocked(err.0))? {
+            return Ok(());
+        }
+        for addr in tokio::net::lookup_host((host, port)).await? {
+            self.check_ip(host, addr.ip())
+                .map_err(|err| blocked(err.0))?;
+        }
+        Ok(())
+    }
+
+    fn check_url(&self, url: &Url) -> Result<(), Blocked> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(Blocked(format!("scheme {:?} is not allowed", url.scheme())));
+        }
+        match url.host() {
+            Some(Host::Domain(host)) => self.check_host(host).map(|_| ()),
+            Some(Host::Ipv4(ip)) => self.check_ip(&ip.to_string(), IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => self.check_ip(&ip.to_string(), IpAddr::V6(ip)),
+            None => Err(Blocked("URL has no host".to_string())),
+        }
+    }
+
+    /// Fails for denied hosts; `Ok(true)` for allowed hosts, whose addresses
+    /// are trusted.
+    fn check_host(&self, host: &str) -> Result<bool, Blocked> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if self
+            .denied_hosts
+            .iter()
+            .any(|pattern| matches_host(pattern, &host))
+        {
+            return Err(Blocked(format!("host {} is denied", host)));
+        }
+        Ok(self
+            .allowed_hosts
+            .iter()
+            .any(|pattern| matches_host(pattern, &host)))
+    }
+
+    /// Checks an address of `host`, which is the address itself for IP URLs.
+    fn check_ip(&self, host: &str, ip: IpAddr) -> Result<(), Blocked> {
+        let blocked = |what: &str| {
+            if host == ip.to_string() {
+                Blocked(format!("{} is {}", ip, what))
+            } else {
+                Blocked(format!("{} resolves to {}, {}", host, ip, what))
+            }
+        };
+        let ip = match ip {
+            IpAddr::V6(v6) => embedded_ipv4(v6).map_or(ip, IpAddr::V4),
+            IpAddr::V4(_) => ip,
+        };
+        if self.denied_cidrs.iter().any(|net| net.contains(&ip)) {
+            return Err(blocked("a denied address"));
+        }
+        if self.allow_private_networks
+            || is_public(ip)
+            || self.allowed_cidrs.iter().any(|net| net.contains(&ip))
+        {
+            return Ok(());
+        }
+        Err(blocked("a private, loopback or link-local address"))
+    }
+}
+
+/// `example.com` matches only itself, `*.example.com` its subdomains.
+fn matches_host(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.')),
+        None => pattern == host,
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => is_public_v6(ip),
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        // Shared address space (carrier-grade NAT).
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments.
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking and reserved ranges.
+        || (a == 198 && (18..20).contains(&b))
+        || a >= 240)
+}
+
+/// IPv4 address an IPv6 address reaches: IPv4-mapped (`::ffff:0:0/96`),
+/// IPv4-compatible (`::/96`), NAT64 (`64:ff9b::/96`) and 6to4 (`2002::/16`)
+/// addresses carry one.
+fn embedded_ipv4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let segments = ip.segments();
+    let [.., a, b, c, d] = ip.octets();
+    match segments {
+        [0, 0, 0, 0, 0, 0xffff, ..] | [0, 0, 0, 0, 0, 0, ..] | [0x64, 0xff9b, 0, 0, 0, 0, ..] => {
+            Some(Ipv4Addr::new(a, b, c, d))
+        }
+        [0x2002, high, low, ..] => Some(Ipv4Addr::from((high as u32) << 16 | low as u32)),
+        _ => None,
+    }
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        // Unique local (fc00::/7) and link-local (fe80::/10) addresses.
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+        // Documentation prefix.
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+/// Resolves names for guarded clients and refuses hosts with any blocked
+/// address, so a name cannot point at the server's network.
+struct GuardedResolver(Arc<OutboundGuard>);
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let guard = self.0.clone();
+        Box::pin(async move {
+            let host = name.as_str();
+            let trusted = guard.check_host(host)?;
+            let addrs: Vec<_> = tokio::net::lookup_host((host, 0)).await?.collect();
+            if !trusted {
+                for addr in &addrs {
+                    guard.check_ip(host, addr.ip())?;
+                }
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    fn guard(settings: OutboundSettings) -> OutboundGuard {
+        OutboundGuard::new(&settings).unwrap()
+    }
+
+    #[test]
+    fn blocks_private_addresses_and_denied_hosts() {
+        let default = OutboundGuard::default();
+        for url in [
+            "http://169.254.169.254/latest/meta-data/",
+            "http://127.0.0.1:8000/api",
+            "http://10.1.2.3/",
+            "http://192.168.0.1/",
+            "http://[::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[::127.0.0.1]/",
+            "http://[64:ff9b::a9fe:a9fe]/",
+            "http://[2002:c0a8:101::1]/",
+            "http://192.0.0.8/",
+            "http://[fd00::1]/",
+            "file:///etc/passwd",
+        ] {
+            let err = default.check(url).unwrap_err();
+            assert!(matches!(err, AppError::Blocked { .. }), "{}: {}", url, err);
+        }
+        default.check("https://93.184.215.14/").unwrap();
+        default.check("https://[64:ff9b::5db8:d70e]/").unwrap();
+        default.check("https://[2002:5db8:d70e::1]/").unwrap();
+        default.check("https://example.com/").unwrap();
+
+        let custom = guard(OutboundSettings {
+            denied_hosts: vec!["*.internal.example.com".to_string()],
+            allowed_cidrs: vec!["10.1.0.0/16".to_string()],
+            denied_cidrs: vec!["93.184.0.0/16".to_string()],
+            ..OutboundSettings::default()
+        });
+        custom.check("http://10.1.2.3/").unwrap();
+        assert!(custom.check("http://10.2.0.1/").is_err());
+        assert!(custom.check("https://93.184.215.14/").is_err());
+        assert!(custom.check("https://db.internal.example.com/").is_err());
+        custom.check("https://internal.example.com/").unwrap();
+
+        assert!(OutboundGuard::new(&OutboundSettings {
+            denied_cidrs: vec!["10.0.0.0/33".to_string()],
+            ..OutboundSettings::default()
+        })
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn checks_job_proxies() {
+        let proxies = |proxies: &[&str]| proxies.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let err = OutboundGuard::default()
+            .check_job_proxies(&proxies(&["http://203.0.114.7:3128"]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::FetchConfig(_)), "{}", err);
+
+        let allowed = guard(OutboundSettings {
+            allow_job_proxies: true,
+            ..OutboundSettings::default()
+        });
+        allowed.check_job_proxies(&[]).await.unwrap();
+        allowed
+            .check_job_proxies(&proxies(&["socks5://93.184.215.14:1080"]))
+            .await
+            .unwrap();
+        for proxy in ["http://10.0.0.5:3128", "socks5h://localhost:1080"] {
+            let err = allowed
+                .check_job_proxies(&proxies(&[proxy]))
+                .await
+                .unwrap_err();
+            assert!(
+                matches!(err, AppError::Blocked { .. }),
+                "{}: {}",
+                proxy,
+                err
+            );
+        }
+    }
+
+    /// Serves a single response that redirects to `location`.
+    async fn redirect_server(location: String) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request).await;
+                let response = format!(
+                    "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\r\n",
+                    location
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        port
+    }
+
+    async fn get(guard: OutboundGuard, url: &str) -> AppError {
+        let client = Arc::new(guard)
+            .configure(reqwest::Client::builder(), 10)
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        client.get(url).send().await.unwrap_err().into()
+    }
+
+    #[tokio::test]
+    async fn checks_resolved_names_and_redirects() {
+        let port = redirect_server("http://10.255.255.1/".to_string()).await;
+        let url = format!("http://localhost:{}/", port);
+
+        let err = get(OutboundGuard::default(), &url).await;
+        assert!(
+            matches!(&err, AppError::Blocked { reason, .. } if reason.contains("loopback")),
+            "{}",
+            err
+        );
+
+        // An allowed host may be reached, but not redirect to a private address.
+        let allowed = guard(OutboundSettings {
+            allowed_hosts: vec!["localhost".to_string()],
+            ..OutboundSettings::default()
+        });
+        let err = get(allowed, &url).await;
+        assert!(
+            matches!(&err, AppError::Blocked { reason, .. } if reason.contains("10.255.255.1")),
+            "{}",
+            err
+        );
+    }
+}
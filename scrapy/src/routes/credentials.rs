@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use rocket::{delete, get, http::Status, put, serde::json::Json, State};
+
+use crate::{
+    auth::User,
+    error::AppError,
+    models::{Credential, CredentialRequest},
+    vault::CredentialVault,
+};
+
+/// Credentials visible to the caller. Values are never returned.
+#[get("/credentials")]
+pub async fn list_credentials(
+    user: User,
+    vault: &State<Arc<CredentialVault>>,
+) -> Json<Vec<Credential>> {
+    Json(vault.list(&user).await)
+}
+
+/// Stores a provider key for the caller, to be used as `credential` in
+/// crawl requests.
+#[put("/credentials/<name>", data = "<request>")]
+pub async fn put_credential(
+    name: &str,
+    request: Json<CredentialRequest>,
+    user: User,
+    vault: &State<Arc<CredentialVault>>,
+) -> Result<Json<Credential>, AppError> {
+    vault
+        .put(user.name.as_deref(), name, request.into_inner())
+        .await
+        .map(Json)
+}
+
+#[delete("/credentials/<name>")]
+pub async fn delete_credential(
+    name: &str,
+    user: User,
+    vault: &State<Arc<CredentialVault>>,
+) -> Result<Status, AppError> {
+    if vault.remove(user.name.as_deref(), name).await? {
+        Ok(Status::NoContent)
+    } else {
+        Err(AppError::NotFound(format!(
+            "no credential named {:?}",
+            name
+        )))
+    }
+}
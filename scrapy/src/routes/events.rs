@@ -7,14 +7,21 @@ use rocket::{
     State,
 };
 
-use crate::services::WebSocketService;
+use crate::{auth::User, metrics::Metrics, services::WebSocketService};
 
 use rocket::tokio::time::Duration;
 
+/// `EventSource` cannot set headers, so the API key may be passed as
+/// `?access_token=`.
 #[get("/events")]
-pub async fn sse_events(websocket_service: &State<Arc<WebSocketService>>) -> EventStream![] {
+pub async fn sse_events(
+    user: User,
+    websocket_service: &State<Arc<WebSocketService>>,
+    metrics: &State<Arc<Metrics>>,
+) -> EventStream![] {
     log::info!("🌟 Client connected to SSE events stream");
     let receiver = websocket_service.subscribe().await;
+    let client = metrics.connect_client("sse");
 
     // Create a heartbeat stream
     let heartbeat = stream::repeat_with(|| Event::data("❤️")).then(|e: Event| async move {
@@ -24,16 +31,21 @@ pub async fn sse_events(websocket_service: &State<Arc<WebSocketService>>) -> Eve
 
     // Combine message stream with heartbeat
     EventStream! {
+        // Counted as connected until the stream is dropped.
+        let _client = client;
         let combined_stream = stream::select(
-            stream::unfold(receiver, |mut rx| async move {
-                match rx.recv().await {
-                    Ok(message) => {
-                        log::debug!("📤 Sending SSE event: {:?}", message);
-                        Some((Event::json(&message), rx))
-                    },
-                    Err(e) => {
-                        log::error!("❌ SSE channel closed: {}", e);
-                        None
+            stream::unfold((receiver, user), |(mut rx, user)| async move {
+                loop {
+                    match rx.recv().await {
+                        Ok(message) if !user.can_access(message.owner.as_deref()) => continue,
+                        Ok(message) => {
+                            log::debug!("📤 Sending SSE event: {:?}", message);
+                            return Some((Event::json(&message), (rx, user)));
+                        },
+                        Err(e) => {
+                            log::error!("❌ SSE channel closed: {}", e);
+                            return None;
+                        }
                     }
                 }
             }),
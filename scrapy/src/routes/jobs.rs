@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use rocket::{get, serde::json::Json, State};
+use uuid::Uuid;
+
+use crate::{
+    auth::User,
+    error::AppError,
+    models::{JobRecord, Usage},
+    services::AccountService,
+};
+
+/// The caller's crawls, or everyone's for admins; most recent first.
+#[get("/jobs")]
+pub async fn list_jobs(
+    user: User,
+    account_service: &State<Arc<AccountService>>,
+) -> Json<Vec<JobRecord>> {
+    Json(account_service.jobs(&user).await)
+}
+
+#[get("/jobs/<id>")]
+pub async fn get_job(
+    id: &str,
+    user: User,
+    account_service: &State<Arc<AccountService>>,
+) -> Result<Json<JobRecord>, AppError> {
+    let not_found = || AppError::NotFound(format!("no job with id {:?}", id));
+    let uuid = Uuid::parse_str(id).map_err(|_| not_found())?;
+    account_service
+        .job(&user, uuid)
+        .await
+        .map(Json)
+        .ok_or_else(not_found)
+}
+
+/// The caller's quotas and what is used of them.
+#[get("/usage")]
+pub async fn get_usage(user: User, account_service: &State<Arc<AccountService>>) -> Json<Usage> {
+    Json(account_service.usage(&user).await)
+}
@@ -0,0 +1,21 @@
+use std::sync::Arc;
+
+use rocket::{get, http::ContentType, State};
+
+use crate::{auth::User, error::AppError, metrics::Metrics};
+
+/// Prometheus scrape endpoint. Scrapers authenticate with an admin API key
+/// like any other client.
+#[get("/metrics")]
+pub fn get_metrics(
+    user: User,
+    metrics: &State<Arc<Metrics>>,
+) -> Result<(ContentType, String), AppError> {
+    if !user.admin {
+        return Err(AppError::Forbidden(
+            "metrics are only visible to admins".to_string(),
+        ));
+    }
+    let content_type = ContentType::new("text", "plain").with_params(("version", "0.0.4"));
+    Ok((content_type, metrics.render()))
+}
@@ -1,16 +1,70 @@
 use rocket::{get, serde::json::Json};
-use rocket::{post, State};
+use rocket::{post, routes, Route, State};
 use std::sync::Arc;
 
-use crate::models::{ScrapeParams, ScrapingResult};
-use crate::services::CrawlerService;
+use crate::auth::User;
+use crate::error::AppError;
+use crate::models::{CrawlResponse, ScrapeParams, ScrapingResult, WebhookDelivery};
+use crate::services::{CrawlerService, WebhookService};
 use crate::utils::get_all_models;
 
-pub use ws::websocket;
+pub use credentials::{delete_credential, list_credentials, put_credential};
 pub use events::sse_events;
+pub use jobs::{get_job, get_usage, list_jobs};
+pub use metrics::get_metrics;
+pub use openapi::{api_docs, openapi_document, openapi_json};
+pub use schedules::{
+    create_schedule, delete_schedule, disable_schedule, enable_schedule, get_schedule,
+    list_schedules, run_schedule, schedule_runs, update_schedule,
+};
+pub use specs::{delete_spec, get_spec, list_specs, run_spec, upload_spec};
+pub use upload::crawl_upload;
+pub use ws::websocket;
 
-mod ws;
+mod credentials;
 mod events;
+mod jobs;
+mod metrics;
+mod openapi;
+mod schedules;
+mod specs;
+mod upload;
+mod ws;
+
+/// The routes mounted under `/api`.
+pub fn api() -> Vec<Route> {
+    routes![
+        index,
+        openapi_json,
+        api_docs,
+        crawl,
+        crawl_upload,
+        websocket,
+        sse_events,
+        get_models,
+        list_schedules,
+        create_schedule,
+        get_schedule,
+        update_schedule,
+        delete_schedule,
+        enable_schedule,
+        disable_schedule,
+        run_schedule,
+        schedule_runs,
+        webhook_deliveries,
+        list_specs,
+        get_spec,
+        upload_spec,
+        delete_spec,
+        run_spec,
+        list_jobs,
+        get_job,
+        get_usage,
+        list_credentials,
+        put_credential,
+        delete_credential
+    ]
+}
 
 #[get("/")]
 pub fn index() -> &'static str {
@@ -19,44 +73,73 @@ pub fn index() -> &'static str {
 }
 
 #[get("/models")]
-pub fn get_models() -> Json<Vec<String>> {
+pub fn get_models(_user: User) -> Json<Vec<String>> {
     Json(get_all_models())
 }
 
+#[get("/webhooks/deliveries")]
+pub fn webhook_deliveries(
+    user: User,
+    webhook_service: &State<Arc<WebhookService>>,
+) -> Result<Json<Vec<WebhookDelivery>>, AppError> {
+    if !user.admin {
+        return Err(AppError::Forbidden(
+            "webhook deliveries are only visible to admins".to_string(),
+        ));
+    }
+    Ok(Json(webhook_service.deliveries()))
+}
+
 #[post("/crawl", data = "<params>")]
 pub async fn crawl(
     params: Json<ScrapeParams>,
+    user: User,
     crawler_service: &State<Arc<CrawlerService>>,
-) -> Result<Json<Vec<ScrapingResult>>, rocket::http::Status> {
+) -> Result<Json<CrawlResponse>, AppError> {
+    run_crawl(params.into_inner(), &user, crawler_service).await
+}
+
+/// Runs a crawl on behalf of `user`, who is charged for it.
+async fn run_crawl(
+    mut params: ScrapeParams,
+    user: &User,
+    crawler_service: &CrawlerService,
+) -> Result<Json<CrawlResponse>, AppError> {
+    params.owner = user.name.clone();
+    let start_urls = params.start_urls();
+
     log::info!(
-        "Initiating crawl request for URL: {} with parameters: {:#?}",
-        params.url,
-        params
+        "Initiating crawl request for {} start URL(s) with model {:?} for {:?}",
+        start_urls.len(),
+        params.model,
+        params.owner
     );
 
-    let params = params.into_inner();
-    match crawler_service.crawl(params.clone()).await {
-        Ok(results) => {
-            log::info!(
-                "Crawl operation completed successfully for URL: {}",
-                params.url
-            );
-            log::debug!("Crawl results: {:?}", results);
-            let response = results
-                .into_iter()
-                .map(|r| ScrapingResult {
-                    all_data: r.data.as_array().unwrap_or(&Vec::new()).to_vec(),
-                    input_tokens: r.usage_metadata.input_tokens,
-                    output_tokens: r.usage_metadata.output_tokens,
-                    total_cost: r.usage_metadata.total_cost,
-                    pagination_info: None,
-                })
-                .collect();
-            Ok(Json(response))
-        }
-        Err(e) => {
-            log::error!("Crawl operation failed: {}", e);
-            Err(rocket::http::Status::InternalServerError)
-        }
-    }
+    let (results, report) = crawler_service.crawl(params).await?;
+    log::info!(
+        "Crawl operation completed successfully for {} start URL(s)",
+        start_urls.len()
+    );
+    log::debug!("Crawl results: {:?}", results);
+    let results = results
+        .into_iter()
+        .map(|r| ScrapingResult {
+            all_data: r.data.as_array().unwrap_or(&Vec::new()).to_vec(),
+            input_tokens: r.usage_metadata.input_tokens,
+            output_tokens: r.usage_metadata.output_tokens,
+            total_cost: r.usage_metadata.total_cost,
+            pagination_info: None,
+            url: r.url,
+            input_rows: r.input_rows,
+        })
+        .collect();
+    Ok(Json(CrawlResponse {
+        results,
+        pages: report.pages,
+        frontier: report.frontier,
+        dedup: report.dedup,
+        cache: report.cache,
+        budget: report.budget,
+        diff: report.diff,
+    }))
 }
@@ -0,0 +1,343 @@
+use rocket::{get, http::Method, response::content::RawHtml, serde::json::Json};
+use serde_json::{json, Value};
+
+use crate::{
+    auth::ACCESS_TOKEN_PARAM,
+    error::ApiError,
+    models::{
+        CrawlResponse, Credential, CredentialRequest, JobRecord, Schedule, ScheduleRequest,
+        ScheduleRun, ScrapeParams, Usage, WebSocketMessage, WebhookDelivery,
+    },
+    openapi::{self, Components, Operation},
+    specs::{SpecSummary, StoredSpec},
+};
+
+fn upload_form(components: &mut Components) -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "params": components.reference::<ScrapeParams>(),
+            "file": {
+                "type": "string",
+                "format": "binary",
+                "description": "URLs one per line, or a CSV file with a `url` column",
+            },
+        },
+        "required": ["params", "file"],
+    })
+}
+
+fn spec_source(_: &mut Components) -> Value {
+    json!({ "type": "string", "description": "The spec file in the given format" })
+}
+
+fn text(_: &mut Components) -> Value {
+    json!({ "type": "string" })
+}
+
+/// Every route mounted under `/api`, with its request and response types.
+pub fn operations() -> Vec<Operation> {
+    use Method::{Delete, Get, Post, Put};
+
+    let stream_description = "Crawl progress messages. Browsers cannot set headers on \
+        this connection, so the API key may be passed in the `access_token` query parameter.";
+    vec![
+        Operation::new(Get, "/", "index", "meta", "Check that the server is up")
+            .with_response(200, "OK", "text/plain", text)
+            .public(),
+        Operation::new(Get, "/openapi.json", "openapi", "meta", "This document")
+            .with_response(
+                200,
+                "OK",
+                "application/json",
+                |_| json!({ "type": "object" }),
+            )
+            .public(),
+        Operation::new(Get, "/docs", "api_docs", "meta", "API reference page")
+            .with_response(200, "OK", "text/html", text)
+            .public(),
+        Operation::new(Get, "/models", "get_models", "crawl", "List AI models")
+            .with_json_response::<Vec<String>>(),
+        Operation::new(Post, "/crawl", "crawl", "crawl", "Run a crawl")
+            .with_json_body::<ScrapeParams>()
+            .with_json_response::<CrawlResponse>(),
+        Operation::new(
+            Post,
+            "/crawl/upload",
+            "crawl_upload",
+            "crawl",
+            "Run a crawl over an uploaded URL list",
+        )
+        .with_body("multipart/form-data", upload_form)
+        .with_json_response::<CrawlResponse>(),
+        Operation::new(
+            Get,
+            "/ws",
+            "websocket",
+            "crawl",
+            "Crawl progress over a WebSocket",
+        )
+        .with_description(stream_description)
+        .with_query(ACCESS_TOKEN_PARAM, "API key")
+        .with_response(
+            101,
+            "Switching to the WebSocket protocol; each message is a WebSocketMessage",
+            "application/json",
+            Components::reference::<WebSocketMessage>,
+        ),
+        Operation::new(
+            Get,
+            "/events",
+            "sse_events",
+            "crawl",
+            "Crawl progress as server-sent events",
+        )
+        .with_description(stream_description)
+        .with_query(ACCESS_TOKEN_PARAM, "API key")
+        .with_response(
+            200,
+            "An event stream; each event's data is a WebSocketMessage",
+            "text/event-stream",
+            Components::reference::<WebSocketMessage>,
+        ),
+        Operation::new(Get, "/jobs", "list_jobs", "jobs", "List crawl jobs")
+            .with_json_response::<Vec<JobRecord>>(),
+        Operation::new(Get, "/jobs/{id}", "get_job", "jobs", "Get a crawl job")
+            .with_json_response::<JobRecord>(),
+        Operation::new(Get, "/usage", "get_usage", "jobs", "Get spend and limits")
+            .with_json_response::<Usage>(),
+        Operation::new(
+            Get,
+            "/schedules",
+            "list_schedules",
+            "schedules",
+            "List schedules",
+        )
+        .with_json_response::<Vec<Schedule>>(),
+        Operation::new(
+            Post,
+            "/schedules",
+            "create_schedule",
+            "schedules",
+            "Create a schedule",
+        )
+        .with_json_body::<ScheduleRequest>()
+        .with_json_response::<Schedule>(),
+        Operation::new(
+            Get,
+            "/schedules/{id}",
+            "get_schedule",
+            "schedules",
+            "Get a schedule",
+        )
+        .with_json_response::<Schedule>(),
+        Operation::new(
+            Put,
+            "/schedules/{id}",
+            "update_schedule",
+            "schedules",
+            "Update a schedule",
+        )
+        .with_json_body::<ScheduleRequest>()
+        .with_json_response::<Schedule>(),
+        Operation::new(
+            Delete,
+            "/schedules/{id}",
+            "delete_schedule",
+            "schedules",
+            "Delete a schedule",
+        )
+        .with_empty_response(204, "Deleted"),
+        Operation::new(
+            Post,
+            "/schedules/{id}/enable",
+            "enable_schedule",
+            "schedules",
+            "Enable a schedule",
+        )
+        .with_json_response::<Schedule>(),
+        Operation::new(
+            Post,
+            "/schedules/{id}/disable",
+            "disable_schedule",
+            "schedules",
+            "Disable a schedule",
+        )
+        .with_json_response::<Schedule>(),
+        Operation::new(
+            Post,
+            "/schedules/{id}/run",
+            "run_schedule",
+            "schedules",
+            "Run a schedule now",
+        )
+        .with_empty_response(202, "The run was started"),
+        Operation::new(
+            Get,
+            "/schedules/{id}/runs",
+            "schedule_runs",
+            "schedules",
+            "List the runs of a schedule",
+        )
+        .with_json_response::<Vec<ScheduleRun>>(),
+        Operation::new(
+            Get,
+            "/webhooks/deliveries",
+            "webhook_deliveries",
+            "webhooks",
+            "List recent webhook deliveries",
+        )
+        .with_description("Only available to admins.")
+        .with_json_response::<Vec<WebhookDelivery>>(),
+        Operation::new(Get, "/specs", "list_specs", "specs", "List job specs")
+            .with_json_response::<Vec<SpecSummary>>(),
+        Operation::new(Get, "/specs/{name}", "get_spec", "specs", "Get a job spec")
+            .with_json_response::<StoredSpec>(),
+        Operation::new(Post, "/specs", "upload_spec", "specs", "Upload a job spec")
+            .with_query("format", "`yaml` (the default), `toml` or `json`")
+            .with_body("text/plain", spec_source)
+            .with_json_response::<StoredSpec>(),
+        Operation::new(
+            Delete,
+            "/specs/{name}",
+            "delete_spec",
+            "specs",
+            "Delete a job spec",
+        )
+        .with_empty_response(204, "Deleted"),
+        Operation::new(
+            Post,
+            "/specs/{name}/run",
+            "run_spec",
+            "specs",
+            "Run a job spec",
+        )
+        .with_json_response::<CrawlResponse>(),
+        Operation::new(
+            Get,
+            "/credentials",
+            "list_credentials",
+            "credentials",
+            "List credentials",
+        )
+        .with_json_response::<Vec<Credential>>(),
+        Operation::new(
+            Put,
+            "/credentials/{name}",
+            "put_credential",
+            "credentials",
+            "Store an AI provider key",
+        )
+        .with_json_body::<CredentialRequest>()
+        .with_json_response::<Credential>(),
+        Operation::new(
+            Delete,
+            "/credentials/{name}",
+            "delete_credential",
+            "credentials",
+            "Delete a credential",
+        )
+        .with_empty_response(204, "Deleted"),
+    ]
+}
+
+/// The OpenAPI document of the REST API.
+pub fn openapi_document() -> Value {
+    openapi::document::<ApiError>("Scrapy API", "/api", &operations())
+}
+
+#[get("/openapi.json")]
+pub fn openapi_json() -> Json<Value> {
+    Json(openapi_document())
+}
+
+/// API reference rendered by Redoc from `openapi.json`.
+#[get("/docs")]
+pub fn api_docs() -> RawHtml<&'static str> {
+    RawHtml(
+        r#"<!DOCTYPE html>
+<html>
+  <head>
+    <title>Scrapy API</title>
+    <meta charset="utf-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1" />
+  </head>
+  <body>
+    <redoc spec-url="openapi.json"></redoc>
+    <script src="https://cdn.redoc.ly/redoc/v2.1.5/bundles/redoc.standalone.js"></script>
+  </body>
+</html>
+"#,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_every_mounted_route() {
+        let mut documented: Vec<(Method, String)> = operations()
+            .iter()
+            .map(|operation| (operation.method(), operation.path().to_string()))
+            .collect();
+        let mut mounted: Vec<(Method, String)> = crate::routes::api()
+            .iter()
+            .map(|route| {
+                let path = route.uri.path().replace('<', "{").replace('>', "}");
+                (route.method, path)
+            })
+            .collect();
+        documented.sort_by_key(|(method, path)| (path.clone(), method.as_str()));
+        mounted.sort_by_key(|(method, path)| (path.clone(), method.as_str()));
+        assert_eq!(documented, mounted);
+    }
+
+    #[test]
+    fn describes_request_and_response_models() {
+        let document = openapi_document();
+        let schemas = &document["components"]["schemas"];
+
+        let params = &schemas["ScrapeParams"];
+        assert_eq!(
+            params["required"],
+            json!(["enableScraping", "tags", "enablePagination"])
+        );
+        assert!(params["properties"]["paginationDetails"].is_object());
+        assert!(params["properties"].get("owner").is_none());
+        assert_eq!(
+            schemas["FetcherConfig"]["discriminator"]["propertyName"],
+            "backend"
+        );
+        assert_eq!(
+            schemas["PaginationInfo"]["properties"]["tokenCounts"]["$ref"],
+            "#/components/schemas/UsageMetadata"
+        );
+        assert!(schemas["ApiError"]["properties"]["requestId"].is_object());
+
+        let crawl = &document["paths"]["/crawl"]["post"];
+        assert_eq!(
+            crawl["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ScrapeParams"
+        );
+        assert_eq!(
+            document["paths"]["/openapi.json"]["get"]["security"],
+            json!([])
+        );
+    }
+
+    #[test]
+    fn client_types_are_up_to_date() {
+        // The web client is only there in a checkout of the whole repository.
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../scrapy-client/src/types/api.ts");
+        let Ok(client) = std::fs::read_to_string(&path) else {
+            return;
+        };
+        assert!(
+            client == openapi::typescript(&openapi_document()),
+            "{} is out of date; run `make api-types`",
+            path.display()
+        );
+    }
+}
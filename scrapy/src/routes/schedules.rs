@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use rocket::{delete, get, http::Status, post, put, serde::json::Json, State};
+use uuid::Uuid;
+
+use crate::{
+    auth::User,
+    error::AppError,
+    models::{Schedule, ScheduleRequest, ScheduleRun},
+    scheduler::Scheduler,
+};
+
+fn not_found(id: &str) -> AppError {
+    AppError::NotFound(format!("no schedule with id {:?}", id))
+}
+
+/// Resolves `id` to a schedule `user` may manage. Other users' schedules are
+/// reported as missing.
+async fn authorize(id: &str, user: &User, scheduler: &Scheduler) -> Result<Uuid, AppError> {
+    let uuid = Uuid::parse_str(id).map_err(|_| not_found(id))?;
+    match scheduler.get(uuid).await {
+        Some(schedule) if user.can_access(schedule.owner.as_deref()) => Ok(uuid),
+        _ => Err(not_found(id)),
+    }
+}
+
+#[get("/schedules")]
+pub async fn list_schedules(user: User, scheduler: &State<Arc<Scheduler>>) -> Json<Vec<Schedule>> {
+    let mut schedules = scheduler.list().await;
+    schedules.retain(|schedule| user.can_access(schedule.owner.as_deref()));
+    Json(schedules)
+}
+
+#[post("/schedules", data = "<request>")]
+pub async fn create_schedule(
+    request: Json<ScheduleRequest>,
+    user: User,
+    scheduler: &State<Arc<Scheduler>>,
+) -> Result<Json<Schedule>, AppError> {
+    scheduler
+        .create(request.into_inner(), user.name)
+        .await
+        .map(Json)
+}
+
+#[get("/schedules/<id>")]
+pub async fn get_schedule(
+    id: &str,
+    user: User,
+    scheduler: &State<Arc<Scheduler>>,
+) -> Result<Json<Schedule>, AppError> {
+    let uuid = authorize(id, &user, scheduler).await?;
+    scheduler
+        .get(uuid)
+        .await
+        .map(Json)
+        .ok_or_else(|| not_found(id))
+}
+
+#[put("/schedules/<id>", data = "<request>")]
+pub async fn update_schedule(
+    id: &str,
+    request: Json<ScheduleRequest>,
+    user: User,
+    scheduler: &State<Arc<Scheduler>>,
+) -> Result<Json<Schedule>, AppError> {
+    let uuid = authorize(id, &user, scheduler).await?;
+    scheduler
+        .update(uuid, request.into_inner())
+        .await?
+        .map(Json)
+        .ok_or_else(|| not_found(id))
+}
+
+#[delete("/schedules/<id>")]
+pub async fn delete_schedule(
+    id: &str,
+    user: User,
+    scheduler: &State<Arc<Scheduler>>,
+) -> Result<Status, AppError> {
+    let uuid = authorize(id, &user, scheduler).await?;
+    if scheduler.delete(uuid).await? {
+        Ok(Status::NoContent)
+    } else {
+        Err(not_found(id))
+    }
+}
+
+#[post("/schedules/<id>/enable")]
+pub async fn enable_schedule(
+    id: &str,
+    user: User,
+    scheduler: &State<Arc<Scheduler>>,
+) -> Result<Json<Schedule>, AppError> {
+    set_enabled(id, &user, true, scheduler).await
+}
+
+#[post("/schedules/<id>/disable")]
+pub async fn disable_schedule(
+    id: &str,
+    user: User,
+    scheduler: &State<Arc<Scheduler>>,
+) -> Result<Json<Schedule>, AppError> {
+    set_enabled(id, &user, false, scheduler).await
+}
+
+async fn set_enabled(
+    id: &str,
+    user: &User,
+    enabled: bool,
+    scheduler: &Scheduler,
+) -> Result<Json<Schedule>, AppError> {
+    let uuid = authorize(id, user, scheduler).await?;
+    scheduler
+        .set_enabled(uuid, enabled)
+        .await?
+        .map(Json)
+        .ok_or_else(|| not_found(id))
+}
+
+#[post("/schedules/<id>/run")]
+pub async fn run_schedule(
+    id: &str,
+    user: User,
+    scheduler: &State<Arc<Scheduler>>,
+) -> Result<Status, AppError> {
+    let uuid = authorize(id, &user, scheduler).await?;
+    if scheduler.inner().run_now(uuid).await? {
+        Ok(Status::Accepted)
+    } else {
+        Err(not_found(id))
+    }
+}
+
+#[get("/schedules/<id>/runs")]
+pub async fn schedule_runs(
+    id: &str,
+    user: User,
+    scheduler: &State<Arc<Scheduler>>,
+) -> Result<Json<Vec<ScheduleRun>>, AppError> {
+    let uuid = authorize(id, &user, scheduler).await?;
+    scheduler
+        .runs(uuid)
+        .await
+        .map(Json)
+        .ok_or_else(|| not_found(id))
+}
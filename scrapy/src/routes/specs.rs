@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use rocket::{delete, get, http::Status, post, serde::json::Json, State};
+
+use crate::{
+    auth::User,
+    error::AppError,
+    models::CrawlResponse,
+    services::CrawlerService,
+    specs::{SpecFormat, SpecStore, SpecSummary, StoredSpec},
+};
+
+use super::run_crawl;
+
+fn not_found(name: &str) -> AppError {
+    AppError::NotFound(format!("no job spec named {:?}", name))
+}
+
+#[get("/specs")]
+pub async fn list_specs(
+    user: User,
+    spec_store: &State<Arc<SpecStore>>,
+) -> Result<Json<Vec<SpecSummary>>, AppError> {
+    spec_store.list(user.name.as_deref()).await.map(Json)
+}
+
+#[get("/specs/<name>")]
+pub async fn get_spec(
+    name: &str,
+    user: User,
+    spec_store: &State<Arc<SpecStore>>,
+) -> Result<Json<StoredSpec>, AppError> {
+    spec_store
+        .get(user.name.as_deref(), name)
+        .await?
+        .map(Json)
+        .ok_or_else(|| not_found(name))
+}
+
+/// Uploads a spec file; `format` is `yaml` (the default), `toml` or `json`.
+#[post("/specs?<format>", data = "<source>")]
+pub async fn upload_spec(
+    format: Option<&str>,
+    source: String,
+    user: User,
+    spec_store: &State<Arc<SpecStore>>,
+) -> Result<Json<StoredSpec>, AppError> {
+    let format = format
+        .unwrap_or("yaml")
+        .parse::<SpecFormat>()
+        .map_err(AppError::BadRequest)?;
+    spec_store
+        .save(user.name.as_deref(), source, format)
+        .await
+        .map(Json)
+}
+
+#[delete("/specs/<name>")]
+pub async fn delete_spec(
+    name: &str,
+    user: User,
+    spec_store: &State<Arc<SpecStore>>,
+) -> Result<Status, AppError> {
+    if spec_store.remove(user.name.as_deref(), name).await? {
+        Ok(Status::NoContent)
+    } else {
+        Err(not_found(name))
+    }
+}
+
+/// Runs a saved spec with its stored credential or the configured `ai.api_key`.
+/// Records are returned in the response; the spec's `outputs` are only
+/// written by the CLI.
+#[post("/specs/<name>/run")]
+pub async fn run_spec(
+    name: &str,
+    user: User,
+    spec_store: &State<Arc<SpecStore>>,
+    crawler_service: &State<Arc<CrawlerService>>,
+) -> Result<Json<CrawlResponse>, AppError> {
+    let stored = spec_store
+        .get(user.name.as_deref(), name)
+        .await?
+        .ok_or_else(|| not_found(name))?;
+    if !stored.spec.outputs.is_empty() {
+        log::info!(
+            "Not writing the outputs of spec {:?}; API runs return records in the response",
+            name
+        );
+    }
+    run_crawl(stored.spec.to_params(), &user, crawler_service).await
+}
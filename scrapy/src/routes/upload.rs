@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use rocket::{
+    form::Form, fs::TempFile, post, serde::json::Json, tokio::io::AsyncReadExt, FromForm, State,
+};
+
+use crate::{
+    auth::User,
+    error::AppError,
+    models::{CrawlResponse, ScrapeParams},
+    services::CrawlerService,
+    utils::parse_url_list,
+};
+
+use super::run_crawl;
+
+#[derive(FromForm)]
+pub struct CrawlUpload<'r> {
+    params: Json<ScrapeParams>,
+    file: TempFile<'r>,
+}
+
+#[post("/crawl/upload", data = "<upload>")]
+pub async fn crawl_upload(
+    upload: Form<CrawlUpload<'_>>,
+    user: User,
+    crawler_service: &State<Arc<CrawlerService>>,
+) -> Result<Json<CrawlResponse>, AppError> {
+    let upload = upload.into_inner();
+
+    let mut content = String::new();
+    let mut file = upload.file.open().await?;
+    file.read_to_string(&mut content)
+        .await
+        .map_err(|err| AppError::BadRequest(format!("the URL list is not UTF-8 text: {}", err)))?;
+
+    let urls = parse_url_list(&content)?;
+    log::info!("Parsed {} URLs from uploaded list", urls.len());
+
+    let mut params = upload.params.into_inner();
+    params.urls.extend(urls);
+    run_crawl(params, &user, crawler_service).await
+}
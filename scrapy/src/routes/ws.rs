@@ -3,20 +3,31 @@ use rocket::{get, State};
 use std::sync::Arc;
 use ws::Message;
 
-use crate::services::WebSocketService;
+use crate::{
+    auth::User,
+    metrics::{ConnectedClient, Metrics},
+    services::WebSocketService,
+};
 
+/// Browsers cannot set headers on WebSocket requests, so the API key may be
+/// passed as `?access_token=`.
 #[get("/ws")]
 pub fn websocket(
     ws: ws::WebSocket,
+    user: User,
     websocket_service: &State<Arc<WebSocketService>>,
+    metrics: &State<Arc<Metrics>>,
 ) -> ws::Channel<'static> {
     let service = websocket_service.inner().clone();
-    ws.channel(move |stream| Box::pin(handle_websocket(stream, service)))
+    let client = metrics.connect_client("websocket");
+    ws.channel(move |stream| Box::pin(handle_websocket(stream, service, user, client)))
 }
 
 async fn handle_websocket(
     mut stream: ws::stream::DuplexStream,
     websocket_service: Arc<WebSocketService>,
+    user: User,
+    _client: ConnectedClient,
 ) -> Result<(), ws::result::Error> {
     let mut rx = websocket_service.subscribe().await;
 
@@ -61,6 +72,9 @@ async fn handle_websocket(
             }
             msg = rx.recv() => {
                 if let Ok(websocket_message) = msg {
+                    if !user.can_access(websocket_message.owner.as_deref()) {
+                        continue;
+                    }
                     let json = serde_json::to_string(&websocket_message).unwrap();
                     if let Err(e) = stream.send(Message::Text(json)).await {
                         log::error!("Failed to send WebSocket message: {}", e);
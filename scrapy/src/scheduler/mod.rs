@@ -0,0 +1,646 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::{
+        OverlapPolicy, RunTrigger, Schedule, ScheduleRequest, ScheduleRun, ScheduleRunStatus,
+        ScheduleTrigger, ScrapeParams,
+    },
+    services::CrawlerService,
+};
+
+use store::{Entry, ScheduleStore};
+
+mod store;
+
+const TICK: Duration = Duration::from_secs(1);
+const MIN_INTERVAL_SECS: u64 = 60;
+
+/// Runs crawl schedules in the background. At most one run per schedule is
+/// active at a time; what happens to a run that fires while the previous one
+/// is still going is decided by the schedule's overlap policy.
+pub struct Scheduler {
+    store: ScheduleStore,
+    crawler_service: Arc<CrawlerService>,
+    /// Schedules with an active run, mapped to whether another run is queued.
+    running: StdMutex<HashMap<Uuid, bool>>,
+}
+
+impl Scheduler {
+    pub fn open(
+        path: impl Into<PathBuf>,
+        crawler_service: Arc<CrawlerService>,
+    ) -> Result<Self, AppError> {
+        Ok(Self {
+            store: ScheduleStore::open(path)?,
+            crawler_service,
+            running: StdMutex::new(HashMap::new()),
+        })
+    }
+
+    /// Starts the loop that fires due schedules. Must be called from within
+    /// the Tokio runtime.
+    pub fn spawn(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(TICK);
+            loop {
+                ticks.tick().await;
+                if let Err(err) = self.fire_due(Utc::now()).await {
+                    log::error!("Failed to fire due schedules: {}", err);
+                }
+            }
+        });
+    }
+
+    pub async fn list(&self) -> Vec<Schedule> {
+        self.store
+            .read(|entries| entries.iter().map(|entry| self.view(entry)).collect())
+            .await
+    }
+
+    pub async fn get(&self, id: Uuid) -> Option<Schedule> {
+        self.store
+            .read(|entries| find(entries, id).map(|entry| self.view(entry)))
+            .await
+    }
+
+    pub async fn runs(&self, id: Uuid) -> Option<Vec<ScheduleRun>> {
+        self.store
+            .read(|entries| find(entries, id).map(|entry| entry.history.clone()))
+            .await
+    }
+
+    pub async fn create(
+        &self,
+        request: ScheduleRequest,
+        owner: Option<String>,
+    ) -> Result<Schedule, AppError> {
+        validate(&request)?;
+        let now = Utc::now();
+        let schedule = Schedule {
+            id: Uuid::new_v4(),
+            next_run_at: next_run(&request.trigger, request.enabled, now)?,
+            name: request.name,
+            trigger: request.trigger,
+            params: without_api_key(request.params),
+            enabled: request.enabled,
+            overlap: request.overlap,
+            created_at: now,
+            updated_at: now,
+            running: false,
+            owner,
+        };
+
+        self.store
+            .update(|entries| {
+                entries.push(Entry {
+                    schedule: schedule.clone(),
+                    history: Vec::new(),
+                })
+            })
+            .await?;
+        log::info!("Created schedule {} ({})", schedule.id, schedule.name);
+        Ok(schedule)
+    }
+
+    pub async fn update(
+        &self,
+        id: Uuid,
+        request: ScheduleRequest,
+    ) -> Result<Option<Schedule>, AppError> {
+        validate(&request)?;
+        let now = Utc::now();
+        let next_run_at = next_run(&request.trigger, request.enabled, now)?;
+
+        self.store
+            .update(|entries| {
+                let entry = find_mut(entries, id)?;
+                let schedule = &mut entry.schedule;
+                schedule.name = request.name;
+                schedule.trigger = request.trigger;
+                schedule.params = without_api_key(request.params);
+                schedule.enabled = request.enabled;
+                schedule.overlap = request.overlap;
+                schedule.updated_at = now;
+                schedule.next_run_at = next_run_at;
+                Some(self.view(entry))
+            })
+            .await
+    }
+
+    pub async fn set_enabled(&self, id: Uuid, enabled: bool) -> Result<Option<Schedule>, AppError> {
+        let now = Utc::now();
+        self.store
+            .update(|entries| {
+                let entry = find_mut(entries, id)?;
+                let schedule = &mut entry.schedule;
+                schedule.enabled = enabled;
+                schedule.updated_at = now;
+                schedule.next_run_at = next_run(&schedule.trigger, enabled, now).ok().flatten();
+                Some(self.view(entry))
+            })
+            .await
+    }
+
+    /// Removes the schedule and its history. An active run is left to finish
+    /// but its outcome is not recorded.
+    pub async fn delete(&self, id: Uuid) -> Result<bool, AppError> {
+        self.store
+            .update(|entries| {
+                let before = entries.len();
+                entries.retain(|entry| entry.schedule.id != id);
+                entries.len() != before
+            })
+            .await
+    }
+
+    /// Starts a run outside of the schedule, subject to its overlap policy.
+    pub async fn run_now(self: &Arc<Self>, id: Uuid) -> Result<bool, AppError> {
+        let Some(schedule) = self.get(id).await else {
+            return Ok(false);
+        };
+        self.start(schedule.id, schedule.overlap, RunTrigger::Manual)
+            .await?;
+        Ok(true)
+    }
+
+    async fn fire_due(self: &Arc<Self>, now: DateTime<Utc>) -> Result<(), AppError> {
+        let due = self
+            .store
+            .read(|entries| {
+                entries
+                    .iter()
+                    .map(|entry| &entry.schedule)
+                    .any(|schedule| is_due(schedule, now))
+            })
+            .await;
+        if !due {
+            return Ok(());
+        }
+
+        // Runs missed while the server was down are coalesced into one, and
+        // the next run is planned from now rather than from the missed time.
+        let due = self
+            .store
+            .update(|entries| {
+                let mut due = Vec::new();
+                for entry in entries.iter_mut() {
+                    let schedule = &mut entry.schedule;
+                    if is_due(schedule, now) {
+                        schedule.next_run_at =
+                            next_run(&schedule.trigger, true, now).ok().flatten();
+                        due.push((schedule.id, schedule.overlap));
+                    }
+                }
+                due
+            })
+            .await?;
+
+        for (id, overlap) in due {
+            self.start(id, overlap, RunTrigger::Scheduled).await?;
+        }
+        Ok(())
+    }
+
+    async fn start(
+        self: &Arc<Self>,
+        id: Uuid,
+        overlap: OverlapPolicy,
+        trigger: RunTrigger,
+    ) -> Result<(), AppError> {
+        let busy = {
+            let mut running = self.running.lock().unwrap_or_else(|err| err.into_inner());
+            match running.get_mut(&id) {
+                Some(queued) => {
+                    *queued |= overlap == OverlapPolicy::Queue;
+                    true
+                }
+                None => {
+                    running.insert(id, false);
+                    false
+                }
+            }
+        };
+
+        if !busy {
+            tokio::spawn(self.clone().execute(id, trigger));
+            return Ok(());
+        }
+
+        match overlap {
+            OverlapPolicy::Queue => {
+                log::info!("Queued run of schedule {} behind the active run", id)
+            }
+            OverlapPolicy::Skip => {
+                log::info!("Skipping run of schedule {}: previous run still active", id);
+                let now = Utc::now();
+                let run = ScheduleRun {
+                    id: Uuid::new_v4(),
+                    schedule_id: id,
+                    trigger,
+                    status: ScheduleRunStatus::Skipped,
+                    started_at: now,
+                    finished_at: Some(now),
+                    pages: 0,
+                    results: 0,
+                    error: None,
+                };
+                self.record(run).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn execute(self: Arc<Self>, id: Uuid, mut trigger: RunTrigger) {
+        loop {
+            if let Err(err) = self.run_once(id, trigger).await {
+                log::error!("Failed to record run of schedule {}: {}", id, err);
+            }
+
+            let mut running = self.running.lock().unwrap_or_else(|err| err.into_inner());
+            match running.get_mut(&id) {
+                Some(queued) if *queued => *queued = false,
+                _ => {
+                    running.remove(&id);
+                    return;
+                }
+            }
+            trigger = RunTrigger::Scheduled;
+        }
+    }
+
+    async fn run_once(&self, id: Uuid, trigger: RunTrigger) -> Result<(), AppError> {
+        let Some((params, owner)) = self
+            .store
+            .read(|entries| {
+                find(entries, id).map(|entry| {
+                    let schedule = &entry.schedule;
+                    (schedule.params.clone(), schedule.owner.clone())
+                })
+            })
+            .await
+        else {
+            return Ok(());
+        };
+
+        let mut run = ScheduleRun {
+            id: Uuid::new_v4(),
+            schedule_id: id,
+            trigger,
+            status: ScheduleRunStatus::Running,
+            started_at: Utc::now(),
+            finished_at: None,
+            pages: 0,
+            results: 0,
+            error: None,
+        };
+        self.record(run.clone()).await?;
+        log::info!("Starting run {} of schedule {}", run.id, id);
+
+        let outcome = match serde_json::from_value::<ScrapeParams>(params) {
+            Ok(params) => {
+                let params = ScrapeParams { owner, ..params };
+                self.crawler_service.crawl(params).await
+            }
+            Err(err) => Err(err.into()),
+        };
+        match outcome {
+            Ok((results, report)) => {
+                run.status = ScheduleRunStatus::Succeeded;
+                run.pages = report.pages.len();
+                run.results = results.len();
+            }
+            Err(err) => {
+                log::error!("Run {} of schedule {} failed: {}", run.id, id, err);
+                run.status = ScheduleRunStatus::Failed;
+                run.error = Some(err.to_string());
+            }
+        }
+        run.finished_at = Some(Utc::now());
+        self.record(run).await
+    }
+
+    async fn record(&self, run: ScheduleRun) -> Result<(), AppError> {
+        self.store
+            .update(|entries| {
+                if let Some(entry) = find_mut(entries, run.schedule_id) {
+                    entry.record(run);
+                }
+            })
+            .await
+    }
+
+    fn view(&self, entry: &Entry) -> Schedule {
+        let running = self.running.lock().unwrap_or_else(|err| err.into_inner());
+        Schedule {
+            running: running.contains_key(&entry.schedule.id),
+            params: without_api_key(entry.schedule.params.clone()),
+            ..entry.schedule.clone()
+        }
+    }
+}
+
+fn find(entries: &[Entry], id: Uuid) -> Option<&Entry> {
+    entries.iter().find(|entry| entry.schedule.id == id)
+}
+
+fn find_mut(entries: &mut [Entry], id: Uuid) -> Option<&mut Entry> {
+    entries.iter_mut().find(|entry| entry.schedule.id == id)
+}
+
+fn is_due(schedule: &Schedule, now: DateTime<Utc>) -> bool {
+    schedule.enabled && schedule.next_run_at.is_some_and(|next| next <= now)
+}
+
+fn validate(request: &ScheduleRequest) -> Result<(), AppError> {
+    if request.name.trim().is_empty() {
+        return Err(AppError::Schedule("Name must not be empty".to_string()));
+    }
+
+    let params: ScrapeParams = serde_json::from_value(request.params.clone())
+        .map_err(|err| AppError::Schedule(format!("Invalid crawl parameters: {}", err)))?;
+    if !params.api_key.is_empty() {
+        return Err(AppError::Schedule(
+            "Schedules cannot store an apiKey; store it with PUT /api/credentials/{name} and set \"credential\"".to_string(),
+        ));
+    }
+    params.validate()
+}
+
+/// Drops `apiKey` from stored crawl parameters, so keys saved by older
+/// versions are neither returned nor written back.
+fn without_api_key(mut params: serde_json::Value) -> serde_json::Value {
+    if let Some(params) = params.as_object_mut() {
+        params.remove("apiKey");
+    }
+    params
+}
+
+/// Next time `trigger` fires after `after`, or `None` for disabled schedules
+/// and cron expressions that never match again.
+fn next_run(
+    trigger: &ScheduleTrigger,
+    enabled: bool,
+    after: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, AppError> {
+    let next = match trigger {
+        ScheduleTrigger::Cron { expression } => cron_schedule(expression)?.after(&after).next(),
+        ScheduleTrigger::Interval { every_secs } if *every_secs < MIN_INTERVAL_SECS => {
+            return Err(AppError::Schedule(format!(
+                "Interval must be at least {} seconds",
+                MIN_INTERVAL_SECS
+            )))
+        }
+        ScheduleTrigger::Interval { every_secs } => {
+            Some(after + chrono::Duration::seconds(*every_secs as i64))
+        }
+    };
+    Ok(next.filter(|_| enabled))
+}
+
+/// Parses a cron expression, accepting the common five-field form by
+/// prepending a seconds field.
+fn cron_schedule(expression: &str) -> Result<cron::Schedule, AppError> {
+    let expression = expression.trim();
+    let expression = match expression.split_whitespace().count() {
+        5 => format!("0 {}", expression),
+        _ => expression.to_string(),
+    };
+    cron::Schedule::from_str(&expression)
+        .map_err(|err| AppError::Schedule(format!("Invalid cron expression: {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{
+        jobs::JobStore,
+        models::{FetchConfig, RateLimitConfig, RetryConfig},
+        services::{AIService, GeminiAIProvider, WebSocketService, WebhookService},
+        Crawler,
+    };
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("scrapy-schedules-{}", Uuid::new_v4()))
+    }
+
+    /// A scheduler whose runs fail right away: the crawler service has no
+    /// vault to resolve the schedules' credential from.
+    fn scheduler(dir: &std::path::Path) -> Arc<Scheduler> {
+        let crawler_service = CrawlerService::new(
+            Crawler::new(RateLimitConfig::default(), RetryConfig::default(), 1, 1),
+            Arc::new(WebSocketService::new(16)),
+            Arc::new(AIService::new(GeminiAIProvider::new())),
+            FetchConfig::default(),
+            None,
+            Arc::new(JobStore::new(dir.join("jobs"))),
+            Arc::new(WebhookService::open(dir.join("webhooks.json"), Vec::new()).unwrap()),
+        );
+        Arc::new(Scheduler::open(dir.join("schedules.json"), Arc::new(crawler_service)).unwrap())
+    }
+
+    fn schedule_request(overlap: OverlapPolicy) -> ScheduleRequest {
+        ScheduleRequest {
+            name: "nightly".to_string(),
+            trigger: ScheduleTrigger::Interval { every_secs: 3600 },
+            params: serde_json::json!({
+                "model": "gemini-1.5-flash-latest",
+                "credential": "gemini",
+                "url": "https://example.com",
+                "enableScraping": true,
+                "tags": ["name"],
+                "enablePagination": false,
+            }),
+            enabled: true,
+            overlap,
+        }
+    }
+
+    fn history(runs: &[ScheduleRun]) -> Vec<(RunTrigger, ScheduleRunStatus)> {
+        runs.iter().map(|run| (run.trigger, run.status)).collect()
+    }
+
+    async fn wait_until_idle(scheduler: &Scheduler, id: Uuid) {
+        while scheduler.get(id).await.unwrap().running {
+            tokio::time::sleep(TICK).await;
+        }
+    }
+
+    #[test]
+    fn computes_next_cron_and_interval_runs() {
+        let now = Utc.with_ymd_and_hms(2024, 10, 7, 8, 30, 0).unwrap();
+        let cron = ScheduleTrigger::Cron {
+            expression: "0 9 * * Mon-Fri".to_string(),
+        };
+        let interval = ScheduleTrigger::Interval { every_secs: 3600 };
+
+        assert_eq!(
+            next_run(&cron, true, now).unwrap(),
+            Some(Utc.with_ymd_and_hms(2024, 10, 7, 9, 0, 0).unwrap())
+        );
+        assert_eq!(
+            next_run(&interval, true, now).unwrap(),
+            Some(Utc.with_ymd_and_hms(2024, 10, 7, 9, 30, 0).unwrap())
+        );
+        assert_eq!(next_run(&interval, false, now).unwrap(), None);
+    }
+
+    #[test]
+    fn keeps_api_keys_out_of_schedules() {
+        let request = |key: serde_json::Value| ScheduleRequest {
+            name: "nightly".to_string(),
+            trigger: ScheduleTrigger::Interval { every_secs: 3600 },
+            params: serde_json::json!({
+                "model": "gemini-1.5-flash-latest",
+                "apiKey": key,
+                "credential": "gemini",
+                "url": "https://example.com",
+                "enableScraping": true,
+                "tags": ["name"],
+                "enablePagination": false,
+            }),
+            enabled: true,
+            overlap: Default::default(),
+        };
+
+        let with_key = request("AIza-secret".into());
+        assert!(matches!(validate(&with_key), Err(AppError::Schedule(_))));
+
+        let with_credential = request("".into());
+        validate(&with_credential).unwrap();
+        let stored = without_api_key(with_credential.params);
+        assert_eq!(stored.get("apiKey"), None);
+        assert_eq!(stored["credential"], "gemini");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn skips_runs_that_fire_during_an_active_run() {
+        let scheduler = scheduler(&temp_dir());
+        let id = scheduler
+            .create(schedule_request(OverlapPolicy::Skip), None)
+            .await
+            .unwrap()
+            .id;
+
+        // The first run is still going when the next one fires, and when a
+        // manual run is requested.
+        scheduler.running.lock().unwrap().insert(id, false);
+        let later = Utc::now() + chrono::Duration::hours(2);
+        scheduler.fire_due(later).await.unwrap();
+        assert!(scheduler.run_now(id).await.unwrap());
+        assert!(scheduler.get(id).await.unwrap().running);
+        assert_eq!(
+            history(&scheduler.runs(id).await.unwrap()),
+            [
+                (RunTrigger::Scheduled, ScheduleRunStatus::Skipped),
+                (RunTrigger::Manual, ScheduleRunStatus::Skipped),
+            ]
+        );
+        assert!(scheduler.get(id).await.unwrap().next_run_at.unwrap() > later);
+
+        scheduler.running.lock().unwrap().remove(&id);
+        assert!(scheduler.run_now(id).await.unwrap());
+        wait_until_idle(&scheduler, id).await;
+        let runs = scheduler.runs(id).await.unwrap();
+        assert_eq!(
+            history(&runs[2..]),
+            [(RunTrigger::Manual, ScheduleRunStatus::Failed)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn queues_one_run_behind_the_active_run() {
+        let scheduler = scheduler(&temp_dir());
+        let id = scheduler
+            .create(schedule_request(OverlapPolicy::Queue), None)
+            .await
+            .unwrap()
+            .id;
+
+        // A scheduled run starts, and both a manual run and the next
+        // scheduled run arrive while it is still going: they share one slot
+        // in the queue.
+        scheduler.running.lock().unwrap().insert(id, false);
+        assert!(scheduler.run_now(id).await.unwrap());
+        scheduler
+            .fire_due(Utc::now() + chrono::Duration::hours(2))
+            .await
+            .unwrap();
+        assert_eq!(scheduler.running.lock().unwrap().get(&id), Some(&true));
+        assert!(scheduler.runs(id).await.unwrap().is_empty());
+
+        scheduler.clone().execute(id, RunTrigger::Scheduled).await;
+        assert!(scheduler.running.lock().unwrap().is_empty());
+        assert_eq!(
+            history(&scheduler.runs(id).await.unwrap()),
+            [
+                (RunTrigger::Scheduled, ScheduleRunStatus::Failed),
+                (RunTrigger::Scheduled, ScheduleRunStatus::Failed),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn keeps_a_capped_run_history() {
+        let dir = temp_dir();
+        let scheduler = scheduler(&dir);
+        let id = scheduler
+            .create(schedule_request(OverlapPolicy::Skip), None)
+            .await
+            .unwrap()
+            .id;
+
+        scheduler.run_now(id).await.unwrap();
+        wait_until_idle(&scheduler, id).await;
+        let run = scheduler.runs(id).await.unwrap().remove(0);
+        assert_eq!(run.status, ScheduleRunStatus::Failed);
+        assert!(run.error.as_ref().unwrap().contains("credential"));
+        assert!(run.finished_at.is_some());
+
+        let mut ids = Vec::new();
+        for _ in 0..store::MAX_HISTORY + 4 {
+            let skipped = ScheduleRun {
+                id: Uuid::new_v4(),
+                status: ScheduleRunStatus::Skipped,
+                ..run.clone()
+            };
+            ids.push(skipped.id);
+            scheduler.record(skipped).await.unwrap();
+        }
+
+        // The oldest runs are dropped, and the history survives a restart.
+        let reopened = self::scheduler(&dir);
+        let runs = reopened.runs(id).await.unwrap();
+        assert_eq!(runs.len(), store::MAX_HISTORY);
+        assert_eq!(runs[0].id, ids[4]);
+        assert_eq!(runs.last().unwrap().id, *ids.last().unwrap());
+    }
+
+    #[test]
+    fn rejects_invalid_triggers() {
+        let now = Utc::now();
+        let invalid = [
+            ScheduleTrigger::Cron {
+                expression: "every day".to_string(),
+            },
+            ScheduleTrigger::Interval { every_secs: 5 },
+        ];
+        for trigger in invalid {
+            assert!(matches!(
+                next_run(&trigger, true, now),
+                Err(AppError::Schedule(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tokio::{fs, sync::Mutex};
+
+use crate::{
+    error::AppError,
+    models::{Schedule, ScheduleRun, ScheduleRunStatus},
+};
+
+/// Number of runs kept per schedule; older runs are dropped first.
+pub(super) const MAX_HISTORY: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub schedule: Schedule,
+    pub history: Vec<ScheduleRun>,
+}
+
+impl Entry {
+    pub fn record(&mut self, run: ScheduleRun) {
+        match self
+            .history
+            .iter_mut()
+            .find(|existing| existing.id == run.id)
+        {
+            Some(existing) => *existing = run,
+            None => self.history.push(run),
+        }
+        let overflow = self.history.len().saturating_sub(MAX_HISTORY);
+        self.history.drain(..overflow);
+    }
+}
+
+/// Schedules and their run history, persisted as a single JSON file that is
+/// rewritten after every change.
+pub struct ScheduleStore {
+    path: PathBuf,
+    entries: Mutex<Vec<Entry>>,
+}
+
+impl ScheduleStore {
+    /// Loads the store from `path`. Runs that were still marked as running
+    /// were interrupted by a restart and are recorded as failed.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, AppError> {
+        let path = path.into();
+        let mut entries: Vec<Entry> = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        for run in entries.iter_mut().flat_map(|entry| &mut entry.history) {
+            if run.status == ScheduleRunStatus::Running {
+                run.status = ScheduleRunStatus::Failed;
+                run.error = Some("Interrupted by a server restart".to_string());
+            }
+        }
+
+        Ok(Self {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Applies `update` to the entries and persists the result.
+    pub async fn update<T>(
+        &self,
+        update: impl FnOnce(&mut Vec<Entry>) -> T,
+    ) -> Result<T, AppError> {
+        let mut entries = self.entries.lock().await;
+        let value = update(&mut entries);
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, serde_json::to_vec_pretty(&*entries)?).await?;
+        fs::rename(&temp, &self.path).await?;
+        Ok(value)
+    }
+
+    pub async fn read<T>(&self, read: impl FnOnce(&[Entry]) -> T) -> T {
+        read(&self.entries.lock().await)
+    }
+}
@@ -0,0 +1,427 @@
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{fs, sync::Mutex};
+use uuid::Uuid;
+
+use crate::{
+    auth::{Authenticator, Quota, User},
+    error::AppError,
+    models::{AiScrapingResult, CrawlReport, JobRecord, JobStatus, ScrapeParams, Usage},
+};
+
+/// Number of job records kept; older ones are dropped first. Monthly spend is
+/// tracked separately and is not affected.
+const MAX_JOBS: usize = 10_000;
+/// Number of equal shares the remaining monthly budget is split into for jobs
+/// without a `maxCostUsd`, when the user has no concurrent job limit.
+const DEFAULT_BUDGET_SHARES: usize = 4;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Ledger {
+    jobs: Vec<JobRecord>,
+    /// AI spend per user and month; `None` owners are not tracked.
+    spend: BTreeMap<String, BTreeMap<String, f64>>,
+    /// Budget set aside for running jobs, by job and owner. Not persisted:
+    /// running jobs do not survive a restart.
+    #[serde(skip)]
+    reserved: BTreeMap<Uuid, (String, f64)>,
+}
+
+impl Ledger {
+    fn spent(&self, owner: &str, month: &str) -> f64 {
+        self.spend
+            .get(owner)
+            .and_then(|months| months.get(month))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    fn reserved(&self, owner: &str) -> f64 {
+        self.reserved
+            .values()
+            .filter(|(reserved_by, _)| reserved_by == owner)
+            .map(|(_, amount)| amount)
+            .sum()
+    }
+
+    fn running(&self, owner: Option<&str>) -> usize {
+        self.jobs
+            .iter()
+            .filter(|job| job.status == JobStatus::Running && job.owner.as_deref() == owner)
+            .count()
+    }
+}
+
+/// A started job, to be handed back to [`AccountService::finish_job`].
+#[derive(Debug)]
+pub struct JobTicket {
+    pub id: Uuid,
+    /// What the job may spend: its own budget, capped by what is left of the
+    /// owner's monthly budget once other running jobs' shares are set aside.
+    pub remaining_budget_usd: Option<f64>,
+}
+
+/// Records who ran which crawl and enforces per-user quotas on concurrent
+/// jobs and monthly AI spend. Persisted as a single JSON file.
+pub struct AccountService {
+    path: PathBuf,
+    authenticator: Arc<Authenticator>,
+    ledger: Mutex<Ledger>,
+}
+
+fn month(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m").to_string()
+}
+
+impl AccountService {
+    /// Loads the ledger from `path`. Jobs still marked as running were
+    /// interrupted by a restart and are recorded as failed.
+    pub fn open(
+        path: impl Into<PathBuf>,
+        authenticator: Arc<Authenticator>,
+    ) -> Result<Self, AppError> {
+        let path = path.into();
+        let mut ledger: Ledger = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ledger::default(),
+            Err(err) => return Err(err.into()),
+        };
+
+        for job in &mut ledger.jobs {
+            if job.status == JobStatus::Running {
+                job.status = JobStatus::Failed;
+                job.error = Some("Interrupted by a server restart".to_string());
+            }
+        }
+
+        Ok(Self {
+            path,
+            authenticator,
+            ledger: Mutex::new(ledger),
+        })
+    }
+
+    pub fn authenticator(&self) -> &Arc<Authenticator> {
+        &self.authenticator
+    }
+
+    /// Records a job for `params.owner`, or fails if that would exceed their
+    /// quota. The job's share of the monthly budget is reserved until
+    /// [`AccountService::finish_job`], so concurrent jobs cannot overspend.
+    /// Jobs without a `maxCostUsd` get an equal share of what is left among
+    /// the job slots still free, so one job cannot starve the others.
+    pub async fn start_job(&self, params: &ScrapeParams) -> Result<JobTicket, AppError> {
+        let owner = params.owner.as_deref();
+        let quota = owner
+            .and_then(|owner| self.authenticator.quota(owner))
+            .unwrap_or_default();
+        let now = Utc::now();
+
+        let mut ledger = self.ledger.lock().await;
+        if let Some(max) = quota.max_concurrent_jobs {
+            if ledger.running(owner) >= max {
+                return Err(AppError::Quota(format!(
+                    "{} concurrent job(s) allowed",
+                    max
+                )));
+            }
+        }
+        let remaining_budget_usd = match (owner, quota.monthly_budget_usd) {
+            (Some(owner), Some(budget)) => {
+                let remaining = budget - ledger.spent(owner, &month(now)) - ledger.reserved(owner);
+                if remaining <= 0.0 {
+                    return Err(AppError::Budget(format!(
+                        "monthly AI budget of ${:.2} used up or reserved by running jobs",
+                        budget
+                    )));
+                }
+                let slots = quota
+                    .max_concurrent_jobs
+                    .unwrap_or(DEFAULT_BUDGET_SHARES)
+                    .saturating_sub(ledger.running(Some(owner)))
+                    .max(1);
+                let share = remaining / slots as f64;
+                Some(
+                    params
+                        .budget
+                        .max_cost_usd
+                        .map_or(share, |max| max.min(remaining)),
+                )
+            }
+            _ => None,
+        };
+
+        let id = Uuid::new_v4();
+        if let (Some(owner), Some(reserved)) = (owner, remaining_budget_usd) {
+            ledger.reserved.insert(id, (owner.to_string(), reserved));
+        }
+        ledger.jobs.push(JobRecord {
+            id,
+            owner: params.owner.clone(),
+            job_id: params.job_id.clone(),
+            status: JobStatus::Running,
+            started_at: now,
+            finished_at: None,
+            start_urls: params.start_urls().len(),
+            pages: 0,
+            results: 0,
+            tokens: 0,
+            cost_usd: 0.0,
+            error: None,
+        });
+        let overflow = ledger.jobs.len().saturating_sub(MAX_JOBS);
+        ledger.jobs.drain(..overflow);
+        self.persist(&ledger).await?;
+
+        Ok(JobTicket {
+            id,
+            remaining_budget_usd,
+        })
+    }
+
+    /// Records the outcome of a job and charges its AI cost to the owner.
+    pub async fn finish_job(
+        &self,
+        ticket: JobTicket,
+        outcome: &Result<(Vec<AiScrapingResult>, CrawlReport), AppError>,
+    ) {
+        let now = Utc::now();
+        let mut ledger = self.ledger.lock().await;
+        ledger.reserved.remove(&ticket.id);
+        let Some(job) = ledger.jobs.iter_mut().find(|job| job.id == ticket.id) else {
+            return;
+        };
+
+        job.finished_at = Some(now);
+        match outcome {
+            Ok((results, report)) => {
+                job.status = JobStatus::Succeeded;
+                job.pages = report.pages.len();
+                job.results = results.len();
+                job.tokens = report.budget.tokens;
+                job.cost_usd = report.budget.cost_usd;
+            }
+            Err(err) => {
+                job.status = JobStatus::Failed;
+                job.error = Some(err.to_string());
+            }
+        }
+
+        let (owner, month, cost) = (job.owner.clone(), month(job.started_at), job.cost_usd);
+        if let Some(owner) = owner {
+            *ledger
+                .spend
+                .entry(owner)
+                .or_default()
+                .entry(month)
+                .or_default() += cost;
+        }
+        if let Err(err) = self.persist(&ledger).await {
+            log::error!("Failed to record job {}: {}", ticket.id, err);
+        }
+    }
+
+    /// Jobs visible to `user`, most recent first.
+    pub async fn jobs(&self, user: &User) -> Vec<JobRecord> {
+        let ledger = self.ledger.lock().await;
+        ledger
+            .jobs
+            .iter()
+            .rev()
+            .filter(|job| user.can_access(job.owner.as_deref()))
+            .cloned()
+            .collect()
+    }
+
+    pub async fn job(&self, user: &User, id: Uuid) -> Option<JobRecord> {
+        let ledger = self.ledger.lock().await;
+        ledger
+            .jobs
+            .iter()
+            .find(|job| job.id == id && user.can_access(job.owner.as_deref()))
+            .cloned()
+    }
+
+    pub async fn usage(&self, user: &User) -> Usage {
+        let Quota {
+            max_concurrent_jobs,
+            monthly_budget_usd,
+        } = user.quota;
+        let month = month(Utc::now());
+        let ledger = self.ledger.lock().await;
+        Usage {
+            user: user.name.clone(),
+            spent_usd: user
+                .name
+                .as_deref()
+                .map_or(0.0, |name| ledger.spent(name, &month)),
+            month,
+            monthly_budget_usd,
+            running_jobs: ledger.running(user.name.as_deref()),
+            max_concurrent_jobs,
+        }
+    }
+
+    async fn persist(&self, ledger: &Ledger) -> Result<(), AppError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, serde_json::to_vec_pretty(ledger)?).await?;
+        fs::rename(&temp, &self.path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{AuthSettings, UserSettings},
+        vault::Secret,
+    };
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("scrapy-accounts-{}", Uuid::new_v4()))
+    }
+
+    fn service(dir: &std::path::Path) -> AccountService {
+        let settings = AuthSettings {
+            enabled: true,
+            users: vec![
+                UserSettings {
+                    name: "alice".to_string(),
+                    api_key: Secret::new("alice-secret-key-0001"),
+                    admin: false,
+                    max_concurrent_jobs: Some(1),
+                    monthly_budget_usd: Some(1.0),
+                },
+                UserSettings {
+                    name: "dave".to_string(),
+                    api_key: Secret::new("dave-secret-key-00001"),
+                    admin: false,
+                    max_concurrent_jobs: Some(2),
+                    monthly_budget_usd: Some(1.0),
+                },
+                UserSettings {
+                    name: "carol".to_string(),
+                    api_key: Secret::new("carol-secret-key-0001"),
+                    admin: false,
+                    max_concurrent_jobs: None,
+                    monthly_budget_usd: Some(1.0),
+                },
+            ],
+        };
+        AccountService::open(
+            dir.join("usage.json"),
+            Arc::new(Authenticator::new(&settings)),
+        )
+        .unwrap()
+    }
+
+    fn params(owner: &str) -> ScrapeParams {
+        let mut params: ScrapeParams = serde_json::from_value(serde_json::json!({
+            "model": "gemini-1.5-flash-latest",
+            "url": "https://example.com",
+            "enableScraping": true,
+            "tags": [],
+            "enablePagination": false,
+        }))
+        .unwrap();
+        params.owner = Some(owner.to_string());
+        params
+    }
+
+    fn report(cost_usd: f64) -> CrawlReport {
+        let mut report = CrawlReport::default();
+        report.budget.cost_usd = cost_usd;
+        report
+    }
+
+    #[tokio::test]
+    async fn enforces_concurrency_and_monthly_budget() {
+        let dir = temp_dir();
+        let accounts = service(&dir);
+
+        let first = accounts.start_job(&params("alice")).await.unwrap();
+        assert_eq!(first.remaining_budget_usd, Some(1.0));
+        let err = accounts.start_job(&params("alice")).await.unwrap_err();
+        assert!(matches!(err, AppError::Quota(_)), "{}", err);
+
+        accounts
+            .finish_job(first, &Ok((Vec::new(), report(0.6))))
+            .await;
+        let second = accounts.start_job(&params("alice")).await.unwrap();
+        assert!((second.remaining_budget_usd.unwrap() - 0.4).abs() < 1e-9);
+        accounts
+            .finish_job(second, &Ok((Vec::new(), report(0.5))))
+            .await;
+
+        let err = accounts.start_job(&params("alice")).await.unwrap_err();
+        assert!(matches!(err, AppError::Budget(_)), "{}", err);
+
+        // Users without a quota are not limited.
+        for _ in 0..3 {
+            accounts.start_job(&params("bob")).await.unwrap();
+        }
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn reserves_budget_for_concurrent_jobs() {
+        let dir = temp_dir();
+        let accounts = service(&dir);
+
+        let share = |ticket: &JobTicket| ticket.remaining_budget_usd.unwrap();
+
+        // Both of dave's job slots get half of the monthly budget.
+        let first = accounts.start_job(&params("dave")).await.unwrap();
+        let second = accounts.start_job(&params("dave")).await.unwrap();
+        assert!((share(&first) - 0.5).abs() < 1e-9);
+        assert!((share(&second) - 0.5).abs() < 1e-9);
+
+        // Without a job limit, jobs get a share of what is left.
+        let mut capped = params("carol");
+        capped.budget.max_cost_usd = Some(0.3);
+        let first = accounts.start_job(&capped).await.unwrap();
+        assert_eq!(first.remaining_budget_usd, Some(0.3));
+        let second = accounts.start_job(&params("carol")).await.unwrap();
+        assert!((share(&second) - 0.7 / 3.0).abs() < 1e-9);
+
+        // The unspent part of a finished job's share is released.
+        let left = 1.0 - share(&second);
+        accounts
+            .finish_job(first, &Ok((Vec::new(), report(0.1))))
+            .await;
+        let third = accounts.start_job(&params("carol")).await.unwrap();
+        assert!((share(&third) - (left - 0.1) / 3.0).abs() < 1e-9);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn lists_only_own_jobs_and_survives_restarts() {
+        let dir = temp_dir();
+        let accounts = service(&dir);
+        accounts.start_job(&params("alice")).await.unwrap();
+        accounts.start_job(&params("bob")).await.unwrap();
+
+        let alice = accounts
+            .authenticator()
+            .authenticate(Some("alice-secret-key-0001"))
+            .unwrap();
+        let jobs = accounts.jobs(&alice).await;
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].owner.as_deref(), Some("alice"));
+        assert_eq!(accounts.jobs(&User::anonymous()).await.len(), 2);
+
+        let reopened = service(&dir);
+        let jobs = reopened.jobs(&alice).await;
+        assert_eq!(jobs[0].status, JobStatus::Failed);
+        assert_eq!(reopened.usage(&alice).await.running_jobs, 0);
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
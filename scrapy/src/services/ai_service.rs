@@ -9,9 +9,12 @@ use google_generative_ai_rs::v1::{
 };
 use log::{debug, info};
 use serde_json::Value;
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 use tokio::sync::Mutex;
 
+use crate::cache::{Cached, DiskCache};
+use crate::constants::MAX_OUTPUT_TOKENS;
+use crate::metrics::Metrics;
 use crate::models::{ScrapeParams, UsageMetadata};
 use crate::utils::calculate_price;
 use crate::{error::AppError, models::AiScrapingResult};
@@ -45,6 +48,12 @@ impl GeminiAIProvider {
     }
 }
 
+impl Default for GeminiAIProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[async_trait]
 impl AIProvider for GeminiAIProvider {
     async fn build_client(&self, model: &str, api_key: &str) -> Result<(), AppError> {
@@ -113,7 +122,7 @@ impl AIProvider for GeminiAIProvider {
                 top_p: None,
                 top_k: None,
                 candidate_count: None,
-                max_output_tokens: Some(8192),
+                max_output_tokens: Some(MAX_OUTPUT_TOKENS as i32),
                 stop_sequences: None,
                 response_mime_type: Some("application/json".to_string()),
             }),
@@ -132,6 +141,8 @@ impl AIProvider for GeminiAIProvider {
 
 pub struct AIService<T: AIProvider> {
     ai_provider: Arc<T>,
+    cache: Option<DiskCache>,
+    metrics: Arc<Metrics>,
 }
 
 impl<T: AIProvider + 'static> AIService<T> {
@@ -140,9 +151,21 @@ impl<T: AIProvider + 'static> AIService<T> {
         info!("AIService initialized successfully");
         Self {
             ai_provider: Arc::new(ai_provider),
+            cache: None,
+            metrics: Arc::new(Metrics::new()),
         }
     }
 
+    pub fn with_cache(mut self, cache: DiskCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
     pub async fn build_client(&self, model: &str, api_key: &str) -> Result<(), AppError> {
         self.ai_provider.build_client(model, api_key).await
     }
@@ -155,6 +178,25 @@ impl<T: AIProvider + 'static> AIService<T> {
     ) -> Result<AiScrapingResult, AppError> {
         debug!("Extracting items with params: {:?}", params);
 
+        // Cache hits report zero usage since no tokens were spent on this run.
+        let cache_key = DiskCache::hash_key(&[&params.model, system_prompt, user_prompt]);
+        if let Some(cache) = self.cache.as_ref().filter(|_| !params.bypass_cache) {
+            if let Some(Cached {
+                value: mut result,
+                fresh: true,
+            }) = cache.get::<AiScrapingResult>(&cache_key).await
+            {
+                debug!("AI cache hit for {}", cache_key);
+                result.cached = true;
+                result.usage_metadata = UsageMetadata {
+                    input_tokens: 0,
+                    output_tokens: 0,
+                    total_cost: 0.0,
+                };
+                return Ok(result);
+            }
+        }
+
         let mut result = AiScrapingResult {
             model: params.model.clone(),
             start_time: Utc::now(),
@@ -165,21 +207,42 @@ impl<T: AIProvider + 'static> AIService<T> {
                 output_tokens: 0,
                 total_cost: 0.0,
             },
+            url: None,
+            input_rows: Vec::new(),
+            cached: false,
         };
 
-        self.build_client(&params.model, &params.api_key).await?;
+        self.build_client(&params.model, params.api_key.expose())
+            .await?;
         let request = self
             .ai_provider
             .build_request(system_prompt.to_string(), user_prompt.to_string());
-        let response = self.ai_provider.process_request(request).await?;
+        let started = Instant::now();
+        let response = match self.ai_provider.process_request(request).await {
+            Ok(response) => response,
+            Err(err) => {
+                self.metrics
+                    .record_ai_request(&params.model, None, started.elapsed());
+                return Err(err);
+            }
+        };
 
         if let Ok(value) = serde_json::from_str::<Value>(&response) {
             result.data = value;
         }
 
         result.usage_metadata = self.ai_provider.get_usage_metadata().await;
+        self.metrics.record_ai_request(
+            &params.model,
+            Some(&result.usage_metadata),
+            started.elapsed(),
+        );
         result.end_time = Some(Utc::now());
 
+        if let Some(cache) = self.cache.as_ref().filter(|_| !result.data.is_null()) {
+            cache.put(&cache_key, &result).await;
+        }
+
         Ok(result)
     }
 }
@@ -1,15 +1,48 @@
-use crate::models::ScrapeParams;
+use crate::cache::DiskCache;
+use crate::constants::DEFAULT_MODEL;
+use crate::jobs::{diff_runs, snapshot_pages, JobStore};
+use crate::metrics::Metrics;
+use crate::models::{
+    CrawlReport, FetchConfig, JobRun, MessageType, PageReport, PageStatus, ScrapeParams,
+    WebSocketMessage, WebhookEvent,
+};
+use crate::outbound::OutboundGuard;
 use crate::spider::GenericSpider;
-use crate::Crawler;
+use crate::vault::{CredentialVault, Secret};
+use crate::{
+    crawler::{Canonicalizer, Frontier},
+    Crawler,
+};
 use crate::{error::AppError, models::AiScrapingResult};
-use std::sync::Arc;
+use chrono::Utc;
+use serde_json::json;
+use std::{collections::BTreeMap, sync::Arc};
+use tokio::sync::mpsc;
+use uuid::Uuid;
 
-use super::{ai_service::GeminiAIProvider, AIService, WebSocketService};
+use super::{
+    account_service::AccountService,
+    ai_service::GeminiAIProvider,
+    webhook_service::{JobWebhooks, WebhookService},
+    AIService, WebSocketService,
+};
 
 pub struct CrawlerService {
     pub crawler: Crawler,
     pub websocket_service: Arc<WebSocketService>,
     pub ai_service: Arc<AIService<GeminiAIProvider>>,
+    pub default_fetch_config: FetchConfig,
+    pub http_cache: Option<Arc<DiskCache>>,
+    pub job_store: Arc<JobStore>,
+    pub webhook_service: Arc<WebhookService>,
+    /// API key for crawls that do not bring their own.
+    pub default_api_key: Option<Secret>,
+    /// Model for crawls that do not name one.
+    pub default_model: String,
+    pub account_service: Option<Arc<AccountService>>,
+    pub vault: Option<Arc<CredentialVault>>,
+    pub outbound_guard: Arc<OutboundGuard>,
+    pub metrics: Arc<Metrics>,
 }
 
 impl CrawlerService {
@@ -17,27 +50,232 @@ impl CrawlerService {
         crawler: Crawler,
         websocket_service: Arc<WebSocketService>,
         ai_service: Arc<AIService<GeminiAIProvider>>,
+        default_fetch_config: FetchConfig,
+        http_cache: Option<Arc<DiskCache>>,
+        job_store: Arc<JobStore>,
+        webhook_service: Arc<WebhookService>,
     ) -> Self {
         Self {
             crawler,
             websocket_service,
             ai_service,
+            default_fetch_config,
+            http_cache,
+            job_store,
+            webhook_service,
+            default_api_key: None,
+            default_model: DEFAULT_MODEL.to_string(),
+            account_service: None,
+            vault: None,
+            outbound_guard: Arc::new(OutboundGuard::default()),
+            metrics: Arc::new(Metrics::new()),
         }
     }
 
-    pub async fn crawl(&self, params: ScrapeParams) -> Result<Vec<AiScrapingResult>, AppError> {
+    pub fn with_default_api_key(mut self, api_key: Option<Secret>) -> Self {
+        self.default_api_key = api_key;
+        self
+    }
+
+    pub fn with_default_model(mut self, model: String) -> Self {
+        self.default_model = model;
+        self
+    }
+
+    /// Records every crawl against its owner and enforces their quotas.
+    pub fn with_account_service(mut self, account_service: Arc<AccountService>) -> Self {
+        self.account_service = Some(account_service);
+        self
+    }
+
+    /// Resolves `credential` names in crawl requests.
+    pub fn with_vault(mut self, vault: Arc<CredentialVault>) -> Self {
+        self.vault = Some(vault);
+        self
+    }
+
+    /// Restricts the hosts crawls may fetch from.
+    pub fn with_outbound_guard(mut self, guard: Arc<OutboundGuard>) -> Self {
+        self.outbound_guard = guard;
+        self
+    }
+
+    /// Records page fetches and cache lookups into `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub async fn crawl(
+        &self,
+        mut params: ScrapeParams,
+    ) -> Result<(Vec<AiScrapingResult>, CrawlReport), AppError> {
+        if params.model.is_empty() {
+            params.model = self.default_model.clone();
+        }
+        params.validate()?;
+        if let Some(name) = &params.credential {
+            let vault = self.vault.as_ref().ok_or_else(|| {
+                AppError::Vault("stored credentials are not available".to_string())
+            })?;
+            params.api_key = vault.resolve(params.owner.as_deref(), name).await?;
+        } else if params.api_key.is_empty() {
+            params.api_key = self.default_api_key.clone().unwrap_or_default();
+        }
+        let ticket = match &self.account_service {
+            Some(accounts) => Some(accounts.start_job(&params).await?),
+            None => None,
+        };
+        // Never spend more than the share of the monthly budget reserved for
+        // this job.
+        if let Some(remaining) = ticket
+            .as_ref()
+            .and_then(|ticket| ticket.remaining_budget_usd)
+        {
+            params.budget.max_cost_usd = Some(remaining);
+        }
+
+        let webhooks = self.webhook_service.start_job(&params);
+        let item_sink = webhooks.as_ref().map(JobWebhooks::item_sink);
+
+        let outcome = self.run(params, item_sink).await;
+
+        if let Some(webhooks) = webhooks {
+            let (event, data) = match &outcome {
+                Ok((results, report)) => (
+                    WebhookEvent::JobCompleted,
+                    json!({
+                        "pages": report.pages.len(),
+                        "results": results.len(),
+                        "items": results.iter().map(|result| result.records().len()).sum::<usize>(),
+                        "diff": report.diff,
+                    }),
+                ),
+                Err(err) => (WebhookEvent::JobFailed, json!({ "error": err.to_string() })),
+            };
+            webhooks.finish(event, data);
+        }
+        if let (Some(accounts), Some(ticket)) = (&self.account_service, ticket) {
+            accounts.finish_job(ticket, &outcome).await;
+        }
+
+        outcome
+    }
+
+    async fn run(
+        &self,
+        params: ScrapeParams,
+        item_sink: Option<mpsc::UnboundedSender<Vec<serde_json::Value>>>,
+    ) -> Result<(Vec<AiScrapingResult>, CrawlReport), AppError> {
+        if params.incremental && params.job_id.is_none() {
+            return Err(AppError::Job(
+                "Incremental crawls require a job id".to_string(),
+            ));
+        }
+        let previous = match &params.job_id {
+            Some(job_id) => {
+                self.job_store
+                    .latest(params.owner.as_deref(), job_id)
+                    .await?
+            }
+            None => None,
+        };
+        let started_at = Utc::now();
+        if let Some(fetch) = &params.fetch {
+            self.outbound_guard
+                .check_job_proxies(&fetch.proxies)
+                .await?;
+        }
+
         let selectors = vec!["body"];
-        let generic_spider = GenericSpider::new(selectors, self.ai_service.clone(), params.clone())?;
+        let generic_spider = GenericSpider::new(
+            selectors,
+            self.ai_service.clone(),
+            params.clone(),
+            &self.default_fetch_config,
+            self.http_cache.clone(),
+            self.outbound_guard.clone(),
+        )?
+        .with_previous_run(previous.clone().filter(|_| params.incremental))
+        .with_item_sink(item_sink)
+        .with_metrics(self.metrics.clone());
         let spider = Arc::new(generic_spider);
-        self.crawler
-            .crawl(
-                spider.clone(),
-                params,
-            )
+        let frontier = Frontier::new(
+            &params.frontier,
+            Canonicalizer::new(params.canonical.clone()),
+        )?;
+
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        let forwarder = tokio::spawn(Self::forward_page_events(
+            self.websocket_service.clone(),
+            events_rx,
+            params.owner.clone(),
+        ));
+
+        let canonicalizer = Canonicalizer::new(params.canonical.clone());
+        let job_id = params.job_id.clone();
+        let owner = params.owner.clone();
+        let record_key = params.record_key.clone();
+        let mut report = self
+            .crawler
+            .crawl(spider.clone(), params, frontier, events_tx)
             .await;
+        let _ = forwarder.await;
 
+        for page in &mut report.pages {
+            page.duplicate_of = spider.duplicate_of(&page.url);
+        }
+        report.dedup = spider.dedup_stats();
+        report.cache = spider.cache_stats();
+        self.metrics.record_cache(&report.cache);
+        report.budget = spider.budget_stats();
         let results = spider.get_results().await;
 
-        Ok(results)
+        if let Some(job_id) = job_id {
+            let mut run = JobRun {
+                job_id,
+                run_id: Uuid::new_v4(),
+                started_at,
+                finished_at: Utc::now(),
+                pages: BTreeMap::new(),
+            };
+            snapshot_pages(
+                &mut run,
+                previous.as_ref(),
+                spider.page_states(),
+                &results,
+                &report,
+                &canonicalizer,
+            );
+
+            let diff = diff_runs(previous.as_ref(), &run, record_key.as_deref());
+            self.job_store.save(owner.as_deref(), &run).await?;
+            report.diff = Some(diff);
+        }
+
+        Ok((results, report))
+    }
+
+    async fn forward_page_events(
+        websocket_service: Arc<WebSocketService>,
+        mut events: mpsc::UnboundedReceiver<PageReport>,
+        owner: Option<String>,
+    ) {
+        while let Some(page) = events.recv().await {
+            let r#type = match page.status {
+                PageStatus::Scraped => MessageType::Progress,
+                PageStatus::DisallowedByRobots => MessageType::Warning,
+                PageStatus::Failed => MessageType::Error,
+            };
+            let message = WebSocketMessage {
+                r#type,
+                payload: page.url.clone(),
+                metadata: serde_json::to_value(&page).ok(),
+                owner: owner.clone(),
+            };
+            if let Err(err) = websocket_service.send_message(message).await {
+                log::debug!("No subscribers for page event: {}", err);
+            }
+        }
     }
 }
@@ -1,3 +1,6 @@
+mod account_service;
+pub use account_service::{AccountService, JobTicket};
+
 mod ai_service;
 pub use ai_service::{AIService, GeminiAIProvider};
 
@@ -6,3 +9,6 @@ pub use crawler_service::CrawlerService;
 
 mod websocket_service;
 pub use websocket_service::WebSocketService;
+
+mod webhook_service;
+pub use webhook_service::WebhookService;
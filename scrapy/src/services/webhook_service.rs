@@ -0,0 +1,529 @@
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+
+use chrono::Utc;
+use futures_util::future::join_all;
+use hmac::{Hmac, Mac};
+use reqwest::{header::CONTENT_TYPE, Client};
+use serde_json::Value;
+use sha2::Sha256;
+use tokio::{
+    fs,
+    sync::{mpsc, Mutex},
+    task::JoinHandle,
+};
+use uuid::Uuid;
+
+use crate::{
+    constants::USER_AGENT,
+    crawler::{backoff_delay, is_retryable, FetchError},
+    error::AppError,
+    models::{
+        ScrapeParams, WebhookConfig, WebhookDelivery, WebhookDeliveryStatus, WebhookEvent,
+        WebhookPayload,
+    },
+    outbound::OutboundGuard,
+};
+
+pub const SIGNATURE_HEADER: &str = "X-Scrapy-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Scrapy-Timestamp";
+pub const EVENT_HEADER: &str = "X-Scrapy-Event";
+pub const DELIVERY_HEADER: &str = "X-Scrapy-Delivery";
+
+/// Number of deliveries kept in the delivery log.
+const DELIVERY_LOG_SIZE: usize = 1000;
+
+/// HMAC-SHA256 signature of a webhook request, hex encoded.
+pub fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Delivers webhooks and keeps a log of the most recent deliveries, persisted
+/// as a single JSON file.
+pub struct WebhookService {
+    client: Client,
+    guard: Arc<OutboundGuard>,
+    global: Vec<WebhookConfig>,
+    path: PathBuf,
+    deliveries: StdMutex<VecDeque<WebhookDelivery>>,
+    persist_lock: Mutex<()>,
+}
+
+impl WebhookService {
+    /// Loads the delivery log from `path`. `global` webhooks are notified
+    /// about every crawl, in addition to the ones configured on the crawl
+    /// itself.
+    pub fn open(path: impl Into<PathBuf>, global: Vec<WebhookConfig>) -> Result<Self, AppError> {
+        let path = path.into();
+        let deliveries: VecDeque<WebhookDelivery> = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => VecDeque::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        let guard = Arc::new(OutboundGuard::default());
+        Ok(Self {
+            client: Self::client(&guard)?,
+            guard,
+            global,
+            path,
+            deliveries: StdMutex::new(deliveries),
+            persist_lock: Mutex::new(()),
+        })
+    }
+
+    /// Restricts webhook URLs to what `guard` allows.
+    pub fn with_outbound_guard(mut self, guard: Arc<OutboundGuard>) -> Result<Self, AppError> {
+        self.client = Self::client(&guard)?;
+        self.guard = guard;
+        Ok(self)
+    }
+
+    fn client(guard: &Arc<OutboundGuard>) -> Result<Client, AppError> {
+        let builder = Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(30));
+        Ok(guard.configure(builder, 10).build()?)
+    }
+
+    /// Most recent deliveries first.
+    pub fn deliveries(&self) -> Vec<WebhookDelivery> {
+        let deliveries = self
+            .deliveries
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        deliveries.iter().rev().cloned().collect()
+    }
+
+    /// Starts tracking a crawl, or returns `None` when no webhook is
+    /// configured for it.
+    pub fn start_job(self: &Arc<Self>, params: &ScrapeParams) -> Option<JobWebhooks> {
+        let hooks: Vec<WebhookConfig> = self
+            .global
+            .iter()
+            .chain(&params.webhooks)
+            .cloned()
+            .collect();
+        if hooks.is_empty() {
+            return None;
+        }
+
+        let crawl_id = Uuid::new_v4();
+        let (items, mut receiver) = mpsc::unbounded_channel::<Vec<Value>>();
+        let (senders, tasks): (Vec<_>, Vec<_>) = hooks
+            .iter()
+            .filter(|hook| hook.subscribes_to(WebhookEvent::ItemsBatch))
+            .map(|hook| {
+                let (sender, receiver) = mpsc::unbounded_channel();
+                let task = tokio::spawn(self.clone().deliver_batches(
+                    hook.clone(),
+                    crawl_id,
+                    params.job_id.clone(),
+                    receiver,
+                ));
+                (sender, task)
+            })
+            .unzip();
+        // Each webhook gets its own task, so a slow endpoint does not hold up
+        // the others.
+        let batches = tokio::spawn(async move {
+            while let Some(items) = receiver.recv().await {
+                for sender in &senders {
+                    let _ = sender.send(items.clone());
+                }
+            }
+            drop(senders);
+            for task in tasks {
+                if let Err(err) = task.await {
+                    log::error!("Webhook batch delivery task failed: {}", err);
+                }
+            }
+        });
+
+        Some(JobWebhooks {
+            service: self.clone(),
+            hooks,
+            crawl_id,
+            job_id: params.job_id.clone(),
+            items,
+            batches,
+        })
+    }
+
+    /// Buffers items for `hook` and delivers them in batches of its
+    /// `batch_size`, flushing what is left once the crawl ends.
+    async fn deliver_batches(
+        self: Arc<Self>,
+        hook: WebhookConfig,
+        crawl_id: Uuid,
+        job_id: Option<String>,
+        mut receiver: mpsc::UnboundedReceiver<Vec<Value>>,
+    ) {
+        let batch_size = hook.batch_size.max(1);
+        let mut buffer = Vec::new();
+        let mut sent = 0usize;
+
+        let mut open = true;
+        while open {
+            let items = receiver.recv().await;
+            open = items.is_some();
+            buffer.extend(items.into_iter().flatten());
+
+            while buffer.len() >= batch_size || (!open && !buffer.is_empty()) {
+                let batch: Vec<Value> = buffer.drain(..batch_size.min(buffer.len())).collect();
+                sent += 1;
+                let data = serde_json::json!({ "batch": sent, "items": batch });
+                let payload = payload(WebhookEvent::ItemsBatch, crawl_id, &job_id, data);
+                self.deliver(&hook, &payload).await;
+            }
+        }
+    }
+
+    /// Delivers `payload` to `hook`, retrying transient failures with
+    /// exponential backoff, and records the outcome in the delivery log.
+    pub async fn deliver(&self, hook: &WebhookConfig, payload: &WebhookPayload) -> WebhookDelivery {
+        let body = serde_json::to_vec(payload).unwrap_or_default();
+        let started_at = Utc::now();
+        let mut attempts = 0;
+
+        let outcome = loop {
+            attempts += 1;
+            match self.send(hook, payload, &body).await {
+                Ok(status) => break Ok(status),
+                Err(err) => {
+                    let retryable = is_retryable(err.failure_kind(), err.http_status());
+                    if !retryable || attempts > hook.retry.max_retries {
+                        break Err(err);
+                    }
+                    log::debug!(
+                        "Webhook delivery to {} failed (attempt {}): {}",
+                        hook.url,
+                        attempts,
+                        err
+                    );
+                    tokio::time::sleep(backoff_delay(&hook.retry, attempts)).await;
+                }
+            }
+        };
+
+        let delivery = WebhookDelivery {
+            id: Uuid::new_v4(),
+            payload_id: payload.id,
+            url: hook.url.clone(),
+            event: payload.event,
+            status: match outcome {
+                Ok(_) => WebhookDeliveryStatus::Delivered,
+                Err(_) => WebhookDeliveryStatus::Failed,
+            },
+            attempts,
+            http_status: match &outcome {
+                Ok(status) => Some(*status),
+                Err(err) => err.http_status(),
+            },
+            error: outcome.as_ref().err().map(ToString::to_string),
+            started_at,
+            finished_at: Utc::now(),
+        };
+        match &delivery.error {
+            Some(err) => log::warn!(
+                "Webhook {} to {} failed after {} attempt(s): {}",
+                payload.event.as_str(),
+                hook.url,
+                attempts,
+                err
+            ),
+            None => log::info!(
+                "Delivered webhook {} to {}",
+                payload.event.as_str(),
+                hook.url
+            ),
+        }
+
+        {
+            let mut deliveries = self
+                .deliveries
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            if deliveries.len() == DELIVERY_LOG_SIZE {
+                deliveries.pop_front();
+            }
+            deliveries.push_back(delivery.clone());
+        }
+        if let Err(err) = self.persist().await {
+            log::error!("Failed to save the webhook delivery log: {}", err);
+        }
+        delivery
+    }
+
+    async fn persist(&self) -> Result<(), AppError> {
+        // Held across the write so an older snapshot never replaces a newer one.
+        let _guard = self.persist_lock.lock().await;
+        let content = {
+            let deliveries = self
+                .deliveries
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            serde_json::to_vec_pretty(&*deliveries)?
+        };
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, content).await?;
+        fs::rename(&temp, &self.path).await?;
+        Ok(())
+    }
+
+    async fn send(
+        &self,
+        hook: &WebhookConfig,
+        payload: &WebhookPayload,
+        body: &[u8],
+    ) -> Result<u16, AppError> {
+        self.guard.check(&hook.url)?;
+        let timestamp = Utc::now().timestamp().to_string();
+        let mut request = self
+            .client
+            .post(&hook.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, payload.event.as_str())
+            .header(DELIVERY_HEADER, payload.id.to_string())
+            .header(TIMESTAMP_HEADER, &timestamp);
+        if let Some(secret) = &hook.secret {
+            request = request.header(
+                SIGNATURE_HEADER,
+                format!("sha256={}", sign(secret.expose(), &timestamp, body)),
+            );
+        }
+
+        let response = request.body(body.to_vec()).send().await?;
+        let status = response.status().as_u16();
+        if !response.status().is_success() {
+            return Err(AppError::HttpStatus {
+                url: hook.url.clone(),
+                status,
+                retry_after: None,
+            });
+        }
+        Ok(status)
+    }
+}
+
+fn payload(
+    event: WebhookEvent,
+    crawl_id: Uuid,
+    job_id: &Option<String>,
+    data: Value,
+) -> WebhookPayload {
+    WebhookPayload {
+        id: Uuid::new_v4(),
+        event,
+        crawl_id,
+        job_id: job_id.clone(),
+        created_at: Utc::now(),
+        data,
+    }
+}
+
+/// Webhooks of a single crawl. Items sent through [`JobWebhooks::item_sink`]
+/// are delivered in batches while the crawl runs.
+pub struct JobWebhooks {
+    service: Arc<WebhookService>,
+    hooks: Vec<WebhookConfig>,
+    crawl_id: Uuid,
+    job_id: Option<String>,
+    items: mpsc::UnboundedSender<Vec<Value>>,
+    batches: JoinHandle<()>,
+}
+
+impl JobWebhooks {
+    pub fn item_sink(&self) -> mpsc::UnboundedSender<Vec<Value>> {
+        self.items.clone()
+    }
+
+    /// Sends the completion or failure event once every item batch has been
+    /// delivered. Item batches are only flushed after all sinks are dropped.
+    pub fn finish(self, event: WebhookEvent, data: Value) -> JoinHandle<()> {
+        let Self {
+            service,
+            hooks,
+            crawl_id,
+            job_id,
+            items,
+            batches,
+        } = self;
+        drop(items);
+
+        tokio::spawn(async move {
+            if let Err(err) = batches.await {
+                log::error!("Webhook batch delivery task failed: {}", err);
+            }
+            let payload = payload(event, crawl_id, &job_id, data);
+            join_all(
+                hooks
+                    .iter()
+                    .filter(|hook| hook.subscribes_to(event))
+                    .map(|hook| service.deliver(hook, &payload)),
+            )
+            .await;
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{models::RetryConfig, vault::Secret};
+
+    /// Accepts requests, answering with the given statuses in turn (the last
+    /// one repeats), and forwards each request to the returned receiver.
+    async fn receiver(statuses: Vec<u16>) -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (requests, received) = mpsc::unbounded_channel();
+        let count = Arc::new(AtomicUsize::new(0));
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let index = count.fetch_add(1, Ordering::SeqCst);
+                let status = statuses[index.min(statuses.len() - 1)];
+                let requests = requests.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    while let Ok(read) = socket.read(&mut chunk).await {
+                        request.extend_from_slice(&chunk[..read]);
+                        let text = String::from_utf8_lossy(&request).to_string();
+                        if read == 0 || is_complete(&text) {
+                            let _ = requests.send(text);
+                            break;
+                        }
+                    }
+                    let response = format!(
+                        "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        status
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        (format!("http://{}/hook", address), received)
+    }
+
+    fn is_complete(request: &str) -> bool {
+        let Some((head, body)) = request.split_once("\r\n\r\n") else {
+            return false;
+        };
+        header(head, "content-length").and_then(|len| len.parse().ok()) == Some(body.len())
+    }
+
+    fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+        request.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    fn service(global: Vec<WebhookConfig>) -> (WebhookService, PathBuf) {
+        let path = std::env::temp_dir()
+            .join(format!("scrapy-webhooks-{}", Uuid::new_v4()))
+            .join("deliveries.json");
+        let service = WebhookService::open(&path, global)
+            .unwrap()
+            .with_outbound_guard(Arc::new(OutboundGuard::unrestricted()))
+            .unwrap();
+        (service, path)
+    }
+
+    fn hook(url: String) -> WebhookConfig {
+        WebhookConfig {
+            batch_size: 2,
+            retry: RetryConfig {
+                max_retries: 3,
+                base_delay_ms: 10,
+                max_delay_ms: 20,
+            },
+            ..WebhookConfig::new(url, Some(Secret::new("s3cret")))
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_and_signs_deliveries() {
+        let (url, mut requests) = receiver(vec![503, 200]).await;
+        let (service, path) = service(Vec::new());
+        let payload = payload(WebhookEvent::JobFailed, Uuid::new_v4(), &None, Value::Null);
+
+        let delivery = service.deliver(&hook(url), &payload).await;
+
+        assert_eq!(delivery.status, WebhookDeliveryStatus::Delivered);
+        assert_eq!((delivery.attempts, delivery.http_status), (2, Some(200)));
+        assert_eq!(service.deliveries().len(), 1);
+        let reopened = WebhookService::open(&path, Vec::new()).unwrap();
+        assert_eq!(reopened.deliveries()[0].id, delivery.id);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+
+        requests.recv().await.unwrap();
+        let request = requests.recv().await.unwrap();
+        let (head, body) = request.split_once("\r\n\r\n").unwrap();
+        let timestamp = header(head, TIMESTAMP_HEADER).unwrap();
+        let expected = format!("sha256={}", sign("s3cret", timestamp, body.as_bytes()));
+        assert_eq!(header(head, SIGNATURE_HEADER), Some(expected.as_str()));
+        assert_eq!(header(head, EVENT_HEADER), Some("jobFailed"));
+    }
+
+    #[tokio::test]
+    async fn batches_items_before_the_completion_event() {
+        let (url, mut requests) = receiver(vec![200]).await;
+        let (service, path) = service(vec![hook(url)]);
+        let service = Arc::new(service);
+        let params: ScrapeParams = serde_json::from_value(serde_json::json!({
+            "model": "gemini-1.5-flash",
+            "apiKey": "",
+            "url": "https://example.com",
+            "enableScraping": true,
+            "tags": [],
+            "enablePagination": false,
+        }))
+        .unwrap();
+
+        let job = service.start_job(&params).unwrap();
+        let items = job.item_sink();
+        items
+            .send(vec![Value::from(1), Value::from(2), Value::from(3)])
+            .unwrap();
+        items.send(vec![Value::from(4), Value::from(5)]).unwrap();
+        drop(items);
+        job.finish(WebhookEvent::JobCompleted, Value::Null)
+            .await
+            .unwrap();
+
+        let mut events = Vec::new();
+        while let Ok(request) = requests.try_recv() {
+            events.push(header(&request, EVENT_HEADER).unwrap().to_string());
+        }
+        assert_eq!(
+            events,
+            ["itemsBatch", "itemsBatch", "itemsBatch", "jobCompleted"]
+        );
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
@@ -0,0 +1,363 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::fs;
+
+use crate::{
+    crawler::{Canonicalizer, Frontier},
+    error::AppError,
+    models::{JobSpec, JOB_SPEC_VERSION},
+    openapi::ApiSchema,
+    utils::owner_dir,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SpecFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl SpecFormat {
+    pub const ALL: [SpecFormat; 3] = [SpecFormat::Yaml, SpecFormat::Toml, SpecFormat::Json];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SpecFormat::Yaml => "yaml",
+            SpecFormat::Toml => "toml",
+            SpecFormat::Json => "json",
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for SpecFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "yaml" | "yml" => Ok(SpecFormat::Yaml),
+            "toml" => Ok(SpecFormat::Toml),
+            "json" => Ok(SpecFormat::Json),
+            other => Err(format!(
+                "unknown spec format {:?}, expected yaml, toml or json",
+                other
+            )),
+        }
+    }
+}
+
+/// Parses and validates a job spec.
+pub fn parse(source: &str, format: SpecFormat) -> Result<JobSpec, AppError> {
+    let spec: JobSpec = match format {
+        SpecFormat::Yaml => serde_yaml::from_str(source).map_err(|err| err.to_string()),
+        SpecFormat::Toml => toml::from_str(source).map_err(|err| err.to_string()),
+        SpecFormat::Json => serde_json::from_str(source).map_err(|err| err.to_string()),
+    }
+    .map_err(AppError::Spec)?;
+
+    validate(&spec)?;
+    Ok(spec)
+}
+
+/// Reads a spec file, picking the format from its extension.
+pub fn load(path: &Path) -> Result<JobSpec, AppError> {
+    let format = SpecFormat::from_path(path).ok_or_else(|| {
+        AppError::Spec(format!(
+            "Cannot tell the format of {:?}: use a .yaml, .toml or .json extension",
+            path
+        ))
+    })?;
+    parse(&std::fs::read_to_string(path)?, format)
+}
+
+fn validate(spec: &JobSpec) -> Result<(), AppError> {
+    let invalid = |message: String| Err(AppError::Spec(message));
+
+    if spec.version != JOB_SPEC_VERSION {
+        return invalid(format!(
+            "Unsupported spec version {} (expected {})",
+            spec.version, JOB_SPEC_VERSION
+        ));
+    }
+    if !is_valid_name(&spec.name) {
+        return invalid(format!(
+            "Invalid name {:?}: use letters, digits, '-', '_' or '.'",
+            spec.name
+        ));
+    }
+    if spec.seeds.urls.is_empty() && spec.seeds.sitemaps.is_none() {
+        return invalid("Seeds must contain at least one URL or sitemap".to_string());
+    }
+    if spec.extraction.enabled
+        && spec.extraction.fields.is_empty()
+        && spec.extraction.schema.is_none()
+    {
+        return invalid("Extraction needs fields or a schema".to_string());
+    }
+    if spec
+        .budget
+        .max_cost_usd
+        .is_some_and(|cost| cost.is_nan() || cost < 0.0)
+    {
+        return invalid("Budget maxCostUsd must not be negative".to_string());
+    }
+
+    // Reject patterns the crawler would fail on at run time.
+    let params = spec.to_params();
+    Frontier::new(
+        &params.frontier,
+        Canonicalizer::new(params.canonical.clone()),
+    )
+    .map_err(|err| AppError::Spec(format!("Invalid priority pattern: {}", err)))?;
+    Ok(())
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 128
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[derive(Debug, Clone, Serialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SpecSummary {
+    pub name: String,
+    pub description: Option<String>,
+    pub format: SpecFormat,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, ApiSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredSpec {
+    pub spec: JobSpec,
+    pub format: SpecFormat,
+    /// The file as uploaded, comments and formatting included.
+    pub source: String,
+}
+
+/// Saved specs, one file per spec named after it, kept verbatim so they can
+/// be copied into a repository. Each user has their own set of specs.
+pub struct SpecStore {
+    dir: PathBuf,
+}
+
+impl SpecStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub async fn list(&self, owner: Option<&str>) -> Result<Vec<SpecSummary>, AppError> {
+        let mut specs = Vec::new();
+        let mut entries = match fs::read_dir(owner_dir(&self.dir, owner)).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(specs),
+            Err(err) => return Err(err.into()),
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let Some(format) = SpecFormat::from_path(&path) else {
+                continue;
+            };
+            let source = fs::read_to_string(&path).await?;
+            let updated_at = entry
+                .metadata()
+                .await
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .map(DateTime::<Utc>::from);
+            match parse(&source, format) {
+                Ok(spec) => specs.push(SpecSummary {
+                    name: spec.name,
+                    description: spec.description,
+                    format,
+                    updated_at,
+                }),
+                Err(err) => log::warn!("Ignoring invalid spec {:?}: {}", path, err),
+            }
+        }
+
+        specs.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(specs)
+    }
+
+    pub async fn get(
+        &self,
+        owner: Option<&str>,
+        name: &str,
+    ) -> Result<Option<StoredSpec>, AppError> {
+        if !is_valid_name(name) {
+            return Ok(None);
+        }
+        for format in SpecFormat::ALL {
+            match fs::read_to_string(self.path(owner, name, format)).await {
+                Ok(source) => {
+                    let spec = parse(&source, format)?;
+                    return Ok(Some(StoredSpec {
+                        spec,
+                        format,
+                        source,
+                    }));
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Validates and stores `source`, replacing any spec with the same name.
+    pub async fn save(
+        &self,
+        owner: Option<&str>,
+        source: String,
+        format: SpecFormat,
+    ) -> Result<StoredSpec, AppError> {
+        let spec = parse(&source, format)?;
+        fs::create_dir_all(owner_dir(&self.dir, owner)).await?;
+        self.remove(owner, &spec.name).await?;
+
+        let path = self.path(owner, &spec.name, format);
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, &source).await?;
+        fs::rename(&temp, &path).await?;
+
+        log::info!("Saved job spec {} to {:?}", spec.name, path);
+        Ok(StoredSpec {
+            spec,
+            format,
+            source,
+        })
+    }
+
+    pub async fn remove(&self, owner: Option<&str>, name: &str) -> Result<bool, AppError> {
+        if !is_valid_name(name) {
+            return Ok(false);
+        }
+        let mut removed = false;
+        for format in SpecFormat::ALL {
+            match fs::remove_file(self.path(owner, name, format)).await {
+                Ok(()) => removed = true,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(removed)
+    }
+
+    fn path(&self, owner: Option<&str>, name: &str, format: SpecFormat) -> PathBuf {
+        owner_dir(&self.dir, owner).join(format!("{}.{}", name, format.extension()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ExportFormat;
+
+    const SPEC: &str = r#"
+version: 1
+name: product-catalog
+seeds:
+  urls:
+    - https://shop.example.com/products
+links:
+  follow: true
+  maxDepth: 2
+  priorityPatterns:
+    - pattern: /products/
+      boost: 5
+extraction:
+  fields: [name, price]
+budget:
+  maxPages: 200
+  maxCostUsd: 2.5
+outputs:
+  - format: csv
+    path: products.csv
+recordKey: sku
+"#;
+
+    #[test]
+    fn maps_a_yaml_spec_to_crawl_params() {
+        let spec = parse(SPEC, SpecFormat::Yaml).unwrap();
+        let params = spec.to_params();
+
+        assert_eq!(params.start_urls().len(), 1);
+        assert_eq!(params.tags, ["name", "price"]);
+        assert_eq!(params.job_id.as_deref(), Some("product-catalog"));
+        assert!(params.frontier.follow_links);
+        assert_eq!(params.frontier.max_depth, Some(2));
+        assert_eq!(params.frontier.max_pages, Some(200));
+        assert_eq!(params.budget.max_cost_usd, Some(2.5));
+        assert_eq!(spec.outputs[0].format, ExportFormat::Csv);
+
+        let toml = toml::to_string(&spec).unwrap();
+        assert_eq!(parse(&toml, SpecFormat::Toml).unwrap().name, spec.name);
+    }
+
+    #[test]
+    fn rejects_invalid_specs() {
+        let cases = [
+            (SPEC.replace("version: 1", "version: 2"), "version"),
+            (SPEC.replace("maxDepth", "maxDepht"), "unknown field"),
+            (
+                SPEC.replace("name: product-catalog", "name: ../etc"),
+                "name",
+            ),
+            (
+                SPEC.replace("pattern: /products/", "pattern: \"(\""),
+                "pattern",
+            ),
+        ];
+
+        for (source, expected) in cases {
+            let err = parse(&source, SpecFormat::Yaml).unwrap_err().to_string();
+            assert!(
+                err.contains(expected),
+                "{:?} does not mention {:?}",
+                err,
+                expected
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_each_users_specs_apart() {
+        let dir = std::env::temp_dir().join(format!("scrapy-specs-{}", uuid::Uuid::new_v4()));
+        let store = SpecStore::new(&dir);
+
+        store
+            .save(Some("alice"), SPEC.to_string(), SpecFormat::Yaml)
+            .await
+            .unwrap();
+        let bobs = SPEC.replace("maxPages: 200", "maxPages: 10");
+        store
+            .save(Some("bob"), bobs, SpecFormat::Yaml)
+            .await
+            .unwrap();
+
+        let alice = store.get(Some("alice"), "product-catalog").await.unwrap();
+        let bob = store.get(Some("bob"), "product-catalog").await.unwrap();
+        assert_eq!(alice.unwrap().spec.budget.max_pages, Some(200));
+        assert_eq!(bob.unwrap().spec.budget.max_pages, Some(10));
+        assert!(store.list(None).await.unwrap().is_empty());
+
+        assert!(store.remove(Some("bob"), "product-catalog").await.unwrap());
+        assert_eq!(store.list(Some("alice")).await.unwrap().len(), 1);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,513 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex as StdMutex,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chromiumoxide::{
+    cdp::browser_protocol::{
+        fetch::{self, ContinueRequestParams, EventRequestPaused, FailRequestParams},
+        network::{
+            ErrorReason, EventLoadingFailed, EventLoadingFinished, EventRequestWillBeSent, Headers,
+            RequestId, ResourceType, SetExtraHttpHeadersParams, SetUserAgentOverrideParams,
+        },
+    },
+    Browser, BrowserConfig, Page,
+};
+use futures_util::StreamExt;
+use tokio::{
+    sync::OnceCell,
+    task::JoinHandle,
+    time::{sleep, timeout, Instant},
+};
+
+use crate::{
+    error::AppError,
+    models::{BrowserOptions, FetchConfig},
+    outbound::OutboundGuard,
+};
+
+use super::fetcher::{FetchedPage, Fetcher};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const NETWORK_IDLE: Duration = Duration::from_millis(500);
+const SCROLL_SETTLE: Duration = Duration::from_millis(500);
+
+struct RunningBrowser {
+    browser: Browser,
+    handler: JoinHandle<()>,
+}
+
+impl Drop for RunningBrowser {
+    fn drop(&mut self) {
+        self.handler.abort();
+    }
+}
+
+#[derive(Default)]
+struct NetworkActivity {
+    in_flight: HashSet<RequestId>,
+    last_change: Option<Instant>,
+}
+
+/// Renders pages in headless Chromium. Every request the page makes,
+/// including redirects and subresources, is paused and checked against the
+/// outbound guard before Chromium may send it. WebSocket connections are not
+/// covered by request interception.
+pub struct BrowserFetcher {
+    options: BrowserOptions,
+    guard: Arc<OutboundGuard>,
+    browser: OnceCell<RunningBrowser>,
+    user_agents: Vec<String>,
+    next_user_agent: AtomicUsize,
+    headers: BTreeMap<String, String>,
+    proxy: Option<String>,
+    max_response_bytes: usize,
+}
+
+impl BrowserFetcher {
+    pub fn new(options: BrowserOptions, guard: Arc<OutboundGuard>) -> Self {
+        Self {
+            options,
+            guard,
+            browser: OnceCell::new(),
+            user_agents: Vec::new(),
+            next_user_agent: AtomicUsize::new(0),
+            headers: BTreeMap::new(),
+            proxy: None,
+            max_response_bytes: FetchConfig::default().max_response_bytes,
+        }
+    }
+
+    /// Applies the job's user agents, headers, proxy and response size
+    /// limit. Chromium takes a single proxy without credentials, which
+    /// `ScrapeParams::validate` checks; timeouts, redirects and cookies are
+    /// Chromium's own.
+    pub fn with_fetch_config(mut self, config: &FetchConfig) -> Self {
+        self.user_agents = if config.user_agent_pool.is_empty() {
+            config.user_agent.iter().cloned().collect()
+        } else {
+            config.user_agent_pool.clone()
+        };
+        self.headers = config.headers.clone();
+        self.proxy = config.proxies.first().cloned();
+        self.max_response_bytes = config.max_response_bytes;
+        self
+    }
+
+    async fn browser(&self) -> Result<&Browser, AppError> {
+        let running = self
+            .browser
+            .get_or_try_init(|| async {
+                let mut config = BrowserConfig::builder()
+                    .no_sandbox()
+                    .request_timeout(Duration::from_millis(self.options.timeout_ms));
+                if let Some(proxy) = &self.proxy {
+                    config = config.arg(format!("--proxy-server={}", proxy));
+                }
+                let config = config.build().map_err(AppError::Browser)?;
+
+                let (browser, mut handler) = Browser::launch(config).await?;
+                let handler = tokio::spawn(async move {
+                    while let Some(event) = handler.next().await {
+                        if let Err(err) = event {
+                            log::debug!("Browser handler error: {}", err);
+                        }
+                    }
+                });
+
+                Ok::<_, AppError>(RunningBrowser { browser, handler })
+            })
+            .await?;
+
+        Ok(&running.browser)
+    }
+
+    async fn track_network(
+        page: &Page,
+    ) -> Result<(Arc<StdMutex<NetworkActivity>>, JoinHandle<()>), AppError> {
+        let mut started = page.event_listener::<EventRequestWillBeSent>().await?;
+        let mut finished = page.event_listener::<EventLoadingFinished>().await?;
+        let mut failed = page.event_listener::<EventLoadingFailed>().await?;
+
+        let activity = Arc::new(StdMutex::new(NetworkActivity::default()));
+        let tracker = activity.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                let (request_id, starting) = tokio::select! {
+                    Some(event) = started.next() => (event.request_id.clone(), true),
+                    Some(event) = finished.next() => (event.request_id.clone(), false),
+                    Some(event) = failed.next() => (event.request_id.clone(), false),
+                    else => break,
+                };
+
+                let mut activity = tracker.lock().unwrap_or_else(|err| err.into_inner());
+                if starting {
+                    activity.in_flight.insert(request_id);
+                } else {
+                    activity.in_flight.remove(&request_id);
+                }
+                activity.last_change = Some(Instant::now());
+            }
+        });
+
+        Ok((activity, handle))
+    }
+
+    /// Intercepts the requests of `page` and fails those the guard refuses.
+    /// The first refused document request is kept to explain a failed
+    /// navigation.
+    async fn guard_requests(
+        &self,
+        page: &Page,
+    ) -> Result<(Arc<StdMutex<Option<AppError>>>, JoinHandle<()>), AppError> {
+        let mut paused = page.event_listener::<EventRequestPaused>().await?;
+        page.execute(fetch::EnableParams::default()).await?;
+
+        let blocked = Arc::new(StdMutex::new(None));
+        let first_blocked = blocked.clone();
+        let guard = self.guard.clone();
+        let page = page.clone();
+        let handle = tokio::spawn(async move {
+            while let Some(event) = paused.next().await {
+                let guard = guard.clone();
+                let page = page.clone();
+                let first_blocked = first_blocked.clone();
+                tokio::spawn(async move {
+                    let url = &event.request.url;
+                    let checked = if url.starts_with("data:") || url.starts_with("blob:") {
+                        Ok(())
+                    } else {
+                        guard.check_resolved(url).await
+                    };
+                    let response = match checked {
+                        Ok(()) => page
+                            .execute(ContinueRequestParams::new(event.request_id.clone()))
+                            .await
+                            .map(|_| ()),
+                        Err(err) => {
+                            log::warn!("Browser request refused: {}", err);
+                            let params = FailRequestParams::new(
+                                event.request_id.clone(),
+                                ErrorReason::BlockedByClient,
+                            );
+                            if event.resource_type == ResourceType::Document {
+                                first_blocked
+                                    .lock()
+                                    .unwrap_or_else(|err| err.into_inner())
+                                    .get_or_insert(err);
+                            }
+                            page.execute(params).await.map(|_| ())
+                        }
+                    };
+                    if let Err(err) = response {
+                        log::debug!("Failed to answer paused request {}: {}", url, err);
+                    }
+                });
+            }
+        });
+
+        Ok((blocked, handle))
+    }
+
+    /// Sets the next user agent of the rotation and the extra headers on
+    /// `page`.
+    async fn configure_page(&self, page: &Page) -> Result<(), AppError> {
+        if !self.user_agents.is_empty() {
+            let index =
+                self.next_user_agent.fetch_add(1, Ordering::Relaxed) % self.user_agents.len();
+            page.set_user_agent(SetUserAgentOverrideParams::new(
+                self.user_agents[index].clone(),
+            ))
+            .await?;
+        }
+        if !self.headers.is_empty() {
+            let headers = serde_json::to_value(&self.headers)?;
+            page.execute(SetExtraHttpHeadersParams::new(Headers::new(headers)))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn wait_for_selector(page: &Page, selector: &str) {
+        while page.find_element(selector).await.is_err() {
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn wait_for_network_idle(activity: &StdMutex<NetworkActivity>) {
+        loop {
+            let idle = {
+                let activity = activity.lock().unwrap_or_else(|err| err.into_inner());
+                activity.in_flight.is_empty()
+                    && activity
+                        .last_change
+                        .is_none_or(|changed| changed.elapsed() >= NETWORK_IDLE)
+            };
+            if idle {
+                return;
+            }
+            sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn scroll_to_bottom(&self, page: &Page) -> Result<(), AppError> {
+        let mut last_height = 0;
+        for _ in 0..self.options.max_scrolls {
+            let height: i64 = page
+                .evaluate(
+                    "window.scrollTo(0, document.body.scrollHeight); document.body.scrollHeight",
+                )
+                .await?
+                .into_value()
+                .unwrap_or_default();
+            if height == last_height {
+                break;
+            }
+            last_height = height;
+            sleep(SCROLL_SETTLE).await;
+        }
+        Ok(())
+    }
+
+    async fn render(
+        &self,
+        page: &Page,
+        url: &str,
+        activity: &StdMutex<NetworkActivity>,
+    ) -> Result<FetchedPage, AppError> {
+        page.goto(url).await?;
+
+        let status = page
+            .wait_for_navigation_response()
+            .await?
+            .and_then(|request| request.response.as_ref().map(|response| response.status));
+        if let Some(status) = status.filter(|status| !(200..300).contains(status)) {
+            return Err(AppError::HttpStatus {
+                url: url.to_string(),
+                status: status as u16,
+                retry_after: None,
+            });
+        }
+
+        if let Some(selector) = &self.options.wait_for_selector {
+            Self::wait_for_selector(page, selector).await;
+        }
+        if self.options.scroll_to_bottom {
+            self.scroll_to_bottom(page).await?;
+        }
+        if self.options.wait_for_network_idle {
+            Self::wait_for_network_idle(activity).await;
+        }
+
+        let body = page.content().await?;
+        if body.len() > self.max_response_bytes {
+            return Err(AppError::ResponseTooLarge {
+                url: url.to_string(),
+                limit: self.max_response_bytes,
+            });
+        }
+
+        Ok(FetchedPage {
+            url: page.url().await?.unwrap_or_else(|| url.to_string()),
+            body,
+            status: status.map_or(200, |status| status as u16),
+            from_cache: false,
+        })
+    }
+}
+
+#[async_trait]
+impl Fetcher for BrowserFetcher {
+    async fn fetch(&self, url: &str) -> Result<FetchedPage, AppError> {
+        self.guard.check_resolved(url).await?;
+        let page = self.browser().await?.new_page("about:blank").await?;
+        self.configure_page(&page).await?;
+        let (blocked, interceptor) = self.guard_requests(&page).await?;
+        let (activity, tracker) = Self::track_network(&page).await?;
+
+        let result = timeout(
+            Duration::from_millis(self.options.timeout_ms),
+            self.render(&page, url, &activity),
+        )
+        .await
+        .unwrap_or_else(|_| Err(AppError::RenderTimeout(url.to_string())));
+        // A navigation to a refused address fails with a generic browser
+        // error; report what the guard refused instead.
+        let blocked = blocked.lock().unwrap_or_else(|err| err.into_inner()).take();
+        let result = match (result, blocked) {
+            (Err(_), Some(blocked)) => Err(blocked),
+            (result, _) => result,
+        };
+
+        tracker.abort();
+        interceptor.abort();
+        if let Err(err) = page.close().await {
+            log::debug!("Failed to close browser page for {}: {}", url, err);
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use scraper::{Html, Selector};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{models::FetchConfig, spider::http::HttpFetcher};
+
+    async fn serve_fixture(name: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name);
+        let body = std::fs::read_to_string(path).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let body = body.clone();
+                tokio::spawn(async move {
+                    let mut request = [0u8; 4096];
+                    let _ = socket.read(&mut request).await;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        format!("http://{}/", address)
+    }
+
+    fn count_products(html: &str) -> usize {
+        let selector = Selector::parse("li.product").unwrap();
+        Html::parse_document(html).select(&selector).count()
+    }
+
+    #[tokio::test]
+    async fn http_fetcher_returns_unrendered_shell() {
+        let url = serve_fixture("spa.html").await;
+        let fetcher = HttpFetcher::new(
+            &FetchConfig::default(),
+            Arc::new(OutboundGuard::unrestricted()),
+        )
+        .unwrap();
+
+        let page = fetcher.fetch(&url).await.unwrap();
+
+        assert_eq!(count_products(&page.body), 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium installation"]
+    async fn browser_fetcher_refuses_redirects_to_blocked_addresses() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 4096];
+                let _ = socket.read(&mut request).await;
+                let response = "HTTP/1.1 302 Found\r\nLocation: http://10.255.255.1/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        let guard = OutboundGuard::new(&crate::config::OutboundSettings {
+            allowed_cidrs: vec!["127.0.0.0/8".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+        let fetcher = BrowserFetcher::new(BrowserOptions::default(), Arc::new(guard));
+
+        let err = fetcher
+            .fetch(&format!("http://{}/", address))
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(&err, AppError::Blocked { reason, .. } if reason.contains("10.255.255.1")),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium installation"]
+    async fn browser_fetcher_renders_javascript() {
+        let url = serve_fixture("spa.html").await;
+        let fetcher = BrowserFetcher::new(
+            BrowserOptions {
+                wait_for_selector: Some("li.product".to_string()),
+                ..BrowserOptions::default()
+            },
+            Arc::new(OutboundGuard::unrestricted()),
+        );
+
+        let page = fetcher.fetch(&url).await.unwrap();
+
+        assert_eq!(count_products(&page.body), 2);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local Chromium installation"]
+    async fn browser_fetcher_applies_the_fetch_config() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0u8; 4096];
+                let read = socket.read(&mut request).await.unwrap_or(0);
+                let body = format!(
+                    "<pre>{}</pre>",
+                    String::from_utf8_lossy(&request[..read]).to_lowercase()
+                );
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        let url = format!("http://{}/", address);
+        let config = FetchConfig {
+            headers: [("x-job".to_string(), "1".to_string())].into(),
+            user_agent: Some("job-agent".to_string()),
+            ..FetchConfig::default()
+        };
+        let fetcher = BrowserFetcher::new(
+            BrowserOptions::default(),
+            Arc::new(OutboundGuard::unrestricted()),
+        )
+        .with_fetch_config(&config);
+
+        let page = fetcher.fetch(&url).await.unwrap();
+        assert!(page.body.contains("user-agent: job-agent"), "{}", page.body);
+        assert!(page.body.contains("x-job: 1"), "{}", page.body);
+
+        let small = BrowserFetcher::new(
+            BrowserOptions::default(),
+            Arc::new(OutboundGuard::unrestricted()),
+        )
+        .with_fetch_config(&FetchConfig {
+            max_response_bytes: 10,
+            ..FetchConfig::default()
+        });
+        let err = small.fetch(&url).await.unwrap_err();
+        assert!(matches!(err, AppError::ResponseTooLarge { .. }), "{}", err);
+    }
+}
@@ -0,0 +1,197 @@
+use std::sync::Mutex as StdMutex;
+
+use tokio::sync::Notify;
+
+use crate::{
+    constants::MAX_OUTPUT_TOKENS,
+    models::{BudgetConfig, BudgetStats, UsageMetadata},
+    utils::calculate_price,
+};
+
+/// Prompt bytes per token used to estimate the cost of an extraction. Low on
+/// purpose: estimates are reserved up front and the unused part is released.
+const ESTIMATED_BYTES_PER_TOKEN: usize = 3;
+
+/// Worst case usage of an extraction: its estimated prompt plus the most
+/// output the model may return.
+#[derive(Debug, Clone, Copy)]
+pub struct Estimate {
+    tokens: u64,
+    cost_usd: f64,
+}
+
+impl Estimate {
+    pub fn of(model: &str, prompt: &str) -> Self {
+        let input = prompt.len().div_ceil(ESTIMATED_BYTES_PER_TOKEN) as u64;
+        Self {
+            tokens: input + MAX_OUTPUT_TOKENS,
+            cost_usd: calculate_price(model, input, MAX_OUTPUT_TOKENS),
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    stats: BudgetStats,
+    reserved_tokens: u64,
+    reserved_cost_usd: f64,
+    in_flight: usize,
+}
+
+/// Enforces a job's AI budget by reserving the worst case cost of each
+/// extraction before it starts and settling it with the real usage once it
+/// is done.
+pub struct BudgetTracker {
+    limits: BudgetConfig,
+    state: StdMutex<State>,
+    settled: Notify,
+}
+
+impl BudgetTracker {
+    pub fn new(limits: BudgetConfig) -> Self {
+        Self {
+            limits,
+            state: StdMutex::new(State::default()),
+            settled: Notify::new(),
+        }
+    }
+
+    /// Reserves `estimate`, waiting for running extractions to settle when it
+    /// does not fit yet. Returns `false` once the budget is spent. An
+    /// extraction estimated above what is left still runs when no other one
+    /// is, so a job can overshoot by at most one extraction.
+    pub async fn reserve(&self, estimate: Estimate) -> bool {
+        loop {
+            let notified = self.settled.notified();
+            {
+                let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+                let spent = self
+                    .limits
+                    .max_tokens
+                    .is_some_and(|max| state.stats.tokens >= max)
+                    || self
+                        .limits
+                        .max_cost_usd
+                        .is_some_and(|max| state.stats.cost_usd >= max);
+                if spent {
+                    state.stats.skipped_extractions += 1;
+                    return false;
+                }
+
+                let fits = self.limits.max_tokens.is_none_or(|max| {
+                    state.stats.tokens + state.reserved_tokens + estimate.tokens <= max
+                }) && self.limits.max_cost_usd.is_none_or(|max| {
+                    state.stats.cost_usd + state.reserved_cost_usd + estimate.cost_usd <= max
+                });
+                if fits || state.in_flight == 0 {
+                    state.reserved_tokens += estimate.tokens;
+                    state.reserved_cost_usd += estimate.cost_usd;
+                    state.in_flight += 1;
+                    return true;
+                }
+            }
+            notified.await;
+        }
+    }
+
+    /// Releases the reservation for `estimate` and records what was actually
+    /// used, if the extraction got that far.
+    pub fn settle(&self, estimate: Estimate, usage: Option<&UsageMetadata>) {
+        {
+            let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+            state.reserved_tokens -= estimate.tokens;
+            state.reserved_cost_usd = (state.reserved_cost_usd - estimate.cost_usd).max(0.0);
+            state.in_flight -= 1;
+            if let Some(usage) = usage {
+                state.stats.tokens += usage.input_tokens + usage.output_tokens;
+                state.stats.cost_usd += usage.total_cost;
+            }
+        }
+        self.settled.notify_waiters();
+    }
+
+    pub fn stats(&self) -> BudgetStats {
+        self.state
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .stats
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::*;
+
+    fn usage(estimate: Estimate) -> UsageMetadata {
+        UsageMetadata {
+            input_tokens: estimate.tokens,
+            output_tokens: 0,
+            total_cost: estimate.cost_usd,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn concurrent_extractions_stay_within_the_budget() {
+        let tracker = Arc::new(BudgetTracker::new(BudgetConfig {
+            max_cost_usd: Some(1.0),
+            max_tokens: None,
+        }));
+        let estimate = Estimate {
+            tokens: 100,
+            cost_usd: 0.25,
+        };
+
+        let extractions = (0..20).map(|_| {
+            let tracker = tracker.clone();
+            tokio::spawn(async move {
+                if !tracker.reserve(estimate).await {
+                    return false;
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                tracker.settle(estimate, Some(&usage(estimate)));
+                true
+            })
+        });
+        let mut extracted = 0;
+        for extraction in extractions.collect::<Vec<_>>() {
+            extracted += extraction.await.unwrap() as usize;
+        }
+
+        let stats = tracker.stats();
+        assert_eq!((extracted, stats.skipped_extractions), (4, 16));
+        assert!(stats.cost_usd <= 1.0);
+    }
+
+    #[tokio::test]
+    async fn waits_for_running_extractions_to_settle() {
+        let tracker = Arc::new(BudgetTracker::new(BudgetConfig {
+            max_cost_usd: None,
+            max_tokens: Some(1_000),
+        }));
+        let estimate = Estimate {
+            tokens: 600,
+            cost_usd: 0.0,
+        };
+
+        assert!(tracker.reserve(estimate).await);
+        let waiting = tokio::spawn({
+            let tracker = tracker.clone();
+            async move { tracker.reserve(estimate).await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        // Only a third of the estimate was used, which leaves room for another.
+        let used = UsageMetadata {
+            input_tokens: 200,
+            output_tokens: 0,
+            total_cost: 0.0,
+        };
+        tracker.settle(estimate, Some(&used));
+        assert!(waiting.await.unwrap());
+        assert_eq!(tracker.stats().tokens, 200);
+    }
+}
@@ -0,0 +1,18 @@
+use async_trait::async_trait;
+
+use crate::error::AppError;
+
+#[derive(Debug)]
+pub struct FetchedPage {
+    pub url: String,
+    pub body: String,
+    /// Status of the response; `304` when a cached page was revalidated.
+    pub status: u16,
+    /// Whether the body came from the HTTP cache rather than the network.
+    pub from_cache: bool,
+}
+
+#[async_trait]
+pub trait Fetcher: Send + Sync {
+    async fn fetch(&self, url: &str) -> Result<FetchedPage, AppError>;
+}
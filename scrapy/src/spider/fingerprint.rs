@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use scraper::Html;
+use sha2::{Digest, Sha256};
+
+use crate::models::{DedupConfig, DedupDecision, DedupStats, DuplicateKind};
+
+const SHINGLE_SIZE: usize = 3;
+const HIDDEN_ELEMENTS: [&str; 4] = ["script", "style", "noscript", "template"];
+
+/// Fingerprint of a page's visible text: an exact hash of the normalized
+/// words plus a 64-bit SimHash over word shingles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fingerprint {
+    exact: u64,
+    simhash: u64,
+}
+
+impl Fingerprint {
+    /// Returns `None` for pages without any visible text, which would
+    /// otherwise all collapse into a single duplicate.
+    pub fn of(document: &Html) -> Option<Self> {
+        let mut words = Vec::new();
+        for node in document.root_element().descendants() {
+            let Some(text) = node.value().as_text() else {
+                continue;
+            };
+            let hidden = node
+                .parent()
+                .and_then(|parent| parent.value().as_element())
+                .is_some_and(|parent| HIDDEN_ELEMENTS.contains(&parent.name()));
+            if !hidden {
+                words.extend(text.split_whitespace().map(str::to_lowercase));
+            }
+        }
+
+        if words.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            exact: hash(&words),
+            simhash: simhash(&words),
+        })
+    }
+
+    pub fn content_hash(&self) -> String {
+        format!("{:016x}", self.exact)
+    }
+
+    fn distance(&self, other: &Self) -> u32 {
+        (self.simhash ^ other.simhash).count_ones()
+    }
+}
+
+/// First 64 bits of the SHA-256 of `words`. Content hashes are stored with
+/// job runs, so they must not change between builds.
+fn hash(words: &[String]) -> u64 {
+    let mut hasher = Sha256::new();
+    for word in words {
+        hasher.update((word.len() as u64).to_le_bytes());
+        hasher.update(word.as_bytes());
+    }
+    let digest = hasher.finalize();
+    u64::from_be_bytes(
+        digest[..8]
+            .try_into()
+            .expect("SHA-256 digests are 32 bytes"),
+    )
+}
+
+fn simhash(words: &[String]) -> u64 {
+    let mut weights = [0i64; 64];
+    for shingle in words.windows(SHINGLE_SIZE.min(words.len())) {
+        let hash = hash(shingle);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if hash >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |simhash, (bit, _)| simhash | 1 << bit)
+}
+
+/// Fingerprints of the pages processed so far in a job.
+///
+/// SimHashes are split into `max_distance + 1` bands. Two hashes within
+/// `max_distance` bits of each other agree on at least one whole band, so only
+/// pages sharing a band are compared.
+pub struct DuplicateIndex {
+    config: DedupConfig,
+    pages: Vec<(Fingerprint, String)>,
+    exact: HashMap<u64, usize>,
+    bands: HashMap<(usize, u64), Vec<usize>>,
+    stats: DedupStats,
+}
+
+impl DuplicateIndex {
+    pub fn new(config: DedupConfig) -> Self {
+        Self {
+            config,
+            pages: Vec::new(),
+            exact: HashMap::new(),
+            bands: HashMap::new(),
+            stats: DedupStats::default(),
+        }
+    }
+
+    /// Records `url` as a new page, or returns the decision linking it to the
+    /// page it duplicates.
+    pub fn check(&mut self, url: &str, fingerprint: Fingerprint) -> Option<DedupDecision> {
+        let exact = self
+            .exact
+            .get(&fingerprint.exact)
+            .map(|&page| (&self.pages[page].1, DuplicateKind::Exact, 0));
+        let near = || {
+            self.candidates(&fingerprint)
+                .into_iter()
+                .map(|page| &self.pages[page])
+                .map(|(page, original)| (original, page.distance(&fingerprint)))
+                .filter(|(_, distance)| *distance <= self.config.max_distance)
+                .min_by_key(|(_, distance)| *distance)
+                .map(|(original, distance)| (original, DuplicateKind::Near, distance))
+        };
+
+        let duplicate = match exact {
+            Some(exact) => Some(exact),
+            None if self.config.near_duplicates => near(),
+            None => None,
+        };
+
+        let Some((original, kind, distance)) = duplicate else {
+            self.insert(url, fingerprint);
+            self.stats.unique += 1;
+            return None;
+        };
+
+        let decision = DedupDecision {
+            url: url.to_string(),
+            duplicate_of: original.clone(),
+            kind,
+            distance,
+        };
+        match kind {
+            DuplicateKind::Exact => self.stats.exact_duplicates += 1,
+            DuplicateKind::Near => self.stats.near_duplicates += 1,
+        }
+        self.stats.decisions.push(decision.clone());
+        Some(decision)
+    }
+
+    pub fn stats(&self) -> DedupStats {
+        self.stats.clone()
+    }
+
+    fn insert(&mut self, url: &str, fingerprint: Fingerprint) {
+        let page = self.pages.len();
+        self.exact.entry(fingerprint.exact).or_insert(page);
+        for band in self.band_keys(fingerprint.simhash) {
+            self.bands.entry(band).or_default().push(page);
+        }
+        self.pages.push((fingerprint, url.to_string()));
+    }
+
+    /// Pages that may be within `max_distance` of `fingerprint`, in the order
+    /// they were recorded.
+    fn candidates(&self, fingerprint: &Fingerprint) -> Vec<usize> {
+        // Any two 64-bit hashes are at most 64 bits apart.
+        if self.config.max_distance >= u64::BITS {
+            return (0..self.pages.len()).collect();
+        }
+        let mut pages: Vec<usize> = self
+            .band_keys(fingerprint.simhash)
+            .filter_map(|band| self.bands.get(&band))
+            .flatten()
+            .copied()
+            .collect();
+        pages.sort_unstable();
+        pages.dedup();
+        pages
+    }
+
+    fn band_keys(&self, simhash: u64) -> impl Iterator<Item = (usize, u64)> {
+        let count = (self.config.max_distance + 1).min(u64::BITS) as usize;
+        (0..count).map(move |band| {
+            let start = band * 64 / count;
+            let width = (band + 1) * 64 / count - start;
+            let mask = u64::MAX >> (64 - width);
+            (band, simhash >> start & mask)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE: &str = "Rust is a general purpose programming language emphasizing \
+        performance, type safety and concurrency. It enforces memory safety without a \
+        garbage collector, using a borrow checker to validate references at compile time. \
+        Rust was influenced by ideas from functional programming, including immutability, \
+        higher order functions and algebraic data types. Software developer Graydon Hoare \
+        created Rust as a personal project while working at Mozilla Research in 2006. \
+        Mozilla officially sponsored the project in 2009, and the first stable release, \
+        Rust 1.0, was published in May 2015. Following a layoff of Mozilla employees in \
+        August 2020, multiple other companies joined Mozilla in sponsoring Rust through \
+        the creation of the Rust Foundation in February 2021. The language has been noted \
+        for its adoption in many software projects, especially web services and system \
+        software, and has been studied academically for its safety guarantees.";
+
+    fn fingerprint(html: &str) -> Fingerprint {
+        Fingerprint::of(&Html::parse_document(html)).unwrap()
+    }
+
+    #[test]
+    fn ignores_markup_and_hidden_text() {
+        let page = fingerprint(&format!("<main><h1>Rust</h1><p>{}</p></main>", ARTICLE));
+        let print_view = fingerprint(&format!(
+            "<div>Rust</div>\n<div>{}</div><script>track('print')</script>",
+            ARTICLE.to_uppercase()
+        ));
+
+        assert_eq!(page, print_view);
+        // Stored with job runs, so the hash must be the same in every build.
+        assert_eq!(
+            fingerprint("<p>Hello, world</p>").content_hash(),
+            "cd0752c1af0b8ca1"
+        );
+        assert!(Fingerprint::of(&Html::parse_document("<script>app()</script>")).is_none());
+    }
+
+    #[test]
+    fn links_duplicates_to_the_first_page() {
+        let mut index = DuplicateIndex::new(DedupConfig::default());
+        let original = fingerprint(&format!("<p>{}</p><p>Sort: newest</p>", ARTICLE));
+        let resorted = fingerprint(&format!("<p>{}</p><p>Sort: oldest</p>", ARTICLE));
+        let other = fingerprint("<p>A completely different page about cooking pasta at home.</p>");
+
+        assert_eq!(index.check("https://x.com/a", original), None);
+        assert_eq!(index.check("https://x.com/b", other), None);
+
+        let exact = index.check("https://x.com/a?print=1", original).unwrap();
+        assert_eq!(exact.kind, DuplicateKind::Exact);
+        assert_eq!(exact.duplicate_of, "https://x.com/a");
+
+        let near = index.check("https://x.com/a?sort=old", resorted).unwrap();
+        assert_eq!(near.kind, DuplicateKind::Near);
+        assert_eq!(near.duplicate_of, "https://x.com/a");
+
+        let stats = index.stats();
+        assert_eq!(
+            (stats.unique, stats.exact_duplicates, stats.near_duplicates),
+            (2, 1, 1)
+        );
+    }
+
+    #[test]
+    fn finds_near_duplicates_in_any_band() {
+        let config = DedupConfig::default();
+        let original = Fingerprint {
+            exact: 1,
+            simhash: 0,
+        };
+        let mut index = DuplicateIndex::new(config);
+        assert_eq!(index.check("https://x.com/a", original), None);
+
+        // Flip `max_distance` bits spread over different bands.
+        let spread = (0..config.max_distance).fold(0u64, |hash, bit| hash | 1 << (bit * 21));
+        let near = index.check(
+            "https://x.com/b",
+            Fingerprint {
+                exact: 2,
+                simhash: spread,
+            },
+        );
+        assert_eq!(near.unwrap().distance, config.max_distance);
+
+        let far = Fingerprint {
+            exact: 3,
+            simhash: u64::MAX >> (64 - config.max_distance - 1),
+        };
+        assert_eq!(index.check("https://x.com/c", far), None);
+    }
+}
@@ -0,0 +1,585 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex as StdMutex,
+    },
+    time::Instant,
+};
+
+use async_trait::async_trait;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use tokio::sync::{mpsc, Mutex};
+use url::Url;
+
+use crate::{
+    cache::DiskCache,
+    crawler::{Canonicalizer, FetchError},
+    error::AppError,
+    jobs,
+    metrics::{self, Metrics},
+    models::{
+        AiScrapingResult, BudgetStats, CacheStats, DedupStats, FetchConfig, FetcherConfig, JobRun,
+        ScrapeParams,
+    },
+    outbound::OutboundGuard,
+    services::{AIService, GeminiAIProvider},
+};
+
+use browser::BrowserFetcher;
+use budget::{BudgetTracker, Estimate};
+use fetcher::{FetchedPage, Fetcher};
+use fingerprint::{DuplicateIndex, Fingerprint};
+use http::HttpCache;
+pub use http::HttpFetcher;
+use session::Session;
+
+mod browser;
+mod budget;
+mod fetcher;
+mod fingerprint;
+mod http;
+mod session;
+
+#[async_trait]
+pub trait Spider: Send + Sync {
+    type Item: Serialize;
+    type Error;
+
+    fn name(&self) -> String;
+    fn start_urls(&self) -> Vec<String>;
+    /// Fetcher for sitemap and feed seeds, sending the same headers, user
+    /// agent, proxy and credentials as the spider's own requests.
+    fn seed_fetcher(&self) -> &HttpFetcher;
+    async fn scrape(&self, url: String) -> Result<(Vec<Self::Item>, Vec<String>), Self::Error>;
+    async fn process(&self, item: Self::Item) -> Result<(), Self::Error>;
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageContent {
+    pub url: String,
+    pub html: String,
+    pub input_rows: Vec<u64>,
+}
+
+/// What a crawl observed about a page, used to build the next job snapshot.
+#[derive(Debug, Clone)]
+pub struct PageState {
+    pub content_hash: Option<String>,
+    pub unchanged: bool,
+}
+
+pub struct GenericSpider {
+    http: Arc<HttpFetcher>,
+    fetcher: Arc<dyn Fetcher>,
+    session: Option<Session>,
+    selectors: Vec<Selector>,
+    ai_service: Arc<AIService<GeminiAIProvider>>,
+    scrape_params: ScrapeParams,
+    canonicalizer: Canonicalizer,
+    input_rows: HashMap<String, Vec<u64>>,
+    processed: StdMutex<HashSet<String>>,
+    /// Input rows of URLs that resolved to an already scraped canonical page.
+    collapsed_rows: StdMutex<HashMap<String, Vec<u64>>>,
+    duplicates: StdMutex<DuplicateIndex>,
+    duplicate_of: StdMutex<HashMap<String, String>>,
+    previous_run: Option<JobRun>,
+    pages: StdMutex<BTreeMap<String, PageState>>,
+    item_sink: Option<mpsc::UnboundedSender<Vec<serde_json::Value>>>,
+    ai_cache_hits: AtomicU64,
+    ai_cache_misses: AtomicU64,
+    budget: BudgetTracker,
+    result: Arc<Mutex<Vec<AiScrapingResult>>>,
+    metrics: Arc<Metrics>,
+}
+
+impl GenericSpider {
+    pub fn new(
+        selectors: Vec<&str>,
+        ai_service: Arc<AIService<GeminiAIProvider>>,
+        scrape_params: ScrapeParams,
+        default_fetch_config: &FetchConfig,
+        http_cache: Option<Arc<DiskCache>>,
+        guard: Arc<OutboundGuard>,
+    ) -> Result<Self, AppError> {
+        let session = scrape_params
+            .session
+            .clone()
+            .map(Session::new)
+            .transpose()?;
+
+        let mut fetch_config = match &scrape_params.fetch {
+            Some(overrides) => default_fetch_config.with_overrides(overrides),
+            None => default_fetch_config.clone(),
+        };
+        fetch_config.persist_cookies |= session.is_some();
+        let canonicalizer = Canonicalizer::new(scrape_params.canonical.clone());
+
+        // Authenticated pages are user specific, so they never go through the cache.
+        let http_cache = http_cache.filter(|_| session.is_none()).map(|store| {
+            HttpCache::new(store, canonicalizer.clone(), scrape_params.bypass_cache)
+                .with_scope(scrape_params.owner.as_deref(), &fetch_config)
+        });
+        let http = Arc::new(
+            HttpFetcher::new(&fetch_config, guard.clone())?
+                .with_auth(
+                    session
+                        .as_ref()
+                        .map(Session::http_auth)
+                        .transpose()?
+                        .flatten(),
+                )
+                .with_cache(http_cache),
+        );
+
+        let fetcher: Arc<dyn Fetcher> = match &scrape_params.fetcher {
+            FetcherConfig::Http => http.clone(),
+            FetcherConfig::Browser(_) if session.is_some() => {
+                return Err(AppError::Session(
+                    "Login sessions are only supported with the HTTP fetcher".to_string(),
+                ))
+            }
+            FetcherConfig::Browser(options) => Arc::new(
+                BrowserFetcher::new(options.clone(), guard).with_fetch_config(&fetch_config),
+            ),
+        };
+
+        let selectors = selectors
+            .into_iter()
+            .map(|s| Selector::parse(s).unwrap())
+            .collect();
+
+        let duplicates = StdMutex::new(DuplicateIndex::new(scrape_params.dedup));
+        let mut input_rows = HashMap::<String, Vec<u64>>::new();
+        for (url, row) in scrape_params.start_urls() {
+            let key = canonicalizer.key(&url).unwrap_or(url);
+            input_rows.entry(key).or_default().extend(row);
+        }
+
+        Ok(Self {
+            http,
+            fetcher,
+            session,
+            selectors,
+            ai_service,
+            budget: BudgetTracker::new(scrape_params.budget),
+            scrape_params,
+            canonicalizer,
+            input_rows,
+            processed: StdMutex::new(HashSet::new()),
+            collapsed_rows: StdMutex::new(HashMap::new()),
+            duplicates,
+            duplicate_of: StdMutex::new(HashMap::new()),
+            previous_run: None,
+            pages: StdMutex::new(BTreeMap::new()),
+            item_sink: None,
+            ai_cache_hits: AtomicU64::new(0),
+            ai_cache_misses: AtomicU64::new(0),
+            result: Arc::new(Mutex::new(vec![])),
+            metrics: Arc::new(Metrics::new()),
+        })
+    }
+
+    fn build_prompt(&self, html: &str) -> String {
+        let mut prompt = format!(
+            "HTML Content: {}\n\nExtract the following information: {:?}",
+            html, self.scrape_params.tags
+        );
+        if let Some(schema) = &self.scrape_params.schema {
+            prompt.push_str(&format!(
+                "\n\nReturn records that match this JSON schema: {}",
+                schema
+            ));
+        }
+        prompt
+    }
+
+    fn build_system_prompt(&self) -> String {
+        "You are an AI assistant specialized in web scraping. Extract the requested information from the provided HTML content and return it as a JSON array or object.".to_string()
+    }
+
+    /// Fetches `url` with the configured fetcher and records the outcome.
+    async fn fetch_page(&self, url: &str) -> Result<FetchedPage, AppError> {
+        let fetcher = match self.scrape_params.fetcher {
+            FetcherConfig::Http => "http",
+            FetcherConfig::Browser(_) => "browser",
+        };
+        let started = Instant::now();
+        let result = self.fetcher.fetch(url).await;
+        let (status, bytes) = match &result {
+            // Fresh cache hits make no request; they are counted as cache lookups.
+            Ok(page) if page.from_cache && page.status != 304 => return result,
+            Ok(page) if page.from_cache => (metrics::status_class(page.status), 0),
+            Ok(page) => (metrics::status_class(page.status), page.body.len()),
+            Err(err) => (err.http_status().map_or("error", metrics::status_class), 0),
+        };
+        self.metrics
+            .record_fetch(fetcher, status, bytes, started.elapsed());
+        result
+    }
+
+    async fn fetch(&self, url: &str) -> Result<String, AppError> {
+        let Some(session) = &self.session else {
+            return Ok(self.fetch_page(url).await?.body);
+        };
+
+        let generation = session.ensure_logged_in(&self.http).await?;
+        let page = self.fetch_page(url).await?;
+        if !session.is_logged_out(&page) {
+            return Ok(page.body);
+        }
+
+        session.reauthenticate(&self.http, generation).await?;
+        let page = self.fetch_page(url).await?;
+        if session.is_logged_out(&page) {
+            return Err(AppError::Session(format!(
+                "Still logged out after re-authenticating while fetching {}",
+                url
+            )));
+        }
+        Ok(page.body)
+    }
+
+    /// Canonical URL of a fetched page, preferring its `<link rel=canonical>`.
+    fn canonical_url(&self, url: &str, document: &Html) -> String {
+        let Some(fetched) = self.canonicalizer.canonicalize(url) else {
+            return url.to_string();
+        };
+
+        if self.canonicalizer.honors_canonical_tag() {
+            let selector = Selector::parse("link[rel~=canonical][href]").unwrap();
+            let declared = document
+                .select(&selector)
+                .next()
+                .and_then(|element| fetched.join(element.value().attr("href")?).ok())
+                .and_then(|declared| self.canonicalizer.canonicalize(declared.as_str()));
+            if let Some(declared) = declared {
+                return declared.to_string();
+            }
+        }
+
+        fetched.to_string()
+    }
+
+    fn rows_for(&self, urls: &[&str]) -> Vec<u64> {
+        let mut rows: Vec<u64> = urls
+            .iter()
+            .filter_map(|url| self.canonicalizer.key(url))
+            .filter_map(|key| self.input_rows.get(&key))
+            .flatten()
+            .copied()
+            .collect();
+        rows.sort_unstable();
+        rows.dedup();
+        rows
+    }
+
+    fn extract_links(page_url: &str, document: &Html) -> Vec<String> {
+        let Ok(base) = Url::parse(page_url) else {
+            return Vec::new();
+        };
+        let selector = Selector::parse("a[href]").unwrap();
+
+        document
+            .select(&selector)
+            .filter_map(|element| base.join(element.value().attr("href")?).ok())
+            .filter(|link| {
+                matches!(link.scheme(), "http" | "https") && link.host_str() == base.host_str()
+            })
+            .map(|mut link| {
+                link.set_fragment(None);
+                link.to_string()
+            })
+            .collect()
+    }
+
+    /// Enables incremental mode: pages whose content hash matches `run` skip
+    /// AI extraction.
+    pub fn with_previous_run(mut self, run: Option<JobRun>) -> Self {
+        self.previous_run = run;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Sends the records of every extraction to `sink` as they are produced.
+    pub fn with_item_sink(
+        mut self,
+        sink: Option<mpsc::UnboundedSender<Vec<serde_json::Value>>>,
+    ) -> Self {
+        self.item_sink = sink;
+        self
+    }
+
+    pub fn page_states(&self) -> BTreeMap<String, PageState> {
+        self.pages
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    pub fn dedup_stats(&self) -> DedupStats {
+        self.duplicates
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .stats()
+    }
+
+    /// Page that `url` was skipped in favour of, if any.
+    pub fn duplicate_of(&self, url: &str) -> Option<String> {
+        self.duplicate_of
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(url)
+            .cloned()
+    }
+
+    fn record_duplicate(&self, url: &str, original: &str) {
+        self.duplicate_of
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(url.to_string(), original.to_string());
+    }
+
+    pub fn budget_stats(&self) -> BudgetStats {
+        self.budget.stats()
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        let (http_hits, http_revalidated, http_misses) =
+            self.http.cache().map(HttpCache::stats).unwrap_or_default();
+        CacheStats {
+            http_hits,
+            http_revalidated,
+            http_misses,
+            ai_hits: self.ai_cache_hits.load(Ordering::Relaxed),
+            ai_misses: self.ai_cache_misses.load(Ordering::Relaxed),
+        }
+    }
+
+    pub async fn get_results(&self) -> Vec<AiScrapingResult> {
+        let mut results = self.result.lock().await.clone();
+        let collapsed = self
+            .collapsed_rows
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        merge_collapsed_rows(&mut results, &collapsed);
+        results
+    }
+}
+
+#[async_trait]
+impl Spider for GenericSpider {
+    type Item = PageContent;
+    type Error = AppError;
+
+    fn name(&self) -> String {
+        String::from("generic")
+    }
+
+    fn start_urls(&self) -> Vec<String> {
+        self.scrape_params
+            .start_urls()
+            .into_iter()
+            .map(|(url, _)| url)
+            .collect()
+    }
+
+    fn seed_fetcher(&self) -> &HttpFetcher {
+        &self.http
+    }
+
+    async fn scrape(&self, url: String) -> Result<(Vec<Self::Item>, Vec<String>), Self::Error> {
+        let html = self.fetch(&url).await?;
+        let document = Html::parse_document(&html);
+
+        let links = if self.scrape_params.frontier.follow_links {
+            Self::extract_links(&url, &document)
+        } else {
+            Vec::new()
+        };
+
+        let page_url = self.canonical_url(&url, &document);
+        let page_key = self
+            .canonicalizer
+            .key(&page_url)
+            .unwrap_or_else(|| page_url.clone());
+        let first_visit = self
+            .processed
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(page_key);
+        if !first_visit {
+            log::info!("Skipping {} (already scraped as {})", url, page_url);
+            self.record_duplicate(&url, &page_url);
+            let rows = self.rows_for(&[&url]);
+            if !rows.is_empty() {
+                self.collapsed_rows
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .entry(page_url)
+                    .or_default()
+                    .extend(rows);
+            }
+            return Ok((Vec::new(), links));
+        }
+
+        let fingerprint = Fingerprint::of(&document);
+        let content_hash = fingerprint.as_ref().map(Fingerprint::content_hash);
+        let unchanged = jobs::is_unchanged(
+            self.previous_run.as_ref(),
+            &page_url,
+            content_hash.as_deref(),
+        );
+        self.pages
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(
+                page_url.clone(),
+                PageState {
+                    content_hash,
+                    unchanged,
+                },
+            );
+        if unchanged {
+            log::info!(
+                "Skipping AI extraction for {} (unchanged since last run)",
+                url
+            );
+            return Ok((Vec::new(), links));
+        }
+
+        if let Some(fingerprint) = fingerprint.filter(|_| self.scrape_params.dedup.enabled) {
+            let duplicate = self
+                .duplicates
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .check(&page_url, fingerprint);
+            if let Some(decision) = duplicate {
+                log::info!(
+                    "Skipping AI extraction for {} ({:?} duplicate of {})",
+                    url,
+                    decision.kind,
+                    decision.duplicate_of
+                );
+                self.record_duplicate(&url, &decision.duplicate_of);
+                return Ok((Vec::new(), links));
+            }
+        }
+
+        let input_rows = self.rows_for(&[&url, &page_url]);
+        let mut items = Vec::new();
+
+        for selector in &self.selectors {
+            for element in document.select(selector) {
+                items.push(PageContent {
+                    url: page_url.clone(),
+                    html: element.inner_html(),
+                    input_rows: input_rows.clone(),
+                });
+            }
+        }
+
+        Ok((items, links))
+    }
+
+    async fn process(&self, page: Self::Item) -> Result<(), Self::Error> {
+        if self.scrape_params.enable_scraping {
+            let system_prompt = self.build_system_prompt();
+            let user_prompt = self.build_prompt(&page.html);
+            let estimate = Estimate::of(
+                &self.scrape_params.model,
+                &format!("{}{}", system_prompt, user_prompt),
+            );
+            if !self.budget.reserve(estimate).await {
+                log::warn!("AI budget used up, not extracting {}", page.url);
+                return Ok(());
+            }
+
+            let extracted = self
+                .ai_service
+                .extract_items(&self.scrape_params, &system_prompt, &user_prompt)
+                .await;
+            self.budget.settle(
+                estimate,
+                extracted.as_ref().ok().map(|result| &result.usage_metadata),
+            );
+            let mut result = extracted?;
+            let counter = if result.cached {
+                &self.ai_cache_hits
+            } else {
+                &self.ai_cache_misses
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+
+            result.input_rows = page.input_rows;
+            result.url = Some(page.url);
+
+            let records = result.records();
+            if let Some(sink) = self.item_sink.as_ref().filter(|_| !records.is_empty()) {
+                let _ = sink.send(records);
+            }
+
+            let mut results = self.result.lock().await;
+            results.push(result);
+        }
+
+        Ok(())
+    }
+}
+
+/// Adds the input rows of collapsed URLs to the results of the canonical page
+/// they resolved to.
+fn merge_collapsed_rows(results: &mut [AiScrapingResult], collapsed: &HashMap<String, Vec<u64>>) {
+    for result in results {
+        let Some(rows) = result.url.as_ref().and_then(|url| collapsed.get(url)) else {
+            continue;
+        };
+        result.input_rows.extend(rows);
+        result.input_rows.sort_unstable();
+        result.input_rows.dedup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::models::UsageMetadata;
+
+    fn result(url: &str, input_rows: Vec<u64>) -> AiScrapingResult {
+        AiScrapingResult {
+            model: "gemini-1.5-flash-latest".to_string(),
+            start_time: Utc::now(),
+            end_time: None,
+            data: serde_json::Value::Null,
+            usage_metadata: UsageMetadata {
+                input_tokens: 0,
+                output_tokens: 0,
+                total_cost: 0.0,
+            },
+            url: Some(url.to_string()),
+            input_rows,
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn merges_rows_of_collapsed_urls_into_the_canonical_result() {
+        let mut results = vec![
+            result("https://example.com/item", vec![0]),
+            result("https://example.com/other", vec![1]),
+        ];
+        let collapsed = HashMap::from([("https://example.com/item".to_string(), vec![2, 0])]);
+
+        merge_collapsed_rows(&mut results, &collapsed);
+
+        assert_eq!(results[0].input_rows, vec![0, 2]);
+        assert_eq!(results[1].input_rows, vec![1]);
+    }
+}
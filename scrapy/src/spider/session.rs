@@ -0,0 +1,406 @@
+use std::collections::BTreeMap;
+
+use reqwest::{cookie::CookieStore, header::HeaderValue};
+use scraper::{Html, Selector};
+use tokio::sync::Mutex;
+use url::Url;
+
+use crate::{
+    error::AppError,
+    models::{SessionAuth, SessionConfig},
+};
+
+use super::{
+    fetcher::{FetchedPage, Fetcher},
+    http::{Credentials, HttpAuth, HttpFetcher},
+};
+
+pub struct Session {
+    config: SessionConfig,
+    logged_out_selector: Option<Selector>,
+    csrf_selector: Option<Selector>,
+    logins: Mutex<u64>,
+}
+
+impl Session {
+    pub fn new(config: SessionConfig) -> Result<Self, AppError> {
+        let parse = |selector: &str| {
+            Selector::parse(selector)
+                .map_err(|err| AppError::Session(format!("selector {:?}: {}", selector, err)))
+        };
+
+        let logged_out_selector = config
+            .logged_out_selector
+            .as_deref()
+            .map(parse)
+            .transpose()?;
+        let csrf_selector = match &config.auth {
+            SessionAuth::Form { csrf_selector, .. } => {
+                csrf_selector.as_deref().map(parse).transpose()?
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            config,
+            logged_out_selector,
+            csrf_selector,
+            logins: Mutex::new(0),
+        })
+    }
+
+    pub fn http_auth(&self) -> Result<Option<HttpAuth>, AppError> {
+        let (origin, credentials) = match &self.config.auth {
+            SessionAuth::Bearer { origin, token } => (origin, Credentials::Bearer(token.clone())),
+            SessionAuth::Basic {
+                origin,
+                username,
+                password,
+            } => (
+                origin,
+                Credentials::Basic(username.clone(), password.clone()),
+            ),
+            _ => return Ok(None),
+        };
+        let origin = Url::parse(origin)
+            .map_err(|err| AppError::Session(format!("origin {:?}: {}", origin, err)))?
+            .origin();
+        Ok(Some(HttpAuth {
+            origin,
+            credentials,
+        }))
+    }
+
+    pub async fn ensure_logged_in(&self, fetcher: &HttpFetcher) -> Result<u64, AppError> {
+        let mut logins = self.logins.lock().await;
+        if *logins == 0 {
+            self.login(fetcher).await?;
+            *logins += 1;
+        }
+        Ok(*logins)
+    }
+
+    pub async fn reauthenticate(&self, fetcher: &HttpFetcher, seen: u64) -> Result<(), AppError> {
+        let mut logins = self.logins.lock().await;
+        if *logins != seen {
+            return Ok(());
+        }
+
+        log::info!("Session looks logged out, re-authenticating");
+        self.login(fetcher).await?;
+        *logins += 1;
+        Ok(())
+    }
+
+    pub fn is_logged_out(&self, page: &FetchedPage) -> bool {
+        if let SessionAuth::Form { login_url, .. } = &self.config.auth {
+            if page.url.starts_with(login_url.as_str()) {
+                return true;
+            }
+        }
+
+        self.shows_logged_out_marker(page)
+    }
+
+    fn shows_logged_out_marker(&self, page: &FetchedPage) -> bool {
+        self.logged_out_selector.as_ref().is_some_and(|selector| {
+            Html::parse_document(&page.body)
+                .select(selector)
+                .next()
+                .is_some()
+        })
+    }
+
+    async fn login(&self, fetcher: &HttpFetcher) -> Result<(), AppError> {
+        match &self.config.auth {
+            SessionAuth::Form {
+                login_url,
+                form_url,
+                fields,
+                csrf_field,
+                ..
+            } => {
+                let mut fields: BTreeMap<String, String> = fields
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.expose().to_string()))
+                    .collect();
+
+                if let Some(selector) = &self.csrf_selector {
+                    let form_page = fetcher
+                        .fetch(form_url.as_deref().unwrap_or(login_url))
+                        .await?;
+                    let document = Html::parse_document(&form_page.body);
+                    let element = document.select(selector).next().ok_or_else(|| {
+                        AppError::Session("CSRF token not found on login form".to_string())
+                    })?;
+                    let token = element
+                        .value()
+                        .attr("value")
+                        .or_else(|| element.value().attr("content"))
+                        .map(str::to_string)
+                        .unwrap_or_else(|| element.text().collect::<String>().trim().to_string());
+                    let name = csrf_field
+                        .clone()
+                        .or_else(|| element.value().attr("name").map(str::to_string))
+                        .ok_or_else(|| {
+                            AppError::Session("CSRF field name is not configured".to_string())
+                        })?;
+                    fields.insert(name, token);
+                }
+
+                let page = fetcher.post_form(login_url, &fields).await?;
+                if self.shows_logged_out_marker(&page) {
+                    return Err(AppError::Session(format!(
+                        "Login to {} was rejected",
+                        login_url
+                    )));
+                }
+                Ok(())
+            }
+            SessionAuth::Cookies { url, cookies } => {
+                let url = Url::parse(url)
+                    .map_err(|err| AppError::Session(format!("cookie url {:?}: {}", url, err)))?;
+                let jar = fetcher.cookies().ok_or_else(|| {
+                    AppError::Session("Cookie jar is disabled for this job".to_string())
+                })?;
+                let headers: Vec<_> = cookies
+                    .iter()
+                    .filter_map(|cookie| HeaderValue::from_str(cookie.expose()).ok())
+                    .collect();
+                jar.set_cookies(&mut headers.iter(), &url);
+                Ok(())
+            }
+            SessionAuth::Bearer { .. } | SessionAuth::Basic { .. } => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    use serde_json::json;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::{
+        models::{FetchConfig, ScrapeParams},
+        outbound::OutboundGuard,
+        services::{AIService, GeminiAIProvider},
+        spider::GenericSpider,
+    };
+
+    const LOGGED_OUT: &str = "<form class=\"login\"></form>";
+
+    /// A site with a CSRF-protected login form and one private page.
+    #[derive(Default)]
+    struct Site {
+        logins: usize,
+        session: Option<String>,
+        /// Accepts logins but never keeps the session, as if it expired at once.
+        drop_sessions: bool,
+    }
+
+    impl Site {
+        fn respond(&mut self, head: &str, body: &str) -> (String, Option<String>) {
+            let csrf = format!("csrf-{}", self.logins);
+            if head.starts_with("GET /login") {
+                let form = format!("<input name=\"csrf\" value=\"{}\">", csrf);
+                return (form, None);
+            }
+            if head.starts_with("POST /login") {
+                let valid = body.contains("user=alice")
+                    && body.contains("password=hunter2")
+                    && body.contains(&format!("csrf={}", csrf));
+                if !valid {
+                    return (LOGGED_OUT.to_string(), None);
+                }
+                self.logins += 1;
+                let session = format!("s{}", self.logins);
+                self.session = (!self.drop_sessions).then(|| session.clone());
+                return ("<p>Welcome</p>".to_string(), Some(session));
+            }
+            let signed_in = self
+                .session
+                .as_ref()
+                .is_some_and(|session| head.contains(&format!("sid={}", session)));
+            if signed_in {
+                ("<p class=\"secret\">data</p>".to_string(), None)
+            } else {
+                (LOGGED_OUT.to_string(), None)
+            }
+        }
+    }
+
+    async fn read_request(socket: &mut TcpStream) -> (String, String) {
+        let mut data = Vec::new();
+        let mut buffer = [0u8; 4096];
+        loop {
+            let read = socket.read(&mut buffer).await.unwrap_or(0);
+            data.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&data).to_string();
+            if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                let length = head
+                    .lines()
+                    .find_map(|line| {
+                        let (name, value) = line.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                if body.len() >= length || read == 0 {
+                    return (head.to_string(), body.to_string());
+                }
+            } else if read == 0 {
+                return (text, String::new());
+            }
+        }
+    }
+
+    async fn serve(site: Arc<StdMutex<Site>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let site = site.clone();
+                tokio::spawn(async move {
+                    let (head, body) = read_request(&mut socket).await;
+                    let (page, cookie) = site.lock().unwrap().respond(&head, &body);
+                    let cookie = cookie
+                        .map(|session| format!("Set-Cookie: sid={}; Path=/\r\n", session))
+                        .unwrap_or_default();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        cookie,
+                        page.len(),
+                        page
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    fn spider(base: &str, password: &str) -> GenericSpider {
+        let params: ScrapeParams = serde_json::from_value(json!({
+            "model": "gemini-1.5-flash-latest",
+            "url": format!("{}/private", base),
+            "enableScraping": false,
+            "tags": [],
+            "enablePagination": false,
+            "session": {
+                "auth": {
+                    "type": "form",
+                    "loginUrl": format!("{}/login", base),
+                    "fields": { "user": "alice", "password": password },
+                    "csrfSelector": "input[name=csrf]",
+                },
+                "loggedOutSelector": "form.login",
+            },
+        }))
+        .unwrap();
+        GenericSpider::new(
+            vec!["body"],
+            Arc::new(AIService::new(GeminiAIProvider::new())),
+            params,
+            &FetchConfig::default(),
+            None,
+            Arc::new(OutboundGuard::unrestricted()),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn logs_in_and_reauthenticates_once_when_logged_out() {
+        let site = Arc::new(StdMutex::new(Site::default()));
+        let base = serve(site.clone()).await;
+        let spider = spider(&base, "hunter2");
+        let private = format!("{}/private", base);
+
+        let body = spider.fetch(&private).await.unwrap();
+        assert!(body.contains("secret"), "{}", body);
+        assert_eq!(site.lock().unwrap().logins, 1);
+
+        // The session expires: the logged-out marker triggers one new login.
+        site.lock().unwrap().session = None;
+        let body = spider.fetch(&private).await.unwrap();
+        assert!(body.contains("secret"), "{}", body);
+        assert_eq!(site.lock().unwrap().logins, 2);
+
+        // Still logged out after logging in again: the fetch fails.
+        {
+            let mut site = site.lock().unwrap();
+            site.session = None;
+            site.drop_sessions = true;
+        }
+        let err = spider.fetch(&private).await.unwrap_err();
+        assert!(
+            matches!(&err, AppError::Session(message) if message.contains("Still logged out")),
+            "{}",
+            err
+        );
+        assert_eq!(site.lock().unwrap().logins, 3);
+    }
+
+    #[test]
+    fn keeps_credentials_out_of_debug_output() {
+        let secrets = [
+            "bearer-token",
+            "basic-password",
+            "form-password",
+            "sid=cookie",
+        ];
+        for auth in [
+            json!({ "type": "bearer", "origin": "https://example.com", "token": secrets[0] }),
+            json!({
+                "type": "basic",
+                "origin": "https://example.com",
+                "username": "alice",
+                "password": secrets[1],
+            }),
+            json!({
+                "type": "form",
+                "loginUrl": "https://example.com/login",
+                "fields": { "password": secrets[2] },
+            }),
+            json!({ "type": "cookies", "url": "https://example.com", "cookies": [secrets[3]] }),
+        ] {
+            let params: ScrapeParams = serde_json::from_value(json!({
+                "model": "gemini-1.5-flash-latest",
+                "url": "https://example.com",
+                "enableScraping": true,
+                "tags": ["name"],
+                "enablePagination": false,
+                "session": { "auth": auth },
+            }))
+            .unwrap();
+
+            let debug = format!("{:?}\n{:#?}", params, params);
+            for secret in secrets {
+                assert!(!debug.contains(secret), "{} in {}", secret, debug);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_rejected_logins() {
+        let site = Arc::new(StdMutex::new(Site::default()));
+        let base = serve(site.clone()).await;
+
+        let err = spider(&base, "wrong")
+            .fetch(&format!("{}/private", base))
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(&err, AppError::Session(message) if message.contains("rejected")),
+            "{}",
+            err
+        );
+        assert_eq!(site.lock().unwrap().logins, 0);
+    }
+}
@@ -1,6 +1,14 @@
-use std::{env, path::PathBuf};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use crate::constants::PRICING_INFO;
+use chrono::{DateTime, Utc};
+
+use url::Url;
+
+use crate::{constants::PRICING_INFO, error::AppError, models::InputUrl};
 
 pub fn calculate_price(model: &str, input_tokens: u64, output_tokens: u64) -> f64 {
     let pricing_info = PRICING_INFO.get(model).unwrap();
@@ -11,6 +19,77 @@ pub fn get_all_models() -> Vec<String> {
     PRICING_INFO.keys().map(|k| k.to_string()).collect()
 }
 
+/// Directory holding `owner`'s files under `root`: `root` itself when
+/// authentication is disabled, `root/users/<owner>` otherwise.
+pub fn owner_dir(root: &Path, owner: Option<&str>) -> PathBuf {
+    match owner {
+        Some(owner) => root.join("users").join(owner),
+        None => root.to_path_buf(),
+    }
+}
+
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+/// Parses an uploaded URL list: plain text with one URL per line, or CSV/TSV.
+/// A `url` header selects the column, otherwise the first cell that looks like
+/// a URL is used. Rows are the line numbers in the uploaded file.
+pub fn parse_url_list(content: &str) -> Result<Vec<InputUrl>, AppError> {
+    let first_line = content.lines().next().unwrap_or_default();
+    let delimiter = if first_line.contains('\t') && !first_line.contains(',') {
+        b'\t'
+    } else {
+        b','
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .comment(Some(b'#'))
+        .delimiter(delimiter)
+        .from_reader(content.as_bytes());
+
+    let is_url =
+        |cell: &str| Url::parse(cell).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+
+    let mut column = None;
+    let mut urls = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let record = record?;
+        if index == 0 {
+            column = record
+                .iter()
+                .position(|cell| cell.eq_ignore_ascii_case("url"));
+            if column.is_some() {
+                continue;
+            }
+        }
+
+        let cell = match column {
+            Some(column) => record.get(column),
+            None => record.iter().find(|cell| is_url(cell)),
+        };
+        if let Some(url) = cell.filter(|cell| is_url(cell)) {
+            let row = record
+                .position()
+                .map_or(index as u64 + 1, |position| position.line());
+            urls.push(InputUrl::Row {
+                url: url.to_string(),
+                row,
+            });
+        }
+    }
+
+    Ok(urls)
+}
 
 pub fn find_static_dir() -> PathBuf {
     // 1. Try STATIC_DIR environment variable
@@ -44,3 +123,35 @@ pub fn find_static_dir() -> PathBuf {
         .expect("Failed to get current working directory")
         .join("static") // Return this path even if it doesn't exist
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_url_lists_with_line_numbers() {
+        let text = "https://example.com/a\n\n# comment\nnot a url\nhttps://example.com/b\n";
+        assert_eq!(
+            parse_url_list(text).unwrap(),
+            vec![
+                InputUrl::Row {
+                    url: "https://example.com/a".to_string(),
+                    row: 1
+                },
+                InputUrl::Row {
+                    url: "https://example.com/b".to_string(),
+                    row: 5
+                },
+            ]
+        );
+
+        let csv = "sku,URL,name\n42,https://example.com/p/42,\"Widget, large\"\n43,,Gadget\n";
+        assert_eq!(
+            parse_url_list(csv).unwrap(),
+            vec![InputUrl::Row {
+                url: "https://example.com/p/42".to_string(),
+                row: 2
+            }]
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use std::path::PathBuf;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{fs, sync::Mutex};
+
+use crate::{
+    auth::User,
+    error::AppError,
+    models::{Credential, CredentialRequest},
+};
+
+pub use secret::Secret;
+
+mod secret;
+
+/// Length of the master key, before base64 encoding.
+pub const MASTER_KEY_LEN: usize = 32;
+
+/// Decodes a base64 master key, as generated by `openssl rand -base64 32`.
+pub fn parse_master_key(master_key: &Secret) -> Result<[u8; MASTER_KEY_LEN], AppError> {
+    BASE64
+        .decode(master_key.expose().trim())
+        .ok()
+        .and_then(|key| key.try_into().ok())
+        .ok_or_else(|| {
+            AppError::Vault(format!(
+                "the master key must be {} bytes encoded as base64",
+                MASTER_KEY_LEN
+            ))
+        })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredCredential {
+    name: String,
+    owner: Option<String>,
+    provider: String,
+    nonce: String,
+    ciphertext: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl StoredCredential {
+    fn view(&self) -> Credential {
+        Credential {
+            name: self.name.clone(),
+            owner: self.owner.clone(),
+            provider: self.provider.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+/// Binds a ciphertext to its owner and name so it cannot be moved to
+/// another entry.
+fn aad(owner: Option<&str>, name: &str) -> String {
+    format!("{}/{}", owner.unwrap_or_default(), name)
+}
+
+fn validate_name(name: &str) -> Result<(), AppError> {
+    let valid = !name.is_empty()
+        && name.len() <= 128
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(AppError::Vault(format!(
+            "Invalid credential name {:?}: use letters, digits, '-', '_' or '.'",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// AI provider keys encrypted with AES-256-GCM under the server's master key
+/// and stored per owner, so crawl requests can refer to them by name.
+pub struct CredentialVault {
+    path: PathBuf,
+    key: Option<LessSafeKey>,
+    rng: SystemRandom,
+    entries: Mutex<Vec<StoredCredential>>,
+}
+
+impl CredentialVault {
+    /// Loads the vault from `path`. Without a master key, stored credentials
+    /// are listed but cannot be used or added.
+    pub fn open(path: impl Into<PathBuf>, master_key: Option<&Secret>) -> Result<Self, AppError> {
+        let path = path.into();
+        let key = master_key
+            .map(|master_key| {
+                let key = parse_master_key(master_key)?;
+                let key = UnboundKey::new(&AES_256_GCM, &key)
+                    .map_err(|_| AppError::Vault("invalid master key".to_string()))?;
+                Ok::<_, AppError>(LessSafeKey::new(key))
+            })
+            .transpose()?;
+        let entries = match std::fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content)?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self {
+            path,
+            key,
+            rng: SystemRandom::new(),
+            entries: Mutex::new(entries),
+        })
+    }
+
+    fn key(&self) -> Result<&LessSafeKey, AppError> {
+        self.key
+            .as_ref()
+            .ok_or_else(|| AppError::Vault("no vault.master_key is configured".to_string()))
+    }
+
+    /// Credentials visible to `user`, without their values.
+    pub async fn list(&self, user: &User) -> Vec<Credential> {
+        let entries = self.entries.lock().await;
+        entries
+            .iter()
+            .filter(|entry| user.can_access(entry.owner.as_deref()))
+            .map(StoredCredential::view)
+            .collect()
+    }
+
+    /// Encrypts and stores a credential, replacing one with the same name.
+    pub async fn put(
+        &self,
+        owner: Option<&str>,
+        name: &str,
+        request: CredentialRequest,
+    ) -> Result<Credential, AppError> {
+        validate_name(name)?;
+        if request.api_key.is_empty() {
+            return Err(AppError::Vault("the API key must not be empty".to_string()));
+        }
+
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| AppError::Vault("failed to generate a nonce".to_string()))?;
+        let mut ciphertext = request.api_key.expose().as_bytes().to_vec();
+        self.key()?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad(owner, name)),
+                &mut ciphertext,
+            )
+            .map_err(|_| AppError::Vault("failed to encrypt the credential".to_string()))?;
+
+        let now = Utc::now();
+        let mut entries = self.entries.lock().await;
+        let created_at = entries
+            .iter()
+            .find(|entry| entry.name == name && entry.owner.as_deref() == owner)
+            .map_or(now, |entry| entry.created_at);
+        entries.retain(|entry| !(entry.name == name && entry.owner.as_deref() == owner));
+        let stored = StoredCredential {
+            name: name.to_string(),
+            owner: owner.map(String::from),
+            provider: request.provider,
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+            created_at,
+            updated_at: now,
+        };
+        let credential = stored.view();
+        entries.push(stored);
+        self.persist(&entries).await?;
+
+        log::info!("Stored credential {:?} for {:?}", name, owner);
+        Ok(credential)
+    }
+
+    pub async fn remove(&self, owner: Option<&str>, name: &str) -> Result<bool, AppError> {
+        let mut entries = self.entries.lock().await;
+        let before = entries.len();
+        entries.retain(|entry| !(entry.name == name && entry.owner.as_deref() == owner));
+        if entries.len() == before {
+            return Ok(false);
+        }
+        self.persist(&entries).await?;
+        Ok(true)
+    }
+
+    /// Decrypts the credential `name` of `owner`.
+    pub async fn resolve(&self, owner: Option<&str>, name: &str) -> Result<Secret, AppError> {
+        let entries = self.entries.lock().await;
+        let entry = entries
+            .iter()
+            .find(|entry| entry.name == name && entry.owner.as_deref() == owner)
+            .ok_or_else(|| AppError::Vault(format!("no credential named {:?}", name)))?;
+
+        let undecryptable = || {
+            AppError::Vault(format!(
+                "credential {:?} cannot be decrypted with the configured master key",
+                name
+            ))
+        };
+        let nonce: [u8; NONCE_LEN] = BASE64
+            .decode(&entry.nonce)
+            .ok()
+            .and_then(|nonce| nonce.try_into().ok())
+            .ok_or_else(undecryptable)?;
+        let mut ciphertext = BASE64
+            .decode(&entry.ciphertext)
+            .map_err(|_| undecryptable())?;
+        let plaintext = self
+            .key()?
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad(owner, name)),
+                &mut ciphertext,
+            )
+            .map_err(|_| undecryptable())?;
+        let value = String::from_utf8(plaintext.to_vec()).map_err(|_| undecryptable())?;
+        Ok(Secret::new(value))
+    }
+
+    async fn persist(&self, entries: &[StoredCredential]) -> Result<(), AppError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, serde_json::to_vec_pretty(entries)?).await?;
+        fs::rename(&temp, &self.path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    const MASTER_KEY: &str = "MDEyMzQ1Njc4OWFiY2RlZjAxMjM0NTY3ODlhYmNkZWY=";
+
+    fn temp_path() -> PathBuf {
+        std::env::temp_dir().join(format!("scrapy-vault-{}.json", Uuid::new_v4()))
+    }
+
+    fn request(api_key: &str) -> CredentialRequest {
+        CredentialRequest {
+            provider: "gemini".to_string(),
+            api_key: Secret::new(api_key),
+        }
+    }
+
+    #[tokio::test]
+    async fn stores_credentials_encrypted_per_owner() {
+        let path = temp_path();
+        let vault = CredentialVault::open(&path, Some(&Secret::new(MASTER_KEY))).unwrap();
+        vault
+            .put(Some("alice"), "gemini", request("AIza-very-secret"))
+            .await
+            .unwrap();
+
+        let stored = std::fs::read_to_string(&path).unwrap();
+        assert!(!stored.contains("AIza-very-secret"));
+
+        let reopened = CredentialVault::open(&path, Some(&Secret::new(MASTER_KEY))).unwrap();
+        let secret = reopened.resolve(Some("alice"), "gemini").await.unwrap();
+        assert_eq!(secret.expose(), "AIza-very-secret");
+        assert_eq!(format!("{:?}", secret), "Secret([redacted])");
+        assert!(reopened.resolve(Some("bob"), "gemini").await.is_err());
+        assert!(reopened.resolve(None, "gemini").await.is_err());
+
+        assert!(reopened.remove(Some("alice"), "gemini").await.unwrap());
+        assert!(reopened.list(&User::anonymous()).await.is_empty());
+        std::fs::remove_file(path).ok();
+    }
+
+    #[tokio::test]
+    async fn requires_the_right_master_key() {
+        let path = temp_path();
+        let vault = CredentialVault::open(&path, Some(&Secret::new(MASTER_KEY))).unwrap();
+        vault.put(None, "gemini", request("secret")).await.unwrap();
+
+        let other_key = Secret::new(BASE64.encode([7u8; MASTER_KEY_LEN]));
+        let other = CredentialVault::open(&path, Some(&other_key)).unwrap();
+        let err = other.resolve(None, "gemini").await.unwrap_err();
+        assert!(err.to_string().contains("cannot be decrypted"), "{}", err);
+
+        let locked = CredentialVault::open(&path, None).unwrap();
+        assert_eq!(locked.list(&User::anonymous()).await.len(), 1);
+        assert!(locked.resolve(None, "gemini").await.is_err());
+
+        assert!(CredentialVault::open(&path, Some(&Secret::new("short"))).is_err());
+        std::fs::remove_file(path).ok();
+    }
+}